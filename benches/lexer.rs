@@ -0,0 +1,47 @@
+//! Times how long the lexer takes on programs of a few different sizes (run it with `cargo bench
+//! --bench lexer`). Each program is a short one repeated, so how the time grows with the size of
+//! the input can be seen as well.
+
+use pseudocompiler::lexer::lex;
+use std::time::{Duration, Instant};
+
+const PROGRAM: &str = r#"function total(values)
+    sum = 0
+    for i = 0 to values.length - 1
+        if values[i] > 0 AND values[i] != 13 then
+            sum += values[i] * 2.5
+        endif
+    next i
+    return sum
+endfunction
+scores = [1, 2, 3, -4, 13]
+print("The total is " + str(total(scores)))
+"#;
+
+/// How many times each program is lexed (the fastest of which is reported).
+const RUNS: usize = 10;
+
+fn time(source: &str) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let mut source = source.to_string();
+            let start = Instant::now();
+            lex(source.as_mut_str()).unwrap();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn main() {
+    for repeats in [10, 100, 1000] {
+        let source = PROGRAM.repeat(repeats);
+        let elapsed = time(&source);
+        println!(
+            "{:>8} bytes: {:>10.3?} ({:.1} MB/s)",
+            source.len(),
+            elapsed,
+            source.len() as f64 / elapsed.as_secs_f64() / 1e6
+        );
+    }
+}
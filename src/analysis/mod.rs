@@ -0,0 +1,71 @@
+//! Answers questions about what a program does, without running it.
+
+#[cfg(test)]
+mod unit_tests;
+
+use crate::parser::visit::{walk_expression, Visitor};
+use crate::parser::{Expression, Statement};
+
+/// The builtin functions which read input or write output. `OUTPUT` and `INPUT` are parsed as calls
+/// to `print` and `input`, so they're covered too.
+const IO_FUNCTIONS: [&str; 2] = ["print", "input"];
+
+/// Whether the program reads any input or writes any output (e.g. so that a playground can tell
+/// whether the program needs a console).
+pub fn uses_io(program: &[Statement]) -> bool {
+    let mut finder = IoFinder::default();
+    for statement in program {
+        finder.visit_statement(statement);
+    }
+    finder.found
+}
+
+/// Whether every path through the block ends in a `return` (or a `throw`), so that a function whose
+/// body is the block can't finish without returning a value.
+///
+/// Loops (other than `do ... until`, whose body always runs) might not run at all, so a `return`
+/// inside one doesn't count.
+pub fn always_returns(block: &[Statement]) -> bool {
+    block.iter().any(|statement| match statement {
+        Statement::ReturnStatement(_) | Statement::ThrowStatement(_) => true,
+        Statement::IfStatement(if_statement) => {
+            always_returns(&if_statement.case_if.block)
+                && if_statement
+                    .cases_elif
+                    .iter()
+                    .all(|case| always_returns(&case.block))
+                && if_statement
+                    .case_else
+                    .as_ref()
+                    .is_some_and(|case| always_returns(&case.block))
+        }
+        Statement::SwitchStatement(switch) => {
+            switch.cases.iter().all(|case| always_returns(&case.block))
+                && switch
+                    .default
+                    .as_ref()
+                    .is_some_and(|default| always_returns(&default.block))
+        }
+        Statement::TryStatement(try_statement) => {
+            always_returns(&try_statement.body) && always_returns(&try_statement.catch_block)
+        }
+        Statement::DoUntilStatement(do_until) => always_returns(&do_until.block),
+        _ => false,
+    })
+}
+
+#[derive(Default)]
+struct IoFinder {
+    found: bool,
+}
+
+impl Visitor for IoFinder {
+    fn visit_expression(&mut self, expression: &Expression) {
+        if let Expression::FunctionCall { name, .. } = expression {
+            if IO_FUNCTIONS.contains(&name.as_str()) {
+                self.found = true;
+            }
+        }
+        walk_expression(self, expression)
+    }
+}
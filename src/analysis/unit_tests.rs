@@ -0,0 +1,53 @@
+//! Unit tests for the analyses.
+
+use crate::analysis::{always_returns, uses_io};
+use crate::lexer::lex;
+use crate::parser::{parse, Statement};
+
+fn program_uses_io(source: &str) -> bool {
+    let mut source = source.to_string();
+    uses_io(&parse(lex(&mut source).unwrap()).unwrap())
+}
+
+#[test]
+pub fn test_pure_program_does_not_use_io() {
+    assert!(!program_uses_io(
+        "function square(x)\n    return x * x\nendfunction\ny = square(3) + 1\n"
+    ));
+}
+
+#[test]
+pub fn test_finds_io() {
+    assert!(program_uses_io("x = 1\nprint(x)\n"));
+    assert!(program_uses_io(
+        "function f(x)\n    if x == 1 then\n        OUTPUT x\n    endif\n    return x\nendfunction\n"
+    ));
+    assert!(program_uses_io("INPUT name\n"));
+}
+
+/// Whether the body of the function which `source` defines always returns.
+fn function_always_returns(source: &str) -> bool {
+    let mut source = source.to_string();
+    match &parse(lex(&mut source).unwrap()).unwrap()[..] {
+        [Statement::FunctionDefinition(function)] => always_returns(&function.block),
+        other => panic!("expected a function definition, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn test_finds_whether_functions_always_return() {
+    assert!(function_always_returns(
+        "function f(x)\n    print(x)\n    return x\nendfunction\n"
+    ));
+    assert!(function_always_returns(
+        "function f(x)\n    switch x:\n        case 1:\n            return 1\n        default:\n            throw \"no\"\n    endswitch\nendfunction\n"
+    ));
+    // an if without an else (or a switch without a default) might not return
+    assert!(!function_always_returns(
+        "function f(x)\n    if x then\n        return 1\n    endif\nendfunction\n"
+    ));
+    // and neither might a loop, which could run no times
+    assert!(!function_always_returns(
+        "function f(x)\n    while x < 3\n        return x\n    endwhile\nendfunction\n"
+    ));
+}
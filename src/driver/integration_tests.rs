@@ -0,0 +1,326 @@
+//! Tests which compile whole programs.
+
+use crate::driver::{compile, compile_check, run, CompileError, Target};
+use crate::errorfmt::{Diagnostic, Severity};
+use crate::interpreter::{Environment, RuntimeError};
+use crate::lexer::{Loc, Span};
+
+const MULTIPLY: &str = r#"
+function multiply(x, y)
+    return x * y
+endfunction
+"#;
+
+#[test]
+pub fn test_compiles_multiply_to_js() {
+    assert_eq!(
+        compile(MULTIPLY, Target::Js).unwrap(),
+        "function multiply(x, y) {\n    return x * y;\n}\n"
+    );
+}
+
+#[test]
+pub fn test_compiles_multiply_to_wasm() {
+    let output = compile(MULTIPLY, Target::Wasm).unwrap();
+    assert!(output.starts_with("(module\n"));
+    assert!(output.contains("(i32.mul (local.get $x) (local.get $y))"));
+}
+
+#[test]
+pub fn test_compiles_for_each_to_js() {
+    assert_eq!(
+        compile("for each x in arr\n    print(x)\nnext x\n", Target::Js).unwrap(),
+        "for (const x of arr) {\n    console.log(x);\n}\n"
+    );
+    assert_eq!(
+        compile("for each x in [1, 2]\n    print(x)\nnext x\n", Target::Js).unwrap(),
+        "for (const x of [1, 2]) {\n    console.log(x);\n}\n"
+    );
+}
+
+#[test]
+pub fn test_compiles_string_repetition_to_js() {
+    assert_eq!(
+        compile("line = \"-\" * 10\n", Target::Js).unwrap(),
+        "line = \"-\".repeat(10);\n"
+    );
+    assert_eq!(
+        compile("line = 3 * \"ab\"\n", Target::Js).unwrap(),
+        "line = \"ab\".repeat(3);\n"
+    );
+    // the operands don't have to be literals, as long as their types are known
+    assert_eq!(
+        compile(
+            "dash = \"-\"\nn = 4\nline = dash * (n + 1)\nline = n * dash\n",
+            Target::Js
+        )
+        .unwrap(),
+        "dash = \"-\";\nn = 4;\nline = dash.repeat(n + 1);\nline = dash.repeat(n);\n"
+    );
+    assert_eq!(
+        compile("width = 4\narea = width * 10\n", Target::Js).unwrap(),
+        "width = 4;\narea = width * 10;\n"
+    );
+}
+
+#[test]
+pub fn test_lowers_inside_catch_blocks() {
+    // the catch variable is a string, so this repeats it
+    assert!(compile(
+        "try\n    throw \"oops\"\ncatch e\n    print(e * 2)\nendtry\n",
+        Target::Js
+    )
+    .unwrap()
+    .contains("console.log(e.repeat(2));"));
+}
+
+#[test]
+pub fn test_folds_constants_in_js() {
+    assert_eq!(
+        compile("const N = 3\nx = N * 2\n", Target::Js).unwrap(),
+        "const N = 3;\nx = 6;\n"
+    );
+}
+
+#[test]
+pub fn test_converts_input_to_the_type_of_its_variable() {
+    // the type can be declared or inferred
+    assert_eq!(
+        compile(
+            "DECLARE age : INTEGER\nage = input(\"Age? \")\nnext = age + 1\n",
+            Target::Js
+        )
+        .unwrap(),
+        "let age = 0;\nage = parseInt(prompt(\"Age? \"), 10);\nnext = age + 1;\n"
+    );
+    assert_eq!(
+        compile("height = 1.5\nheight = input()\n", Target::Js).unwrap(),
+        "height = 1.5;\nheight = parseFloat(prompt());\n"
+    );
+    assert_eq!(
+        compile("DECLARE done : BOOLEAN\ndone = input()\n", Target::Js).unwrap(),
+        "let done = false;\ndone = (prompt().toLowerCase() === \"true\");\n"
+    );
+    // aliases are expanded first
+    assert_eq!(
+        compile(
+            "TYPE Age = INTEGER\nDECLARE age : Age\nage = input()\n",
+            Target::Js
+        )
+        .unwrap(),
+        "// TYPE Age = INTEGER\nlet age = 0;\nage = parseInt(prompt(), 10);\n"
+    );
+    // strings (and variables whose types aren't known) are left alone
+    assert_eq!(
+        compile("name = \"\"\nname = input()\nother = input()\n", Target::Js).unwrap(),
+        "name = \"\";\nname = prompt();\nother = prompt();\n"
+    );
+}
+
+#[test]
+pub fn test_compiles_named_arguments_to_js() {
+    assert_eq!(
+        compile("x = 2\nprint(x = 1)\n", Target::Js).unwrap(),
+        "x = 2;\nconsole.log(x === 1);\n"
+    );
+    let output = compile(
+        r#"
+function f(a, b = 1, c = 2)
+    return a + b + c
+endfunction
+print(f(0, c = 5), f(c = 3, a = 4), separator = " ")
+"#,
+        Target::Js,
+    )
+    .unwrap();
+    assert!(output.ends_with("console.log([f(0, 1, 5), f(4, 1, 3)].join(\" \"));\n"));
+}
+
+#[test]
+pub fn test_compiles_default_arguments_to_js() {
+    let output = compile(
+        r#"
+function greet(name, greeting = "Hello")
+    return greeting + ", " + name
+endfunction
+print(greet("Ada"))
+print(greet("Ada", "Hi"))
+"#,
+        Target::Js,
+    )
+    .unwrap();
+    assert_eq!(
+        output,
+        r#"function greet(name, greeting = "Hello") {
+    return (greeting + ", ") + name;
+}
+console.log(greet("Ada"));
+console.log(greet("Ada", "Hi"));
+"#
+    );
+    // the parameter without a default still has to be given an argument
+    assert!(matches!(
+        compile(
+            "function greet(name, greeting = \"Hello\")\n    return greeting\nendfunction\nprint(greet())\n",
+            Target::Js
+        ),
+        Err(CompileError::Type(_))
+    ));
+}
+
+#[test]
+pub fn test_reports_lex_errors() {
+    assert!(matches!(
+        compile("for i = 1 to 3\n    print(i)\nnext j\n", Target::Js),
+        Err(CompileError::Lex(_))
+    ));
+}
+
+#[test]
+pub fn test_reports_unclosed_blocks() {
+    match compile("x = 1\nwhile x < 3\n    x += 1\n", Target::Js) {
+        Err(CompileError::Parse(error)) => assert_eq!(
+            error.to_string(),
+            "expected `endwhile` to close while at line 2, reached end of file"
+        ),
+        other => panic!("expected a parse error, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn test_reports_type_errors() {
+    assert!(matches!(
+        compile(
+            "function f() returns INTEGER\n    return \"one\"\nendfunction\n",
+            Target::Js
+        ),
+        Err(CompileError::Type(_))
+    ));
+}
+
+/// The severity, code and line of each diagnostic (every one of which should have a span).
+fn summarise(diagnostics: &[Diagnostic]) -> Vec<(Severity, Option<&str>, u32)> {
+    diagnostics
+        .iter()
+        .map(|diagnostic| {
+            (
+                diagnostic.severity,
+                diagnostic.code.as_deref(),
+                diagnostic.span.unwrap().start.line(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+pub fn test_check_returns_errors_and_warnings() {
+    let diagnostics = compile_check(
+        r#"
+function f() returns INTEGER
+    return "one"
+endfunction
+if x = 1 then
+    print(x)
+endif
+while x == 2
+    x = 3
+endwhile
+"#,
+    );
+    assert_eq!(
+        summarise(&diagnostics),
+        [
+            (Severity::Error, None, 2),
+            (Severity::Warning, Some("inconsistent-equality"), 8)
+        ]
+    );
+    // the type error (which doesn't know where it is) gets the span of its function
+    assert_eq!(
+        diagnostics[0].span,
+        Some(Span::new(Loc::new(2, 1), Loc::new(4, 12)))
+    );
+    let errors = compile_check("for i = 1 to 3\n    print(i)\nnext j\n");
+    assert_eq!(summarise(&errors), [(Severity::Error, None, 3)]);
+    // a parse error points at the token it didn't expect
+    let errors = compile_check("if x then\n    y = 2\nelse y\nendif\n");
+    assert_eq!(
+        errors[0].span,
+        Some(Span::new(Loc::new(3, 6), Loc::new(3, 7)))
+    );
+    assert!(compile_check(MULTIPLY).is_empty());
+}
+
+#[test]
+pub fn test_check_sorts_diagnostics_by_position() {
+    let diagnostics = compile_check(
+        r#"
+DECLARE count : INTEGER
+print(count)
+function f() returns INTEGER
+    return "one"
+endfunction
+"#,
+    );
+    assert_eq!(
+        summarise(&diagnostics),
+        [
+            (Severity::Warning, Some("uninitialised-variable"), 3),
+            (Severity::Error, None, 4)
+        ]
+    );
+}
+
+#[test]
+pub fn test_check_keeps_going_after_type_errors() {
+    let diagnostics = compile_check(
+        r#"
+function f(a)
+    same = a == 1 AND "yes"
+    return a
+endfunction
+DECLARE count : INTEGER
+print(f(1, 2), count)
+if 1 OR true then
+    other = 2 == "two"
+endif
+"#,
+    );
+    assert_eq!(
+        summarise(&diagnostics),
+        [
+            (Severity::Error, None, 2),
+            (Severity::Warning, Some("uninitialised-variable"), 7),
+            (Severity::Error, None, 7),
+            (Severity::Error, None, 8),
+            (Severity::Error, None, 8)
+        ]
+    );
+    // the warning is about the whole statement, and the error about the extra argument
+    assert_eq!(diagnostics[1].span.unwrap().start, Loc::new(7, 1));
+    assert_eq!(diagnostics[2].span.unwrap().start, Loc::new(7, 12));
+    assert!(diagnostics[3].message.contains("`OR`"));
+    assert!(diagnostics[4].message.contains("compare"));
+}
+
+#[test]
+pub fn test_runs_programs() {
+    let mut output = Vec::new();
+    let source = format!("{}print(multiply(6, 7))", MULTIPLY);
+    run(&source, &mut Environment::with_output(&mut output)).unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), "42\n");
+}
+
+#[test]
+pub fn test_checks_programs_before_running_them() {
+    let mut output = Vec::new();
+    let result = run(
+        "print(\"before\")\nfunction f() returns INTEGER\n    return \"one\"\nendfunction\n",
+        &mut Environment::with_output(&mut output),
+    );
+    assert!(matches!(result, Err(CompileError::Type(_))));
+    assert!(output.is_empty());
+    assert!(matches!(
+        run("throw \"oops\"", &mut Environment::with_output(Vec::new())),
+        Err(CompileError::Runtime(RuntimeError::Thrown(_)))
+    ));
+}
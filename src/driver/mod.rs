@@ -0,0 +1,96 @@
+//! Ties the stages of the compiler together (source code -> tokens -> AST -> output code).
+
+#[cfg(test)]
+mod integration_tests;
+
+use crate::errorfmt::Diagnostic;
+use crate::interpreter::{self, Environment, RuntimeError};
+use crate::js_codegen;
+use crate::lexer::{lex_spanned, LexError, LexOptions};
+use crate::lint;
+use crate::parser::{parse_located, parse_spanned, ParseError, Statement};
+use crate::type_checker::{check, check_located, fold_constants, lower, TypeError};
+use crate::wasm_codegen::{self, WasmError};
+use thiserror::Error as ThisError;
+
+/// What to compile the program to.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Target {
+    Js,
+    Llvm,
+    Wasm,
+}
+
+#[derive(ThisError, Debug)]
+/// An error from any of the stages of compilation.
+pub enum CompileError {
+    #[error("{0}")]
+    Lex(#[from] LexError),
+    #[error("{0}")]
+    Parse(#[from] ParseError),
+    #[error("{0}")]
+    Type(#[from] TypeError),
+    #[error("{0}")]
+    Wasm(#[from] WasmError),
+    #[error("{0}")]
+    Runtime(#[from] RuntimeError),
+    #[error("compiling to {0:?} isn't supported yet")]
+    Unsupported(Target),
+}
+
+/// Checks some source code without generating any output, returning everything wrong with it (this
+/// is what an editor would show in its "problems" panel). Every diagnostic has a span; the ones
+/// which don't know where they are themselves get the span of the (top-level) statement they were
+/// found in.
+///
+/// The lexer and the parser can't recover from an error, so if there is one it's the only
+/// diagnostic. Otherwise, every type error (see `type_checker::check_located`) and warning is
+/// returned, sorted by where they start.
+pub fn compile_check(source: &str) -> Vec<Diagnostic> {
+    let mut source = source.to_string();
+    let stream = match lex_spanned(source.as_mut_str(), LexOptions::default()) {
+        Ok(stream) => stream,
+        Err(error) => return vec![Diagnostic::from(&error)],
+    };
+    let (program, spans) = match parse_located(&stream) {
+        Ok(parsed) => parsed,
+        Err((span, error)) => return vec![Diagnostic::from(&error).or_span(span)],
+    };
+    let mut diagnostics = check_located(&program)
+        .into_iter()
+        .map(|(index, error)| (index, Diagnostic::from(&error)))
+        .chain(lint::lint_located(&program))
+        .map(|(index, diagnostic)| diagnostic.or_span(Some(spans[index])))
+        .collect::<Vec<_>>();
+    // every diagnostic has a span now, and the sort is stable, so errors come before any warnings
+    // which start at the same place
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span.map(|span| span.start));
+    diagnostics
+}
+
+/// Compiles some source code to the target language.
+pub fn compile(source: &str, target: Target) -> Result<String, CompileError> {
+    let program = checked_program(source)?;
+    match target {
+        Target::Js => Ok(js_codegen::program(&program)),
+        Target::Wasm => Ok(wasm_codegen::module(&program)?),
+        Target::Llvm => Err(CompileError::Unsupported(target)),
+    }
+}
+
+/// Runs some source code with the interpreter, once it's been checked.
+pub fn run(source: &str, environment: &mut Environment) -> Result<(), CompileError> {
+    let program = checked_program(source)?;
+    Ok(interpreter::run(&program, environment)?)
+}
+
+/// Lexes, parses, checks and lowers some source code, ready to be output (or run).
+fn checked_program(source: &str) -> Result<Vec<Statement>, CompileError> {
+    let mut source = source.to_string();
+    let stream = lex_spanned(source.as_mut_str(), LexOptions::default())?;
+    let mut program = parse_spanned(&stream)?;
+    check(&program)?;
+    fold_constants(&mut program);
+    lower(&mut program);
+    Ok(program)
+}
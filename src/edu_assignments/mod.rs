@@ -2,5 +2,5 @@
 //!
 //! This will be extracted into a separate crate at some point.
 //! A web interface and integration into the playground is also planned.
-mod correctness;
-mod plagiarism;
+pub mod correctness;
+pub mod plagiarism;
@@ -4,6 +4,6 @@
 pub struct Plagiarism {}
 
 /// Detects suspected plagiarism between submissions.
-pub fn detect(ast_list: Vec<()>) -> Vec<Plagiarism> {
+pub fn detect(_ast_list: Vec<()>) -> Vec<Plagiarism> {
     todo!()
 }
@@ -1 +1,157 @@
 //! Outputs "nicely" formatted errors to the user.
+
+#[cfg(test)]
+mod unit_tests;
+
+use crate::driver::CompileError;
+use crate::lexer::{LexError, Span};
+use crate::parser::ParseError;
+use crate::type_checker::TypeError;
+use std::fmt;
+
+/// How serious a diagnostic is.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Severity {
+    /// The program can't be compiled.
+    Error,
+    /// The program can be compiled, but something in it should probably be changed.
+    Warning,
+    /// Extra information (e.g. about another diagnostic).
+    Note,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        })
+    }
+}
+
+/// Something to tell the user about their program, from any of the stages of compilation (including
+/// the linter's warnings).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// The part of the source code the diagnostic is about (if this is known).
+    pub span: Option<Span>,
+    pub message: String,
+    /// A short name for this kind of diagnostic (e.g. `inconsistent-equality`).
+    pub code: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            span: None,
+            message: message.into(),
+            code: None,
+        }
+    }
+
+    /// Gives the diagnostic a span, if it doesn't have one already (e.g. the span of the statement
+    /// it was found in, for an error which doesn't know where it is itself).
+    pub fn or_span(self, span: Option<Span>) -> Self {
+        Self {
+            span: self.span.or(span),
+            ..self
+        }
+    }
+
+    /// Renders the diagnostic for showing to the user. If it has a span, the line of `source` it
+    /// starts on is quoted, with the span underlined:
+    ///
+    /// ```text
+    /// error: the `(` opened at line 1 isn't closed
+    ///  --> 1:5
+    ///   |
+    /// 1 | x = (1 + 2
+    ///   |     ^
+    /// ```
+    pub fn render(&self, source: &str) -> String {
+        let mut output = self.severity.to_string();
+        if let Some(code) = &self.code {
+            output.push_str(&format!("[{}]", code));
+        }
+        output.push_str(&format!(": {}\n", self.message));
+        let span = match self.span {
+            Some(span) => span,
+            None => return output,
+        };
+        let (line, col) = (span.start.line(), span.start.col());
+        // tabs count as four columns (as they do in `Loc`), so they're expanded to line up with
+        // the underline
+        let text = source
+            .lines()
+            .nth((line as usize).saturating_sub(1))
+            .unwrap_or_default()
+            .replace('\t', "    ");
+        // a span which goes on to another line is underlined up to the end of this one
+        let stop = if span.stop.line() == line {
+            span.stop.col()
+        } else {
+            text.chars().count() as u32 + 1
+        };
+        let gutter = " ".repeat(line.to_string().len());
+        output.push_str(&format!("{}--> {}:{}\n", gutter, line, col));
+        output.push_str(&format!("{} |\n", gutter));
+        output.push_str(&format!("{} | {}\n", line, text));
+        output.push_str(&format!(
+            "{} | {}{}\n",
+            gutter,
+            " ".repeat((col as usize).saturating_sub(1)),
+            "^".repeat(stop.saturating_sub(col).max(1) as usize)
+        ));
+        output
+    }
+}
+
+impl From<&LexError> for Diagnostic {
+    fn from(error: &LexError) -> Self {
+        let span = match error {
+            LexError::UnexpectedToken(token) | LexError::NonDefaultAfterDefault(token) => {
+                Some(token.span)
+            }
+            LexError::UnmatchedBracket(span) | LexError::UnclosedBracket(span) => Some(*span),
+            LexError::IndentationError { loc, .. } => Some(Span::new(*loc, *loc)),
+            LexError::UnexpectedEndOfInput => None,
+        };
+        Self {
+            span,
+            ..Self::new(Severity::Error, error.to_string())
+        }
+    }
+}
+
+/// Parse errors don't know where they are, so these diagnostics don't have spans. The span which
+/// `parser::parse_located` returns along with the error can be added with `or_span`.
+impl From<&ParseError> for Diagnostic {
+    fn from(error: &ParseError) -> Self {
+        Self::new(Severity::Error, error.to_string())
+    }
+}
+
+/// Only some type errors know where they are (see `TypeError::span`); the others can be given the
+/// span of the statement they were found in with `or_span`.
+impl From<&TypeError> for Diagnostic {
+    fn from(error: &TypeError) -> Self {
+        Self {
+            span: error.span(),
+            ..Self::new(Severity::Error, error.to_string())
+        }
+    }
+}
+
+impl From<&CompileError> for Diagnostic {
+    fn from(error: &CompileError) -> Self {
+        match error {
+            CompileError::Lex(error) => error.into(),
+            CompileError::Parse(error) => error.into(),
+            CompileError::Type(error) => error.into(),
+            error => Self::new(Severity::Error, error.to_string()),
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! Tests for rendering diagnostics.
+
+use crate::errorfmt::{Diagnostic, Severity};
+use crate::lexer::{lex, lex_spanned, LexOptions, Loc, Span};
+use crate::parser::parse_located;
+use crate::type_checker::TypeError;
+
+#[test]
+pub fn test_renders_each_severity() {
+    for (severity, rendered) in [
+        (Severity::Error, "error: something is wrong\n"),
+        (Severity::Warning, "warning: something is wrong\n"),
+        (Severity::Note, "note: something is wrong\n"),
+    ] {
+        let diagnostic = Diagnostic::new(severity, "something is wrong");
+        assert_eq!(diagnostic.render(""), rendered);
+    }
+}
+
+#[test]
+pub fn test_renders_span_and_code() {
+    let diagnostic = Diagnostic {
+        span: Some(Span::new(Loc::new(2, 5), Loc::new(2, 10))),
+        code: Some("example".to_string()),
+        ..Diagnostic::new(Severity::Warning, "this is a warning")
+    };
+    assert_eq!(
+        diagnostic.render("x = 1\ny = total + 1\n"),
+        "warning[example]: this is a warning\n --> 2:5\n  |\n2 | y = total + 1\n  |     ^^^^^\n"
+    );
+}
+
+#[test]
+pub fn test_converts_lex_errors() {
+    let source = "x = (1 + 2\n";
+    let error = lex(source.to_string().as_mut_str()).unwrap_err();
+    let diagnostic = Diagnostic::from(&error);
+    assert_eq!(diagnostic.severity, Severity::Error);
+    assert_eq!(
+        diagnostic.render(source),
+        "error: the `(` opened at line 1 isn't closed\n --> 1:5\n  |\n1 | x = (1 + 2\n  |     ^\n"
+    );
+}
+
+#[test]
+pub fn test_converts_parse_errors_with_their_spans() {
+    // the span of an unclosed block is the keyword which opened it
+    let source = "x = 3\nwhile x > 1\n    x = x - 1\n";
+    let stream = lex_spanned(source.to_string().as_mut_str(), LexOptions::default()).unwrap();
+    let (span, error) = parse_located(&stream).unwrap_err();
+    assert_eq!(
+        Diagnostic::from(&error).or_span(span).render(source),
+        "error: expected `endwhile` to close while at line 2, reached end of file\n --> 2:1\n  |\n2 | while x > 1\n  | ^^^^^\n"
+    );
+}
+
+#[test]
+pub fn test_converts_type_errors_with_their_spans() {
+    let span = Span::new(Loc::new(1, 5), Loc::new(1, 6));
+    let error = TypeError::TooManyArguments {
+        function: "f".to_string(),
+        expected: 0,
+        found: 1,
+        span: Some(span),
+    };
+    // the error's own span is kept
+    let diagnostic = Diagnostic::from(&error).or_span(Some(Span::new(span.start, span.start)));
+    assert_eq!(diagnostic.severity, Severity::Error);
+    assert_eq!(diagnostic.span, Some(span));
+}
+
+#[test]
+pub fn test_renders_span_at_line_zero() {
+    let diagnostic = Diagnostic {
+        span: Some(Span::new(Loc::new(0, 0), Loc::new(0, 0))),
+        ..Diagnostic::new(Severity::Error, "somewhere")
+    };
+    assert_eq!(
+        diagnostic.render("x = 1\n"),
+        "error: somewhere\n --> 0:0\n  |\n0 | x = 1\n  | ^\n"
+    );
+}
@@ -0,0 +1,282 @@
+//! Formats an AST back into source code.
+//!
+//! The output is always laid out in the same way (one statement per line, with blocks indented by
+//! four spaces), so this is the start of the code formatter on the roadmap.
+
+use crate::lexer::{escape_string, Operator, StringPart};
+use crate::parser::{Expression, Statement, SwitchCasePattern};
+
+/// Formats a whole program.
+pub fn program(program: &[Statement]) -> String {
+    let mut output = String::new();
+    for statement in program {
+        output.push_str(&statement.format());
+        output.push('\n');
+    }
+    output
+}
+
+/// Formats a single expression (e.g. to quote it in an error message).
+pub fn expression(expression: &Expression) -> String {
+    expression.format()
+}
+
+/// A trait for formatting AST nodes as source code.
+trait Format {
+    /// Formats the AST node (without a trailing newline).
+    fn format(&self) -> String;
+}
+
+impl Format for Statement {
+    fn format(&self) -> String {
+        match self {
+            Statement::ForStatement(for_statement) => {
+                let mut output = format!(
+                    "for {} = {} to {}",
+                    for_statement.ident,
+                    for_statement.start.format(),
+                    for_statement.stop.format()
+                );
+                if let Some(step) = &for_statement.step {
+                    output.push_str(&format!(" step {}", step.format()));
+                }
+                format!(
+                    "{}\n{}next {}",
+                    output,
+                    format_block(&for_statement.block),
+                    for_statement.ident
+                )
+            }
+            Statement::ForEachStatement(for_each) => format!(
+                "for each {} in {}\n{}next {}",
+                for_each.item,
+                for_each.iterable.format(),
+                format_block(&for_each.block),
+                for_each.item
+            ),
+            Statement::WhileStatement(while_statement) => format!(
+                "while {}\n{}endwhile",
+                while_statement.predicate.format(),
+                format_block(&while_statement.block)
+            ),
+            Statement::IfStatement(if_statement) => {
+                let mut output = format!(
+                    "if {} then\n{}",
+                    if_statement.case_if.predicate.format(),
+                    format_block(&if_statement.case_if.block)
+                );
+                for case in &if_statement.cases_elif {
+                    output.push_str(&format!(
+                        "elseif {} then\n{}",
+                        case.predicate.format(),
+                        format_block(&case.block)
+                    ));
+                }
+                if let Some(case_else) = &if_statement.case_else {
+                    output.push_str(&format!("else\n{}", format_block(&case_else.block)));
+                }
+                output.push_str("endif");
+                output
+            }
+            Statement::AssignmentStatement(assignment) => format!(
+                "{} {} {}",
+                assignment.ident,
+                assignment.operator,
+                assignment.expression.format()
+            ),
+            Statement::ElementAssignment(assignment) => format!(
+                "{}{} {} {}",
+                assignment.array,
+                assignment
+                    .indexes
+                    .iter()
+                    .map(|index| format!("[{}]", index.format()))
+                    .collect::<String>(),
+                assignment.operator,
+                assignment.expression.format()
+            ),
+            Statement::DoUntilStatement(do_until) => format!(
+                "do\n{}until {}",
+                format_block(&do_until.block),
+                do_until.predicate.format()
+            ),
+            Statement::SwitchStatement(switch) => {
+                let mut cases = String::new();
+                for case in &switch.cases {
+                    let pattern = match &case.pattern {
+                        SwitchCasePattern::Value(value) => value.format(),
+                        SwitchCasePattern::Comparison(operator, value) => {
+                            format!("is {} {}", operator, value.format())
+                        }
+                    };
+                    cases.push_str(&format!("case {}:\n{}", pattern, format_block(&case.block)));
+                }
+                if let Some(default) = &switch.default {
+                    cases.push_str(&format!("default:\n{}", format_block(&default.block)));
+                }
+                format!(
+                    "switch {}:\n{}endswitch",
+                    switch.subject.format(),
+                    format_block_lines(&cases)
+                )
+            }
+            Statement::FunctionDefinition(function) => {
+                let parameters = function
+                    .parameters
+                    .iter()
+                    .map(|parameter| {
+                        let name = if parameter.by_ref {
+                            format!("{}:byRef", parameter.name)
+                        } else {
+                            parameter.name.to_string()
+                        };
+                        match &parameter.default {
+                            Some(default) => format!("{} = {}", name, default.format()),
+                            None => name,
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let mut output = String::new();
+                for line in function.doc.iter().flat_map(|doc| doc.lines()) {
+                    output.push_str(&format!("// {}\n", line));
+                }
+                output.push_str(&format!("function {}({})", function.name, parameters));
+                if let Some(returns) = &function.returns {
+                    output.push_str(&format!(" returns {}", returns));
+                }
+                format!("{}\n{}endfunction", output, format_block(&function.block))
+            }
+            Statement::TryStatement(try_statement) => format!(
+                "try\n{}catch {}\n{}endtry",
+                format_block(&try_statement.body),
+                try_statement.catch_var,
+                format_block(&try_statement.catch_block)
+            ),
+            Statement::Declaration(declaration) => {
+                format!("DECLARE {} : {}", declaration.name, declaration.ty)
+            }
+            Statement::TypeAlias(alias) => format!("TYPE {} = {}", alias.name, alias.ty),
+            Statement::ConstDeclaration(declaration) => {
+                format!(
+                    "const {} = {}",
+                    declaration.name,
+                    declaration.value.format()
+                )
+            }
+            Statement::ArrayDeclaration(declaration) => {
+                format!("array {}[{}]", declaration.name, declaration.size.format())
+            }
+            Statement::ReturnStatement(expression) => format!("return {}", expression.format()),
+            Statement::ThrowStatement(expression) => format!("throw {}", expression.format()),
+            Statement::PassStatement => "pass".to_string(),
+            Statement::ExpressionStatement(expression) => expression.format(),
+        }
+    }
+}
+
+impl Format for Expression {
+    fn format(&self) -> String {
+        match self {
+            Expression::Integer(integer) => integer.to_string(),
+            Expression::Float(float) => format!("{:?}", float),
+            Expression::Boolean(boolean) => boolean.to_string(),
+            Expression::String(string) => format!("\"{}\"", escape_string(string)),
+            Expression::InterpolatedString(parts) => format!(
+                "\"{}\"",
+                parts.iter().map(StringPart::to_string).collect::<String>()
+            ),
+            Expression::Ident(ident) => ident.to_string(),
+            Expression::FunctionCall {
+                name,
+                args,
+                named_args,
+                ..
+            } => format!("{}({})", name, format_arguments(args, named_args)),
+            Expression::Member { object, member } => {
+                format!("{}.{}", format_operand(object), member)
+            }
+            Expression::MethodCall {
+                object,
+                name,
+                args,
+                named_args,
+            } => format!(
+                "{}.{}({})",
+                format_operand(object),
+                name,
+                format_arguments(args, named_args)
+            ),
+            Expression::Array(elements) => format!("[{}]", format_arguments(elements, &[])),
+            Expression::Index { array, index } => {
+                format!("{}[{}]", format_operand(array), index.format())
+            }
+            Expression::Slice { array, start, stop } => format!(
+                "{}[{}:{}]",
+                format_operand(array),
+                start.format(),
+                stop.format()
+            ),
+            Expression::UnaryOp { operator, operand } => match operator {
+                // word operators need a space between them and their operand
+                Operator::Not => format!("{} {}", operator, format_operand(operand)),
+                _ => format!("{}{}", operator, format_operand(operand)),
+            },
+            Expression::BinaryOp { operator, lhs, rhs } => format!(
+                "{} {} {}",
+                format_operand(lhs),
+                operator,
+                format_operand(rhs)
+            ),
+        }
+    }
+}
+
+/// Formats the arguments to a function or method, with the ones given by name at the end.
+fn format_arguments(args: &[Expression], named_args: &[(String, Expression)]) -> String {
+    args.iter()
+        .map(Format::format)
+        .chain(
+            named_args
+                .iter()
+                .map(|(name, arg)| format!("{} = {}", name, arg.format())),
+        )
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Formats a block, indenting each of its lines (including the final newline).
+fn format_block(block: &[Statement]) -> String {
+    let mut output = String::new();
+    for statement in block {
+        output.push_str(&format_block_lines(&statement.format()));
+    }
+    output
+}
+
+/// Indents each line of some already formatted code, ending each of them with a newline.
+fn format_block_lines(code: &str) -> String {
+    let mut output = String::new();
+    for line in code.lines() {
+        output.push_str("    ");
+        output.push_str(line);
+        output.push('\n');
+    }
+    output
+}
+
+/// Formats an operand of an operator, bracketing it if it's an operation itself (so that it's
+/// parsed back with the same precedence). Negative numbers are bracketed too, because `-2 ^ 2`
+/// means `-(2 ^ 2)`.
+fn format_operand(operand: &Expression) -> String {
+    match operand {
+        Expression::UnaryOp { .. } | Expression::BinaryOp { .. } => {
+            format!("({})", operand.format())
+        }
+        Expression::Integer(integer) if *integer < 0 => format!("({})", operand.format()),
+        Expression::Float(float) if float.is_sign_negative() => {
+            format!("({})", operand.format())
+        }
+        _ => operand.format(),
+    }
+}
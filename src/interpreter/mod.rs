@@ -0,0 +1,1052 @@
+//! Runs programs directly, rather than compiling them to another language first.
+//!
+//! Programs should be checked and lowered (see `type_checker::check` and `type_checker::lower`)
+//! before they're run; `driver::run` does all of this. Anything the type checker can't rule out is
+//! reported as a `RuntimeError`.
+
+mod random;
+#[cfg(test)]
+mod unit_tests;
+mod value;
+
+pub use random::Rng;
+pub use value::Value;
+
+use crate::lexer::{Operator, StringPart};
+use crate::parser::{
+    ArrayDeclaration, AssignmentStatement, ConstDeclaration, DoUntilStatement, ElementAssignment,
+    Expression, ForEachStatement, ForStatement, FunctionDefinition, IfStatement, NamedArgs,
+    Statement, SwitchCasePattern, SwitchStatement, TryStatement, WhileStatement,
+};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io::{self, BufRead, BufReader, Write};
+use thiserror::Error as ThisError;
+
+/// How many function calls can be inside each other (so that a function which recurses forever
+/// stops with an error, rather than overflowing the interpreter's own stack).
+pub const MAX_CALL_DEPTH: usize = 200;
+
+/// What a running program reads from and writes to.
+pub struct Environment<'a> {
+    /// Where `print` (and `OUTPUT`) write to.
+    pub output: Box<dyn Write + 'a>,
+    /// Where `input` (and `INPUT`) read lines from.
+    pub input: Box<dyn BufRead + 'a>,
+    /// Where `RANDOM` and `RANDOM_INT` get their numbers from (which can be seeded, with
+    /// `Rng::seeded`, to make them reproducible).
+    pub rng: Rng,
+    /// How many bytes a program can write to `output` (if there's a limit) before it's stopped with
+    /// `RuntimeError::OutputLimitExceeded`, so that a loop which prints forever can't use up all of
+    /// the memory of something which collects the output (like a playground).
+    pub output_limit: Option<usize>,
+}
+
+impl Default for Environment<'_> {
+    /// An environment which writes to stdout and reads from stdin.
+    fn default() -> Self {
+        Self {
+            output: Box::new(io::stdout()),
+            input: Box::new(BufReader::new(io::stdin())),
+            rng: Rng::default(),
+            output_limit: None,
+        }
+    }
+}
+
+impl<'a> Environment<'a> {
+    /// An environment which writes to `output` (e.g. a `Vec<u8>`, to capture what's printed), and
+    /// reads from stdin.
+    pub fn with_output(output: impl Write + 'a) -> Self {
+        Self {
+            output: Box::new(output),
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(ThisError, Debug)]
+/// An error which stops a running program. Apart from `OutputLimitExceeded` and `Io`, these can be caught with `try ... catch
+/// e ... endtry` (which gives `e` the error's message).
+pub enum RuntimeError {
+    /// The message given to a `throw` statement.
+    #[error("{0}")]
+    Thrown(String),
+    #[error("`{0}` isn't defined")]
+    UndefinedVariable(String),
+    /// A variable was declared (with `DECLARE`) but read before it was given a value.
+    #[error("`{0}` hasn't been given a value")]
+    UninitializedVariable(String),
+    /// An element of an array declared with `array` was read before it was given a value.
+    #[error("element {0} of the array hasn't been given a value")]
+    UninitializedElement(i64),
+    #[error("an array can't have a negative size ({0})")]
+    NegativeSize(i64),
+    #[error("a string can't be repeated a negative number of times ({0})")]
+    NegativeRepeat(i64),
+    #[error("there isn't a function called `{0}`")]
+    UndefinedFunction(String),
+    #[error("`{function}` takes {expected} arguments, but was given {found}")]
+    WrongNumberOfArguments {
+        function: String,
+        expected: usize,
+        found: usize,
+    },
+    #[error("expected {expected}, found {found}")]
+    WrongType {
+        expected: &'static str,
+        found: &'static str,
+    },
+    #[error("`{operator}` can't be used on {lhs} and {rhs}")]
+    InvalidOperands {
+        operator: Operator,
+        lhs: &'static str,
+        rhs: &'static str,
+    },
+    /// `SORT` was given an array with elements which can't be ordered with each other.
+    #[error("an array containing {lhs} and {rhs} can't be sorted")]
+    Unsortable {
+        lhs: &'static str,
+        rhs: &'static str,
+    },
+    #[error("{found} doesn't have a `{member}`")]
+    UnknownMember { member: String, found: &'static str },
+    /// `MIN` or `MAX` was given an empty array.
+    #[error("`{0}` can't be used on an empty array")]
+    EmptyArray(String),
+    /// `RANDOM_INT` was given a lower bound which is greater than the upper one.
+    #[error("there aren't any integers from {low} to {high}")]
+    EmptyRange { low: i64, high: i64 },
+    #[error("division by zero")]
+    DivisionByZero,
+    #[error("the result is too big to fit in an integer")]
+    IntegerOverflow,
+    #[error("index {index} is out of bounds (the length is {length})")]
+    IndexOutOfBounds { index: i64, length: usize },
+    /// A string which was converted to a number (e.g. with `int`) isn't one.
+    #[error("`{0}` isn't a number")]
+    InvalidNumber(String),
+    #[error("the step of a `for` loop can't be zero")]
+    ZeroStep,
+    /// `input` was called after all of the input had been read.
+    #[error("there isn't any more input to read")]
+    EndOfInput,
+    #[error("`return` can only be used inside a function")]
+    ReturnOutsideFunction,
+    #[error("more than {MAX_CALL_DEPTH} function calls are inside each other")]
+    TooMuchRecursion,
+    #[error("the program's output is longer than the limit of {0} bytes")]
+    OutputLimitExceeded(usize),
+    /// Reading input or writing output failed.
+    #[error("{0}")]
+    Io(#[from] io::Error),
+}
+
+impl RuntimeError {
+    /// Whether a `try` statement can catch the error.
+    pub fn is_catchable(&self) -> bool {
+        !matches!(
+            self,
+            RuntimeError::OutputLimitExceeded(_) | RuntimeError::Io(_)
+        )
+    }
+}
+
+/// Runs a (checked and lowered) program. The program's functions are all defined before anything
+/// is run, so a function can be called above its definition.
+pub fn run(program: &[Statement], environment: &mut Environment) -> Result<(), RuntimeError> {
+    let mut interpreter = Interpreter {
+        functions: program
+            .iter()
+            .filter_map(|statement| match statement {
+                Statement::FunctionDefinition(function) => Some((function.name.as_str(), function)),
+                _ => None,
+            })
+            .collect(),
+        scopes: vec![HashMap::new()],
+        constants: HashMap::new(),
+        written: 0,
+        environment,
+    };
+    let flow = interpreter.run_block(program);
+    interpreter.environment.output.flush()?;
+    match flow? {
+        Flow::Next => Ok(()),
+        Flow::Return(_) => Err(RuntimeError::ReturnOutsideFunction),
+    }
+}
+
+/// What happens after a statement has been run.
+enum Flow {
+    /// The next statement is run.
+    Next,
+    /// The function which is running returns this value.
+    Return(Value),
+}
+
+struct Interpreter<'p, 'e, 'a> {
+    /// The functions defined in the program.
+    functions: HashMap<&'p str, &'p FunctionDefinition>,
+    /// The variables of the program (first) and of each function which is running (the innermost
+    /// call last). A function can't see the variables outside of it, apart from the constants.
+    scopes: Vec<HashMap<String, Value>>,
+    /// The constants declared outside of any function.
+    constants: HashMap<String, Value>,
+    /// How many bytes of output have been written so far.
+    written: usize,
+    environment: &'e mut Environment<'a>,
+}
+
+// Each kind of statement and expression is run by its own method, so that the frames of the
+// methods which recurse (`run_statement`, `evaluate` and `call`) stay small: otherwise a recursive
+// function can overflow the interpreter's stack (in a debug build) before `MAX_CALL_DEPTH` is
+// reached.
+impl Interpreter<'_, '_, '_> {
+    /// Runs the statements in a block, stopping early if one of them returns.
+    fn run_block(&mut self, block: &[Statement]) -> Result<Flow, RuntimeError> {
+        for statement in block {
+            if let Flow::Return(value) = self.run_statement(statement)? {
+                return Ok(Flow::Return(value));
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    fn run_statement(&mut self, statement: &Statement) -> Result<Flow, RuntimeError> {
+        match statement {
+            Statement::ForStatement(for_statement) => self.run_for(for_statement),
+            Statement::ForEachStatement(for_each) => self.run_for_each(for_each),
+            Statement::WhileStatement(while_statement) => self.run_while(while_statement),
+            Statement::DoUntilStatement(do_until) => self.run_do_until(do_until),
+            Statement::IfStatement(if_statement) => self.run_if(if_statement),
+            Statement::SwitchStatement(switch) => self.run_switch(switch),
+            Statement::AssignmentStatement(assignment) => self.run_assignment(assignment),
+            Statement::ElementAssignment(assignment) => self.run_element_assignment(assignment),
+            Statement::TryStatement(try_statement) => self.run_try(try_statement),
+            Statement::ConstDeclaration(declaration) => self.run_const_declaration(declaration),
+            // a declared variable (or each element of a declared array) is null until it's assigned
+            Statement::Declaration(declaration) => {
+                self.assign(&declaration.name, Value::Null);
+                Ok(Flow::Next)
+            }
+            Statement::ArrayDeclaration(declaration) => self.run_array_declaration(declaration),
+            Statement::ReturnStatement(expression) => self.evaluate(expression).map(Flow::Return),
+            Statement::ThrowStatement(expression) => Err(self.throw(expression)),
+            Statement::ExpressionStatement(expression) => {
+                self.evaluate(expression).map(|_| Flow::Next)
+            }
+            // the functions were all defined before the program started
+            Statement::FunctionDefinition(_)
+            | Statement::TypeAlias(_)
+            | Statement::PassStatement => Ok(Flow::Next),
+        }
+    }
+
+    /// Runs a `for` loop. The step can be negative, in which case the loop counts down.
+    fn run_for(&mut self, for_statement: &ForStatement) -> Result<Flow, RuntimeError> {
+        let mut counter = self.integer(&for_statement.start)?;
+        let stop = self.integer(&for_statement.stop)?;
+        let step = match &for_statement.step {
+            Some(step) => self.integer(step)?,
+            None => 1,
+        };
+        if step == 0 {
+            return Err(RuntimeError::ZeroStep);
+        }
+        while (step > 0 && counter <= stop) || (step < 0 && counter >= stop) {
+            self.assign(&for_statement.ident, Value::Integer(counter));
+            if let Flow::Return(value) = self.run_block(&for_statement.block)? {
+                return Ok(Flow::Return(value));
+            }
+            // the block can change the loop variable
+            counter = match self.variable(&for_statement.ident)? {
+                Value::Integer(counter) => counter
+                    .checked_add(step)
+                    .ok_or(RuntimeError::IntegerOverflow)?,
+                value => return Err(wrong_type("an integer", &value)),
+            };
+        }
+        Ok(Flow::Next)
+    }
+
+    /// Runs a `for ... in` loop over the elements of an array (or the characters of a string).
+    fn run_for_each(&mut self, for_each: &ForEachStatement) -> Result<Flow, RuntimeError> {
+        let items = match self.evaluate(&for_each.iterable)? {
+            Value::Array(elements) => elements,
+            Value::String(string) => string
+                .chars()
+                .map(|character| Value::String(character.to_string()))
+                .collect(),
+            value => return Err(wrong_type("an array", &value)),
+        };
+        for item in items {
+            self.assign(&for_each.item, item);
+            if let Flow::Return(value) = self.run_block(&for_each.block)? {
+                return Ok(Flow::Return(value));
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    fn run_while(&mut self, while_statement: &WhileStatement) -> Result<Flow, RuntimeError> {
+        while self.boolean(&while_statement.predicate)? {
+            if let Flow::Return(value) = self.run_block(&while_statement.block)? {
+                return Ok(Flow::Return(value));
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    fn run_do_until(&mut self, do_until: &DoUntilStatement) -> Result<Flow, RuntimeError> {
+        loop {
+            if let Flow::Return(value) = self.run_block(&do_until.block)? {
+                return Ok(Flow::Return(value));
+            }
+            if self.boolean(&do_until.predicate)? {
+                return Ok(Flow::Next);
+            }
+        }
+    }
+
+    fn run_if(&mut self, if_statement: &IfStatement) -> Result<Flow, RuntimeError> {
+        for case in std::iter::once(&if_statement.case_if).chain(&if_statement.cases_elif) {
+            if self.boolean(&case.predicate)? {
+                return self.run_block(&case.block);
+            }
+        }
+        match &if_statement.case_else {
+            Some(case_else) => self.run_block(&case_else.block),
+            None => Ok(Flow::Next),
+        }
+    }
+
+    /// Runs the block of the first case which matches the subject (or the default case, if none of
+    /// them do).
+    fn run_switch(&mut self, switch: &SwitchStatement) -> Result<Flow, RuntimeError> {
+        let subject = self.evaluate(&switch.subject)?;
+        for case in &switch.cases {
+            let (operator, value) = match &case.pattern {
+                SwitchCasePattern::Value(value) => (&Operator::Comparison, value),
+                SwitchCasePattern::Comparison(operator, value) => (operator, value),
+            };
+            let value = self.evaluate(value)?;
+            if compare(operator, &subject, &value)? {
+                return self.run_block(&case.block);
+            }
+        }
+        match &switch.default {
+            Some(default) => self.run_block(&default.block),
+            None => Ok(Flow::Next),
+        }
+    }
+
+    /// Runs the body of a `try` statement, and then the `catch` block if an error is raised (with
+    /// the error's message bound to the catch variable).
+    fn run_try(&mut self, try_statement: &TryStatement) -> Result<Flow, RuntimeError> {
+        match self.run_block(&try_statement.body) {
+            Err(error) if error.is_catchable() => {
+                self.assign(&try_statement.catch_var, Value::String(error.to_string()));
+                self.run_block(&try_statement.catch_block)
+            }
+            flow => flow,
+        }
+    }
+
+    fn run_assignment(&mut self, assignment: &AssignmentStatement) -> Result<Flow, RuntimeError> {
+        let value = self.evaluate(&assignment.expression)?;
+        let value = match assignment.operator {
+            Operator::Increment => {
+                arithmetic(&Operator::Plus, self.variable(&assignment.ident)?, value)?
+            }
+            _ => value,
+        };
+        self.assign(&assignment.ident, value);
+        Ok(Flow::Next)
+    }
+
+    fn run_element_assignment(
+        &mut self,
+        assignment: &ElementAssignment,
+    ) -> Result<Flow, RuntimeError> {
+        let indexes = assignment
+            .indexes
+            .iter()
+            .map(|index| self.integer(index))
+            .collect::<Result<Vec<_>, _>>()?;
+        let value = self.evaluate(&assignment.expression)?;
+        let mut target = match self.scopes.last_mut().unwrap().get_mut(&assignment.array) {
+            Some(Value::Null) => {
+                return Err(RuntimeError::UninitializedVariable(
+                    assignment.array.clone(),
+                ))
+            }
+            Some(target) => target,
+            None => return Err(RuntimeError::UndefinedVariable(assignment.array.clone())),
+        };
+        // the index of the element `target` is in (if it's an element)
+        let mut outer_index = None;
+        for index in indexes {
+            target = match (target, outer_index) {
+                (Value::Array(elements), _) => {
+                    let length = elements.len();
+                    usize::try_from(index)
+                        .ok()
+                        .and_then(move |index| elements.get_mut(index))
+                        .ok_or(RuntimeError::IndexOutOfBounds { index, length })?
+                }
+                (Value::Null, Some(outer_index)) => {
+                    return Err(RuntimeError::UninitializedElement(outer_index))
+                }
+                (target, _) => return Err(wrong_type("an array", target)),
+            };
+            outer_index = Some(index);
+        }
+        *target = match (assignment.operator.clone(), outer_index) {
+            (Operator::Increment, Some(index)) if *target == Value::Null => {
+                return Err(RuntimeError::UninitializedElement(index))
+            }
+            (Operator::Increment, _) => arithmetic(&Operator::Plus, target.clone(), value)?,
+            _ => value,
+        };
+        Ok(Flow::Next)
+    }
+
+    /// Declares a constant. Constants declared outside of any function can be used inside them.
+    fn run_const_declaration(
+        &mut self,
+        declaration: &ConstDeclaration,
+    ) -> Result<Flow, RuntimeError> {
+        let value = self.evaluate(&declaration.value)?;
+        if self.scopes.len() == 1 {
+            self.constants
+                .insert(declaration.name.clone(), value.clone());
+        }
+        self.assign(&declaration.name, value);
+        Ok(Flow::Next)
+    }
+
+    fn run_array_declaration(
+        &mut self,
+        declaration: &ArrayDeclaration,
+    ) -> Result<Flow, RuntimeError> {
+        let size = self.integer(&declaration.size)?;
+        let size = usize::try_from(size).map_err(|_| RuntimeError::NegativeSize(size))?;
+        self.assign(&declaration.name, Value::Array(vec![Value::Null; size]));
+        Ok(Flow::Next)
+    }
+
+    /// Works out the error which a `throw` statement raises.
+    fn throw(&mut self, message: &Expression) -> RuntimeError {
+        match self.evaluate(message) {
+            Ok(message) => RuntimeError::Thrown(message.to_string()),
+            Err(error) => error,
+        }
+    }
+
+    fn evaluate(&mut self, expression: &Expression) -> Result<Value, RuntimeError> {
+        match expression {
+            Expression::Integer(integer) => Ok(Value::Integer(*integer)),
+            Expression::Float(float) => Ok(Value::Real(*float)),
+            Expression::Boolean(boolean) => Ok(Value::Boolean(*boolean)),
+            Expression::String(string) => Ok(Value::String(string.clone())),
+            Expression::InterpolatedString(parts) => self.interpolate(parts),
+            Expression::Ident(name) => self.variable(name),
+            Expression::Array(elements) => elements
+                .iter()
+                .map(|element| self.evaluate(element))
+                .collect::<Result<_, _>>()
+                .map(Value::Array),
+            Expression::FunctionCall {
+                name,
+                args,
+                named_args,
+                ..
+            } => self.call(name, args, named_args),
+            Expression::Member { object, member } => self.member(object, member),
+            Expression::MethodCall {
+                object, name, args, ..
+            } => self.method_call(object, name, args),
+            Expression::Index { array, index } => self.index(array, index),
+            Expression::Slice { array, start, stop } => self.slice(array, start, stop),
+            Expression::UnaryOp { operator, operand } => self.unary_op(operator, operand),
+            Expression::BinaryOp { operator, lhs, rhs } => self.binary_op(operator, lhs, rhs),
+        }
+    }
+
+    /// Fills in the placeholders of an interpolated string with the values of the variables.
+    fn interpolate(&mut self, parts: &[StringPart]) -> Result<Value, RuntimeError> {
+        let mut string = String::new();
+        for part in parts {
+            match part {
+                StringPart::Literal(literal) => string.push_str(literal),
+                StringPart::Placeholder(name) => string.push_str(&self.variable(name)?.to_string()),
+            }
+        }
+        Ok(Value::String(string))
+    }
+
+    /// Finds a member (e.g. `length`) of a value.
+    fn member(&mut self, object: &Expression, member: &str) -> Result<Value, RuntimeError> {
+        match (self.evaluate(object)?, member) {
+            (Value::Array(elements), "length") => Ok(Value::Integer(elements.len() as i64)),
+            (Value::String(string), "length") => Ok(Value::Integer(string.chars().count() as i64)),
+            (object, member) => Err(RuntimeError::UnknownMember {
+                member: member.to_string(),
+                found: object.type_name(),
+            }),
+        }
+    }
+
+    /// Calls a method. The only one is `repeat`, which `type_checker::lower` turns string
+    /// repetition (`"-" * 10` or `10 * "-"`) into.
+    fn method_call(
+        &mut self,
+        object: &Expression,
+        name: &str,
+        args: &[Expression],
+    ) -> Result<Value, RuntimeError> {
+        let object = self.evaluate(object)?;
+        let args = args
+            .iter()
+            .map(|arg| self.evaluate(arg))
+            .collect::<Result<Vec<_>, _>>()?;
+        match (object, name, &args[..]) {
+            (Value::String(string), "repeat", [Value::Integer(count)]) => {
+                let count =
+                    usize::try_from(*count).map_err(|_| RuntimeError::NegativeRepeat(*count))?;
+                // `repeat` panics if the length overflows
+                string
+                    .len()
+                    .checked_mul(count)
+                    .ok_or(RuntimeError::IntegerOverflow)?;
+                Ok(Value::String(string.repeat(count)))
+            }
+            (object, name, _) => Err(RuntimeError::UnknownMember {
+                member: name.to_string(),
+                found: object.type_name(),
+            }),
+        }
+    }
+
+    fn index(&mut self, array: &Expression, index: &Expression) -> Result<Value, RuntimeError> {
+        let index = self.integer(index)?;
+        // the array isn't copied if it's in a variable
+        let element = match array {
+            Expression::Ident(name) => element(self.lookup(name)?, index)?,
+            array => element(&self.evaluate(array)?, index)?,
+        };
+        match element {
+            Value::Null => Err(RuntimeError::UninitializedElement(index)),
+            element => Ok(element),
+        }
+    }
+
+    fn slice(
+        &mut self,
+        array: &Expression,
+        start: &Expression,
+        stop: &Expression,
+    ) -> Result<Value, RuntimeError> {
+        let array = self.evaluate(array)?;
+        let (start, stop) = (self.integer(start)?, self.integer(stop)?);
+        slice(array, start, stop)
+    }
+
+    fn unary_op(
+        &mut self,
+        operator: &Operator,
+        operand: &Expression,
+    ) -> Result<Value, RuntimeError> {
+        match (operator, self.evaluate(operand)?) {
+            (Operator::Not, Value::Boolean(boolean)) => Ok(Value::Boolean(!boolean)),
+            (Operator::Minus, Value::Integer(integer)) => integer
+                .checked_neg()
+                .map(Value::Integer)
+                .ok_or(RuntimeError::IntegerOverflow),
+            (Operator::Minus, Value::Real(real)) => Ok(Value::Real(-real)),
+            (Operator::Not, operand) => Err(wrong_type("a boolean", &operand)),
+            (_, operand) => Err(wrong_type("a number", &operand)),
+        }
+    }
+
+    fn binary_op(
+        &mut self,
+        operator: &Operator,
+        lhs: &Expression,
+        rhs: &Expression,
+    ) -> Result<Value, RuntimeError> {
+        match operator {
+            Operator::And | Operator::Or => self.logical_op(operator, lhs, rhs),
+            _ => {
+                let lhs = self.evaluate(lhs)?;
+                let rhs = self.evaluate(rhs)?;
+                operate(operator, lhs, rhs)
+            }
+        }
+    }
+
+    /// Applies `AND` or `OR`, only evaluating the right hand side if it's needed.
+    fn logical_op(
+        &mut self,
+        operator: &Operator,
+        lhs: &Expression,
+        rhs: &Expression,
+    ) -> Result<Value, RuntimeError> {
+        let lhs = self.boolean(lhs)?;
+        Ok(Value::Boolean(match operator {
+            Operator::And => lhs && self.boolean(rhs)?,
+            _ => lhs || self.boolean(rhs)?,
+        }))
+    }
+
+    /// Calls a builtin function, or one of the program's functions.
+    fn call(
+        &mut self,
+        name: &str,
+        args: &[Expression],
+        named_args: &NamedArgs,
+    ) -> Result<Value, RuntimeError> {
+        // the arguments are copies (of any variables which are passed), so changing a `:byVal`
+        // parameter (or an element of one) doesn't change what was passed
+        let values = args
+            .iter()
+            .map(|arg| self.evaluate(arg))
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(value) = self.call_builtin(name, &values, named_args)? {
+            return Ok(value);
+        }
+        let function = *self
+            .functions
+            .get(name)
+            .ok_or_else(|| RuntimeError::UndefinedFunction(name.to_string()))?;
+        self.call_function(function, values, args)
+    }
+
+    /// Calls a builtin function, returning `None` if there isn't a builtin with the name which
+    /// takes that many arguments.
+    fn call_builtin(
+        &mut self,
+        name: &str,
+        args: &[Value],
+        named_args: &NamedArgs,
+    ) -> Result<Option<Value>, RuntimeError> {
+        Ok(Some(match (name, args) {
+            ("print", _) => {
+                let separator = match named_args.iter().find(|(name, _)| name == "separator") {
+                    Some((_, separator)) => self.evaluate(separator)?.to_string(),
+                    None => " ".to_string(),
+                };
+                let line = args
+                    .iter()
+                    .map(Value::to_string)
+                    .collect::<Vec<_>>()
+                    .join(&separator);
+                self.write(&format!("{}\n", line))?;
+                Value::Null
+            }
+            ("input", _) => {
+                if let Some(prompt) = args.first() {
+                    self.write(&prompt.to_string())?;
+                    self.environment.output.flush()?;
+                }
+                self.read_line()?
+            }
+            ("int", [value]) => to_integer(value)?,
+            ("float", [value]) | ("real", [value]) => to_real(value)?,
+            ("SORT", [value]) => sort(value)?,
+            ("RANDOM", []) => Value::Real(self.environment.rng.real()),
+            ("RANDOM_INT", [Value::Integer(low), Value::Integer(high)]) => {
+                if low > high {
+                    return Err(RuntimeError::EmptyRange {
+                        low: *low,
+                        high: *high,
+                    });
+                }
+                Value::Integer(self.environment.rng.integer(*low, *high))
+            }
+            ("MIN", [_, _]) | ("MAX", [_, _]) => aggregate(name, args)?,
+            ("MIN", [Value::Array(elements)])
+            | ("MAX", [Value::Array(elements)])
+            | ("SUM", [Value::Array(elements)]) => aggregate(name, elements)?,
+            ("MIN", [value]) | ("MAX", [value]) | ("SUM", [value]) => {
+                return Err(wrong_type("an array", value))
+            }
+            ("str", [value]) => Value::String(value.to_string()),
+            // as in the Javascript output, anything other than "true" (in any case) is false
+            ("bool", [value]) => match value {
+                Value::Boolean(boolean) => Value::Boolean(*boolean),
+                Value::String(string) => Value::Boolean(string.to_lowercase() == "true"),
+                value => return Err(wrong_type("a string", value)),
+            },
+            _ => return Ok(None),
+        }))
+    }
+
+    /// Calls one of the program's functions with the values of the arguments (`args`). Any
+    /// parameters after the arguments take their default values, which are worked out (inside the
+    /// function) when it's called.
+    fn call_function(
+        &mut self,
+        function: &FunctionDefinition,
+        values: Vec<Value>,
+        args: &[Expression],
+    ) -> Result<Value, RuntimeError> {
+        if values.len() > function.parameters.len()
+            || function.parameters[values.len()..]
+                .iter()
+                .any(|parameter| parameter.default.is_none())
+        {
+            return Err(RuntimeError::WrongNumberOfArguments {
+                function: function.name.clone(),
+                expected: function.parameters.len(),
+                found: values.len(),
+            });
+        }
+        if self.scopes.len() > MAX_CALL_DEPTH {
+            return Err(RuntimeError::TooMuchRecursion);
+        }
+        self.scopes.push(HashMap::new());
+        let result = self.run_function(function, values);
+        let scope = self.scopes.pop().unwrap();
+        self.pass_back(function, args, scope);
+        match result? {
+            Flow::Return(value) => Ok(value),
+            Flow::Next => Ok(Value::Null),
+        }
+    }
+
+    /// Binds the parameters of a function (in its new scope) and runs it.
+    fn run_function(
+        &mut self,
+        function: &FunctionDefinition,
+        args: Vec<Value>,
+    ) -> Result<Flow, RuntimeError> {
+        let mut args = args.into_iter();
+        for parameter in &function.parameters {
+            let value = match (args.next(), &parameter.default) {
+                (Some(value), _) => value,
+                (None, Some(default)) => self.evaluate(default)?,
+                // `call_function` has already checked that there are enough arguments
+                (None, None) => unreachable!(),
+            };
+            self.assign(&parameter.name, value);
+        }
+        self.run_block(&function.block)
+    }
+
+    /// Gives the variables passed to `:byRef` parameters the values the parameters had when the
+    /// function returned (or raised an error), so that the function shares them with its caller.
+    /// An argument which isn't a variable is passed by value.
+    fn pass_back(
+        &mut self,
+        function: &FunctionDefinition,
+        args: &[Expression],
+        mut scope: HashMap<String, Value>,
+    ) {
+        for (parameter, arg) in function.parameters.iter().zip(args) {
+            if let (true, Expression::Ident(variable)) = (parameter.by_ref, arg) {
+                if let Some(value) = scope.remove(&parameter.name) {
+                    self.assign(variable, value);
+                }
+            }
+        }
+    }
+
+    /// Reads a line of input, without the newline at the end of it.
+    fn read_line(&mut self) -> Result<Value, RuntimeError> {
+        let mut line = String::new();
+        if self.environment.input.read_line(&mut line)? == 0 {
+            return Err(RuntimeError::EndOfInput);
+        }
+        let line = line.strip_suffix('\n').unwrap_or(&line);
+        Ok(Value::String(
+            line.strip_suffix('\r').unwrap_or(line).to_string(),
+        ))
+    }
+
+    /// Gives a variable (in the innermost scope) a value.
+    fn assign(&mut self, name: &str, value: Value) {
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(name.to_string(), value);
+    }
+
+    /// Finds the value of a variable (or a constant), which has to have been assigned.
+    fn lookup(&self, name: &str) -> Result<&Value, RuntimeError> {
+        match self
+            .scopes
+            .last()
+            .unwrap()
+            .get(name)
+            .or_else(|| self.constants.get(name))
+        {
+            Some(Value::Null) => Err(RuntimeError::UninitializedVariable(name.to_string())),
+            Some(value) => Ok(value),
+            None => Err(RuntimeError::UndefinedVariable(name.to_string())),
+        }
+    }
+
+    /// Copies the value of a variable (or a constant).
+    fn variable(&self, name: &str) -> Result<Value, RuntimeError> {
+        self.lookup(name).cloned()
+    }
+
+    /// Evaluates an expression which should give an integer.
+    fn integer(&mut self, expression: &Expression) -> Result<i64, RuntimeError> {
+        match self.evaluate(expression)? {
+            Value::Integer(integer) => Ok(integer),
+            value => Err(wrong_type("an integer", &value)),
+        }
+    }
+
+    /// Evaluates an expression which should give a boolean (e.g. the condition of an `if`).
+    fn boolean(&mut self, expression: &Expression) -> Result<bool, RuntimeError> {
+        match self.evaluate(expression)? {
+            Value::Boolean(boolean) => Ok(boolean),
+            value => Err(wrong_type("a boolean", &value)),
+        }
+    }
+
+    /// Writes some of the program's output, unless that would take it over the environment's
+    /// output limit (in which case none of `text` is written).
+    fn write(&mut self, text: &str) -> Result<(), RuntimeError> {
+        self.written += text.len();
+        match self.environment.output_limit {
+            Some(limit) if self.written > limit => Err(RuntimeError::OutputLimitExceeded(limit)),
+            _ => Ok(self.environment.output.write_all(text.as_bytes())?),
+        }
+    }
+}
+
+fn wrong_type(expected: &'static str, found: &Value) -> RuntimeError {
+    RuntimeError::WrongType {
+        expected,
+        found: found.type_name(),
+    }
+}
+
+/// Converts a value to an integer (as `int` does). Reals are rounded towards zero.
+fn to_integer(value: &Value) -> Result<Value, RuntimeError> {
+    match value {
+        Value::Integer(integer) => Ok(Value::Integer(*integer)),
+        // `as` saturates (and turns NaN into zero), so the range has to be checked first
+        Value::Real(real) if real.is_nan() || real.trunc().abs() >= i64::MAX as f64 => {
+            Err(RuntimeError::IntegerOverflow)
+        }
+        Value::Real(real) => Ok(Value::Integer(*real as i64)),
+        Value::String(string) => string
+            .trim()
+            .parse()
+            .map(Value::Integer)
+            .map_err(|_| RuntimeError::InvalidNumber(string.clone())),
+        value => Err(wrong_type("a number or a string", value)),
+    }
+}
+
+/// Converts a value to a real (as `float` and `real` do).
+fn to_real(value: &Value) -> Result<Value, RuntimeError> {
+    match value {
+        Value::String(string) => string
+            .trim()
+            .parse()
+            .map(Value::Real)
+            .map_err(|_| RuntimeError::InvalidNumber(string.clone())),
+        value => value
+            .as_real()
+            .map(Value::Real)
+            .ok_or_else(|| wrong_type("a number or a string", value)),
+    }
+}
+
+/// Applies an operator (other than `AND` or `OR`) to two values.
+fn operate(operator: &Operator, lhs: Value, rhs: Value) -> Result<Value, RuntimeError> {
+    if operator.is_comparison() {
+        compare(operator, &lhs, &rhs).map(Value::Boolean)
+    } else {
+        arithmetic(operator, lhs, rhs)
+    }
+}
+
+/// Applies an arithmetic operator. Integers stay integers, unless they're combined with a real
+/// (or divided with `/`, which always gives a real, whereas `DIV` always gives an integer, rounding
+/// towards zero). Adding anything to a string converts it to a string (as Javascript does).
+fn arithmetic(operator: &Operator, lhs: Value, rhs: Value) -> Result<Value, RuntimeError> {
+    let invalid = |lhs: &Value, rhs: &Value| RuntimeError::InvalidOperands {
+        operator: operator.clone(),
+        lhs: lhs.type_name(),
+        rhs: rhs.type_name(),
+    };
+    Ok(match (operator, lhs, rhs) {
+        (Operator::Plus, Value::String(lhs), rhs) => Value::String(lhs + &rhs.to_string()),
+        (Operator::Plus, lhs, Value::String(rhs)) => Value::String(lhs.to_string() + &rhs),
+        (operator, Value::Integer(lhs), Value::Integer(rhs)) => {
+            let result = match operator {
+                Operator::Plus => lhs.checked_add(rhs),
+                Operator::Minus => lhs.checked_sub(rhs),
+                Operator::Times => lhs.checked_mul(rhs),
+                Operator::IntegerDivide | Operator::Modulo if rhs == 0 => {
+                    return Err(RuntimeError::DivisionByZero)
+                }
+                Operator::IntegerDivide => lhs.checked_div(rhs),
+                Operator::Modulo => lhs.checked_rem(rhs),
+                // `/` always gives a real (so `5 / 2` is 2.5)
+                Operator::Divide => {
+                    return arithmetic(operator, Value::Real(lhs as f64), Value::Integer(rhs))
+                }
+                // a negative power gives a fraction
+                Operator::Power if rhs < 0 => {
+                    return Ok(Value::Real((lhs as f64).powf(rhs as f64)))
+                }
+                Operator::Power => u32::try_from(rhs).ok().and_then(|rhs| lhs.checked_pow(rhs)),
+                _ => {
+                    return Err(invalid(&Value::Integer(lhs), &Value::Integer(rhs)));
+                }
+            };
+            Value::Integer(result.ok_or(RuntimeError::IntegerOverflow)?)
+        }
+        (operator, lhs, rhs) => {
+            let (l, r) = match (lhs.as_real(), rhs.as_real()) {
+                (Some(l), Some(r)) => (l, r),
+                _ => return Err(invalid(&lhs, &rhs)),
+            };
+            Value::Real(match operator {
+                Operator::Divide | Operator::IntegerDivide if r == 0.0 => {
+                    return Err(RuntimeError::DivisionByZero)
+                }
+                Operator::Divide => l / r,
+                Operator::IntegerDivide => return to_integer(&Value::Real(l / r)),
+                Operator::Plus => l + r,
+                Operator::Minus => l - r,
+                Operator::Times => l * r,
+                Operator::Modulo => l % r,
+                Operator::Power => l.powf(r),
+                _ => return Err(invalid(&lhs, &rhs)),
+            })
+        }
+    })
+}
+
+/// Applies a comparison operator. Only numbers (see `Value`'s `PartialOrd`) and strings can be
+/// ordered.
+fn compare(operator: &Operator, lhs: &Value, rhs: &Value) -> Result<bool, RuntimeError> {
+    let ordering = match operator {
+        Operator::Equals | Operator::Comparison => return Ok(lhs == rhs),
+        Operator::NotEquals => return Ok(lhs != rhs),
+        _ if lhs.is_orderable_with(rhs) => lhs.partial_cmp(rhs),
+        _ => {
+            return Err(RuntimeError::InvalidOperands {
+                operator: operator.clone(),
+                lhs: lhs.type_name(),
+                rhs: rhs.type_name(),
+            })
+        }
+    };
+    // nothing is ordered with NaN
+    Ok(ordering.is_some_and(|ordering| match operator {
+        Operator::LessThan => ordering == Ordering::Less,
+        Operator::GreaterThan => ordering == Ordering::Greater,
+        Operator::LessThanOrEqual => ordering != Ordering::Greater,
+        _ => ordering != Ordering::Less,
+    }))
+}
+
+/// Sorts a copy of an array (for `SORT`) into ascending order. All of the elements have to be
+/// numbers, or all of them strings; NaN comes after every other number.
+fn sort(array: &Value) -> Result<Value, RuntimeError> {
+    let mut elements = match array {
+        Value::Array(elements) => elements.clone(),
+        value => return Err(wrong_type("an array", value)),
+    };
+    if let Some(first) = elements.first() {
+        if let Some(other) = elements
+            .iter()
+            .find(|other| !first.is_orderable_with(other))
+        {
+            return Err(RuntimeError::Unsortable {
+                lhs: first.type_name(),
+                rhs: other.type_name(),
+            });
+        }
+    }
+    elements.sort_by(|lhs, rhs| match (lhs.as_real(), rhs.as_real()) {
+        (Some(l), Some(r)) if l.is_nan() || r.is_nan() => l.is_nan().cmp(&r.is_nan()),
+        _ => lhs.partial_cmp(rhs).unwrap(),
+    });
+    Ok(Value::Array(elements))
+}
+
+/// Works out the smallest (`MIN`), largest (`MAX`) or total (`SUM`) of some numbers. The result is
+/// an integer if all of the numbers are, and a real otherwise.
+fn aggregate(function: &str, numbers: &[Value]) -> Result<Value, RuntimeError> {
+    if let Some(value) = numbers.iter().find(|value| value.as_real().is_none()) {
+        return Err(wrong_type("a number", value));
+    }
+    let mut result = match (function, numbers.first()) {
+        ("SUM", _) => Value::Integer(0),
+        (_, Some(first)) => first.clone(),
+        (_, None) => return Err(RuntimeError::EmptyArray(function.to_string())),
+    };
+    for number in numbers {
+        result = match function {
+            "SUM" => arithmetic(&Operator::Plus, result, number.clone())?,
+            "MIN" if number < &result => number.clone(),
+            "MAX" if number > &result => number.clone(),
+            _ => result,
+        };
+    }
+    if numbers
+        .iter()
+        .any(|number| matches!(number, Value::Real(_)))
+    {
+        result = Value::Real(result.as_real().unwrap());
+    }
+    Ok(result)
+}
+
+/// Finds an element of an array (or a character of a string).
+fn element(value: &Value, index: i64) -> Result<Value, RuntimeError> {
+    let out_of_bounds = |length| RuntimeError::IndexOutOfBounds { index, length };
+    match value {
+        Value::Array(elements) => usize::try_from(index)
+            .ok()
+            .and_then(|index| elements.get(index))
+            .cloned()
+            .ok_or_else(|| out_of_bounds(elements.len())),
+        Value::String(string) => usize::try_from(index)
+            .ok()
+            .and_then(|index| string.chars().nth(index))
+            .map(|character| Value::String(character.to_string()))
+            .ok_or_else(|| out_of_bounds(string.chars().count())),
+        value => Err(wrong_type("an array", value)),
+    }
+}
+
+/// Takes the part of an array (or a string) from `start` up to (but not including) `stop`. As in
+/// Javascript, a negative index counts back from the end, and the indexes are clamped to the
+/// length.
+fn slice(value: Value, start: i64, stop: i64) -> Result<Value, RuntimeError> {
+    let range = |length: usize| {
+        let clamp = |index: i64| {
+            let index = if index < 0 {
+                index + length as i64
+            } else {
+                index
+            };
+            index.clamp(0, length as i64) as usize
+        };
+        let start = clamp(start);
+        start..clamp(stop).max(start)
+    };
+    match value {
+        Value::Array(elements) => Ok(Value::Array(elements[range(elements.len())].to_vec())),
+        Value::String(string) => {
+            let characters = string.chars().collect::<Vec<_>>();
+            Ok(Value::String(
+                characters[range(characters.len())].iter().collect(),
+            ))
+        }
+        value => Err(wrong_type("an array", &value)),
+    }
+}
@@ -0,0 +1,50 @@
+//! The random number generator behind `RANDOM` and `RANDOM_INT`.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A (non-cryptographic) random number generator, using the SplitMix64 algorithm. The same seed
+/// always gives the same sequence of numbers, so tests can seed it to get reproducible output.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Default for Rng {
+    /// A generator seeded from the current time.
+    fn default() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_nanos() as u64);
+        Self::seeded(nanos)
+    }
+}
+
+impl Rng {
+    pub fn seeded(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// The next 64 random bits.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A random real from 0 (inclusive) to 1 (exclusive).
+    pub fn real(&mut self) -> f64 {
+        // a double has 53 bits of precision
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// A random integer from `low` to `high` (both inclusive), which mustn't be greater than
+    /// `high`.
+    pub fn integer(&mut self, low: i64, high: i64) -> i64 {
+        // the range can be as big as 2^64, so this is worked out in 128 bits
+        let range = (high as i128 - low as i128 + 1) as u128;
+        let offset = (self.next_u64() as u128 * range) >> 64;
+        (low as i128 + offset as i128) as i64
+    }
+}
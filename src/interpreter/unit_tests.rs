@@ -0,0 +1,418 @@
+//! Unit tests for the interpreter.
+
+use crate::interpreter::{run, Environment, Rng, RuntimeError};
+use crate::lexer::lex;
+use crate::parser::parse;
+use crate::type_checker::lower;
+
+/// Runs a program (without type checking it, so that the interpreter's own errors can be tested).
+fn run_source(source: &str, environment: &mut Environment) -> Result<(), RuntimeError> {
+    let mut source = source.to_string();
+    let mut program = parse(lex(source.as_mut_str()).unwrap()).unwrap();
+    lower(&mut program);
+    run(&program, environment)
+}
+
+/// Runs a program, returning what it printed.
+fn output_of(source: &str) -> Result<String, RuntimeError> {
+    let mut output = Vec::new();
+    run_source(source, &mut Environment::with_output(&mut output))?;
+    Ok(String::from_utf8(output).unwrap())
+}
+
+#[test]
+pub fn test_prints_to_the_output() {
+    assert_eq!(
+        output_of("print(\"Hello\", 42, true)\nprint([1, 2.5, \"three\"])").unwrap(),
+        "Hello 42 true\n[1, 2.5, \"three\"]\n"
+    );
+    assert_eq!(
+        output_of("print(1, 2, separator=\", \")").unwrap(),
+        "1, 2\n"
+    );
+}
+
+#[test]
+pub fn test_runs_loops() {
+    assert_eq!(
+        output_of("for i = 0 to 2\n    print(i)\nnext i").unwrap(),
+        "0\n1\n2\n"
+    );
+    assert_eq!(
+        output_of("for i = 3 to 1 step -2\n    print(i)\nnext i").unwrap(),
+        "3\n1\n"
+    );
+    assert_eq!(
+        output_of("x = 0\nwhile x < 3\n    x += 1\nendwhile\nprint(x)").unwrap(),
+        "3\n"
+    );
+    assert_eq!(
+        output_of("x = 5\ndo\n    x = x - 2\nuntil x < 0\nprint(x)").unwrap(),
+        "-1\n"
+    );
+}
+
+#[test]
+pub fn test_runs_branches() {
+    let source = "x = 7
+if x < 5 then
+    print(\"small\")
+elseif x < 10 then
+    print(\"medium\")
+else
+    print(\"large\")
+endif
+switch x:
+    case 1:
+        print(\"one\")
+    case > 5:
+        print(\"big\")
+    default:
+        print(\"other\")
+endswitch";
+    assert_eq!(output_of(source).unwrap(), "medium\nbig\n");
+}
+
+#[test]
+pub fn test_calls_functions_defined_below() {
+    let source = "print(factorial(5))
+function factorial(n)
+    if n <= 1 then
+        return 1
+    endif
+    return n * factorial(n - 1)
+endfunction";
+    assert_eq!(output_of(source).unwrap(), "120\n");
+}
+
+#[test]
+pub fn test_functions_cannot_see_outer_variables() {
+    let source = "const LIMIT = 3
+x = 1
+function f()
+    print(LIMIT)
+    print(x)
+endfunction
+f()";
+    assert!(matches!(
+        output_of(source),
+        Err(RuntimeError::UndefinedVariable(name)) if name == "x"
+    ));
+}
+
+#[test]
+pub fn test_catches_runtime_errors() {
+    let source = "try
+    print(1 MOD 0)
+catch e
+    print(\"caught: \" + e)
+endtry
+try
+    throw \"oops\"
+catch e
+    print(e)
+endtry";
+    assert_eq!(
+        output_of(source).unwrap(),
+        "caught: division by zero\noops\n"
+    );
+}
+
+#[test]
+pub fn test_uncaught_errors_stop_the_program() {
+    assert!(matches!(
+        output_of("print(\"before\")\nthrow \"oops\"\nprint(\"after\")"),
+        Err(RuntimeError::Thrown(message)) if message == "oops"
+    ));
+    assert!(matches!(
+        output_of("x = 9223372036854775807 + 1"),
+        Err(RuntimeError::IntegerOverflow)
+    ));
+    // the recursive call is inside a few blocks, which all use some of the interpreter's stack
+    let source = "function f(n)
+    for i = 0 to 1
+        if i == 0 then
+            while true
+                return f(n + 1)
+            endwhile
+        endif
+    next i
+endfunction
+f(0)";
+    assert!(matches!(
+        output_of(source),
+        Err(RuntimeError::TooMuchRecursion)
+    ));
+}
+
+#[test]
+pub fn test_indexes_and_slices() {
+    assert_eq!(
+        output_of("a = [1, 2, 3, 4]\nprint(a[1], a[1:3], a[-2:10], a.length)").unwrap(),
+        "2 [2, 3] [3, 4] 4\n"
+    );
+    assert_eq!(
+        output_of("s = \"hello\"\nprint(s[0], s[1:3], s.length)").unwrap(),
+        "h el 5\n"
+    );
+    assert!(matches!(
+        output_of("a = [1, 2]\nprint(a[2])"),
+        Err(RuntimeError::IndexOutOfBounds {
+            index: 2,
+            length: 2
+        })
+    ));
+}
+
+#[test]
+pub fn test_converts_values() {
+    assert_eq!(
+        output_of("print(int(\"42\") + 1, float(\"2.5\"), str(3) + \"!\", bool(\"True\"))")
+            .unwrap(),
+        "43 2.5 3! true\n"
+    );
+    assert!(matches!(
+        output_of("print(int(\"forty\"))"),
+        Err(RuntimeError::InvalidNumber(string)) if string == "forty"
+    ));
+}
+
+#[test]
+pub fn test_reads_input() {
+    let mut output = Vec::new();
+    let mut environment = Environment::with_output(&mut output);
+    environment.input = Box::new("Ada\n".as_bytes());
+    run_source(
+        "name = input(\"Name? \")\nprint(\"Hi \" + name)",
+        &mut environment,
+    )
+    .unwrap();
+    drop(environment);
+    assert_eq!(String::from_utf8(output).unwrap(), "Name? Hi Ada\n");
+}
+
+#[test]
+pub fn test_declared_variables_must_be_assigned_before_they_are_read() {
+    assert!(matches!(
+        output_of("DECLARE count : INTEGER\nprint(count)"),
+        Err(RuntimeError::UninitializedVariable(name)) if name == "count"
+    ));
+    assert_eq!(
+        output_of("DECLARE count : INTEGER\ncount = 3\nprint(count)").unwrap(),
+        "3\n"
+    );
+    // the linter can't tell whether this is assigned, so it has to be checked when it's run
+    let source = "DECLARE name : STRING
+for i = 1 to 2
+    if i == 2 then
+        print(name)
+    endif
+    name = \"Ada\"
+next i";
+    assert_eq!(output_of(source).unwrap(), "Ada\n");
+}
+
+#[test]
+pub fn test_declared_arrays_start_empty() {
+    assert_eq!(
+        output_of("array names[2]\nprint(names.length, names)").unwrap(),
+        "2 [null, null]\n"
+    );
+    assert!(matches!(
+        output_of("array names[2]\nprint(names[1])"),
+        Err(RuntimeError::UninitializedElement(1))
+    ));
+    assert!(matches!(
+        output_of("array names[-1]"),
+        Err(RuntimeError::NegativeSize(-1))
+    ));
+}
+
+#[test]
+pub fn test_integers_equal_reals_with_the_same_value() {
+    assert_eq!(
+        output_of("print(5 == 5.0, 5 != 5.0, 5 == 5.5, [1, 2] == [1.0, 2])").unwrap(),
+        "true false false true\n"
+    );
+    // the type checker rejects this, but values of different types are never equal anyway
+    assert_eq!(output_of("print(5 == \"5\")").unwrap(), "false\n");
+}
+
+#[test]
+pub fn test_orders_numbers_and_strings() {
+    assert_eq!(
+        output_of("print(2 < 2.5, 3.0 >= 3, \"apple\" < \"banana\", \"Z\" < \"a\")").unwrap(),
+        "true true true true\n"
+    );
+    assert!(matches!(
+        output_of("print(true < false)"),
+        Err(RuntimeError::InvalidOperands { .. })
+    ));
+}
+
+#[test]
+pub fn test_sorts_arrays() {
+    assert_eq!(
+        output_of("scores = [10, 9, 2.5, -1]\nprint(SORT(scores), scores)").unwrap(),
+        "[-1, 2.5, 9, 10] [10, 9, 2.5, -1]\n"
+    );
+    assert_eq!(
+        output_of("print(SORT([\"pear\", \"apple\", \"fig\"]))").unwrap(),
+        "[\"apple\", \"fig\", \"pear\"]\n"
+    );
+    assert!(matches!(
+        output_of("print(SORT([1, \"two\", 3]))"),
+        Err(RuntimeError::Unsortable {
+            lhs: "an integer",
+            rhs: "a string"
+        })
+    ));
+}
+
+#[test]
+pub fn test_divides_into_reals_or_integers() {
+    assert_eq!(
+        output_of("print(5 / 2, 4 / 2, 5 DIV 2, -5 DIV 2, 7.5 DIV 2, 5 MOD 2)").unwrap(),
+        "2.5 2 2 -2 3 1\n"
+    );
+    assert!(matches!(
+        output_of("print(5 / 0)"),
+        Err(RuntimeError::DivisionByZero)
+    ));
+    assert!(matches!(
+        output_of("print(5 DIV 0)"),
+        Err(RuntimeError::DivisionByZero)
+    ));
+}
+
+#[test]
+pub fn test_aggregates_numbers() {
+    assert_eq!(
+        output_of("scores = [3, 9, -2, 4]\nprint(MIN(scores), MAX(scores), SUM(scores))").unwrap(),
+        "-2 9 14\n"
+    );
+    assert_eq!(
+        output_of("print(MIN(3, 2), MAX(3, 2), MAX(1, 2.5), SUM([1, 2.5]), SUM([]))").unwrap(),
+        "2 3 2.5 3.5 0\n"
+    );
+    assert!(matches!(
+        output_of("print(MAX([]))"),
+        Err(RuntimeError::EmptyArray(function)) if function == "MAX"
+    ));
+}
+
+/// Rolls a dice ten times (and picks a random real) with a seeded random number generator.
+fn rolls(seed: u64) -> String {
+    let mut output = Vec::new();
+    let mut environment = Environment::with_output(&mut output);
+    environment.rng = Rng::seeded(seed);
+    let source = "for i = 1 to 10
+    roll = RANDOM_INT(1, 6)
+    if roll < 1 OR roll > 6 then
+        throw \"out of range\"
+    endif
+    print(roll)
+next i
+x = RANDOM()
+print(x >= 0 AND x < 1)";
+    run_source(source, &mut environment).unwrap();
+    drop(environment);
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+pub fn test_seeded_random_numbers_are_reproducible() {
+    assert_eq!(rolls(42), rolls(42));
+    assert_ne!(rolls(42), rolls(43));
+    assert!(matches!(
+        output_of("print(RANDOM_INT(6, 1))"),
+        Err(RuntimeError::EmptyRange { low: 6, high: 1 })
+    ));
+}
+
+#[test]
+pub fn test_repeats_strings() {
+    assert_eq!(
+        output_of("n = 3\nprint(\"-\" * 10, n * \"ab\", \"x\" * 0)").unwrap(),
+        "---------- ababab \n"
+    );
+    assert!(matches!(
+        output_of("print(\"-\" * -1)"),
+        Err(RuntimeError::NegativeRepeat(-1))
+    ));
+}
+
+#[test]
+pub fn test_assigns_to_elements() {
+    let source = "grid = [[0, 0], [0, 0]]
+grid[1][0] = 5
+grid[1][0] += 2
+array names[2]
+names[1] = \"Ada\"
+print(grid, names[1])";
+    assert_eq!(output_of(source).unwrap(), "[[0, 0], [7, 0]] Ada\n");
+    assert!(matches!(
+        output_of("a = [1]\na[1] = 2"),
+        Err(RuntimeError::IndexOutOfBounds {
+            index: 1,
+            length: 1
+        })
+    ));
+    assert!(matches!(
+        output_of("array a[1]\na[0] += 1"),
+        Err(RuntimeError::UninitializedElement(0))
+    ));
+}
+
+#[test]
+pub fn test_passes_arrays_by_value() {
+    let source = "function clear(values:byVal)
+    values[0] = 0
+    print(values)
+endfunction
+scores = [3, 4]
+clear(scores)
+print(scores)";
+    assert_eq!(output_of(source).unwrap(), "[0, 4]\n[3, 4]\n");
+}
+
+#[test]
+pub fn test_passes_by_reference() {
+    let source = "function clear(values:byRef)
+    values[0] = 0
+endfunction
+function swap(a:byRef, b:byRef)
+    temp = a
+    a = b
+    b = temp
+endfunction
+scores = [3, 4]
+clear(scores)
+x = 1
+y = 2
+swap(x, y)
+print(scores, x, y)";
+    assert_eq!(output_of(source).unwrap(), "[0, 4] 2 1\n");
+}
+
+#[test]
+pub fn test_stops_at_the_output_limit() {
+    let mut output = Vec::new();
+    let mut environment = Environment::with_output(&mut output);
+    environment.output_limit = Some(10);
+    // the error can't be caught, or the program would keep going
+    let source = "try
+    while true
+        print(\"ab\")
+    endwhile
+catch e
+    print(e)
+endtry";
+    assert!(matches!(
+        run_source(source, &mut environment),
+        Err(RuntimeError::OutputLimitExceeded(10))
+    ));
+    drop(environment);
+    // the line which would have gone over the limit isn't written
+    assert_eq!(String::from_utf8(output).unwrap(), "ab\nab\nab\n");
+}
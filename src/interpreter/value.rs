@@ -0,0 +1,113 @@
+//! The values which a running program works with.
+
+use crate::lexer::escape_string;
+use std::cmp::Ordering;
+use std::fmt;
+
+/// A value, which a variable can hold (or an expression can produce).
+///
+/// Arrays hold their elements directly, so copying a value (e.g. assigning it to another variable)
+/// copies the whole array.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Integer(i64),
+    Real(f64),
+    String(String),
+    Boolean(bool),
+    Array(Vec<Value>),
+    /// The value of a variable which has been declared (with `DECLARE`, or as an element of an
+    /// `array`) but not assigned yet, and what a function which doesn't return anything gives back.
+    /// Reading a variable which is null is an error.
+    Null,
+}
+
+impl Value {
+    /// A description of the type of the value, for error messages (e.g. "an integer").
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Integer(_) => "an integer",
+            Value::Real(_) => "a real",
+            Value::String(_) => "a string",
+            Value::Boolean(_) => "a boolean",
+            Value::Array(_) => "an array",
+            Value::Null => "nothing",
+        }
+    }
+
+    /// Whether the value can be ordered with `other` (which is the case if they're both numbers, or
+    /// both strings).
+    pub fn is_orderable_with(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::String(_), Value::String(_)) => true,
+            (lhs, rhs) => lhs.as_real().is_some() && rhs.as_real().is_some(),
+        }
+    }
+
+    /// The value as a real, if it's a number.
+    pub fn as_real(&self) -> Option<f64> {
+        match self {
+            Value::Integer(integer) => Some(*integer as f64),
+            Value::Real(real) => Some(*real),
+            _ => None,
+        }
+    }
+}
+
+/// Integers and reals are equal if they have the same numeric value (so `5 == 5.0` is true), and
+/// arrays are equal if their elements are. Values of any other different types are never equal
+/// (although the type checker doesn't allow comparing them in the first place, e.g. `5 == "5"`).
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Integer(lhs), Value::Integer(rhs)) => lhs == rhs,
+            (Value::String(lhs), Value::String(rhs)) => lhs == rhs,
+            (Value::Boolean(lhs), Value::Boolean(rhs)) => lhs == rhs,
+            (Value::Array(lhs), Value::Array(rhs)) => lhs == rhs,
+            (Value::Null, Value::Null) => true,
+            (lhs, rhs) => match (lhs.as_real(), rhs.as_real()) {
+                (Some(lhs), Some(rhs)) => lhs == rhs,
+                _ => false,
+            },
+        }
+    }
+}
+
+/// Integers and reals are ordered by their numeric values (with each other too), and strings are
+/// ordered by their characters' code points (so "Z" comes before "a"). Nothing else is ordered (and
+/// neither is NaN).
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Integer(lhs), Value::Integer(rhs)) => Some(lhs.cmp(rhs)),
+            (Value::String(lhs), Value::String(rhs)) => Some(lhs.cmp(rhs)),
+            (lhs, rhs) => lhs.as_real()?.partial_cmp(&rhs.as_real()?),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    /// Outputs the value as `print` does. Strings inside arrays are quoted, so that the elements can
+    /// be told apart.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Integer(integer) => write!(f, "{}", integer),
+            Value::Real(real) => write!(f, "{}", real),
+            Value::String(string) => f.write_str(string),
+            Value::Boolean(boolean) => write!(f, "{}", boolean),
+            Value::Array(elements) => {
+                f.write_str("[")?;
+                for (index, element) in elements.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+                    match element {
+                        Value::String(string) => write!(f, "\"{}\"", escape_string(string))?,
+                        element => write!(f, "{}", element)?,
+                    }
+                }
+                f.write_str("]")
+            }
+            Value::Null => f.write_str("null"),
+        }
+    }
+}
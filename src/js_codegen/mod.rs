@@ -4,8 +4,371 @@
 //! compile the entire codebase to WebAssembly (including compiling LLVM to Wasm) to run it
 //! interactively in the browser.
 
+#[cfg(test)]
+mod unit_tests;
+
+use crate::lexer::{escape_string, Operator, StringPart};
+use crate::parser::{Expression, Statement, SwitchCasePattern};
+use crate::type_checker::Type;
+
+/// Outputs Javascript code for a whole program.
+pub fn program(program: &[Statement]) -> String {
+    let mut output = String::new();
+    for statement in program {
+        output.push_str(&statement.output());
+        output.push('\n');
+    }
+    output
+}
+
 /// A trait for outputting Javascript code from AST nodes.
 trait JSCodegen {
     /// Outputs Javascript code for the AST node.
     fn output(&self) -> String;
 }
+
+impl JSCodegen for Operator {
+    fn output(&self) -> String {
+        match self {
+            Operator::Equals | Operator::Comparison => "===",
+            Operator::NotEquals => "!==",
+            Operator::LessThan => "<",
+            Operator::GreaterThan => ">",
+            Operator::LessThanOrEqual => "<=",
+            Operator::GreaterThanOrEqual => ">=",
+            Operator::And => "&&",
+            Operator::Or => "||",
+            Operator::Not => "!",
+            Operator::Times => "*",
+            Operator::Plus => "+",
+            Operator::Minus => "-",
+            Operator::Divide | Operator::IntegerDivide => "/",
+            Operator::Modulo => "%",
+            Operator::Power => "**",
+            Operator::Increment => "+=",
+            Operator::Assign => "=",
+        }
+        .to_string()
+    }
+}
+
+impl JSCodegen for Expression {
+    fn output(&self) -> String {
+        match self {
+            Expression::Integer(integer) => integer.to_string(),
+            Expression::Float(float) => format!("{:?}", float),
+            Expression::Boolean(boolean) => boolean.to_string(),
+            Expression::String(string) => format!("\"{}\"", escape_string(string)),
+            Expression::InterpolatedString(parts) => {
+                let mut output = "`".to_string();
+                for part in parts {
+                    match part {
+                        StringPart::Literal(literal) => output.push_str(
+                            &literal
+                                .replace('\\', "\\\\")
+                                .replace('`', "\\`")
+                                .replace("${", "\\${")
+                                .replace('\n', "\\n"),
+                        ),
+                        StringPart::Placeholder(name) => output.push_str(&format!("${{{}}}", name)),
+                    }
+                }
+                output.push('`');
+                output
+            }
+            Expression::Ident(ident) => ident.to_string(),
+            Expression::FunctionCall {
+                name,
+                args,
+                named_args,
+                ..
+            } => match (name.as_str(), &args[..]) {
+                ("print", _) => match named_args.iter().find(|(name, _)| name == "separator") {
+                    Some((_, separator)) => format!(
+                        "console.log([{}].join({}))",
+                        output_list(args),
+                        separator.output()
+                    ),
+                    None => format!("console.log({})", output_list(args)),
+                },
+                // `SORT` gives back a sorted copy of the array (as it does in the interpreter),
+                // rather than sorting it in place. `Array.prototype.sort` compares elements as
+                // strings by default (which puts 10 before 9), so it needs a comparator which works
+                // for numbers too
+                ("SORT", [array]) => format!(
+                    "[...{}].sort((a, b) => (a < b ? -1 : a > b ? 1 : 0))",
+                    output_operand(array)
+                ),
+                ("MIN", [array]) => format!("Math.min(...{})", output_operand(array)),
+                ("MAX", [array]) => format!("Math.max(...{})", output_operand(array)),
+                ("MIN", _) => format!("Math.min({})", output_list(args)),
+                ("MAX", _) => format!("Math.max({})", output_list(args)),
+                ("SUM", [array]) => {
+                    format!("{}.reduce((a, b) => a + b, 0)", output_operand(array))
+                }
+                ("input", _) => format!("prompt({})", output_list(args)),
+                ("int", [value]) => format!("parseInt({}, 10)", value.output()),
+                ("float", [value]) | ("real", [value]) => format!("parseFloat({})", value.output()),
+                ("str", [value]) => format!("String({})", value.output()),
+                ("bool", [value]) => {
+                    format!("({}.toLowerCase() === \"true\")", output_operand(value))
+                }
+                ("RANDOM", []) => "Math.random()".to_string(),
+                // a random integer from `low` to `high` (inclusive)
+                ("RANDOM_INT", [low, high]) => format!(
+                    "Math.floor(Math.random() * ({} - {} + 1)) + {}",
+                    output_operand(high),
+                    output_operand(low),
+                    output_operand(low)
+                ),
+                // `type_checker::lower` passes any named arguments to the program's functions by
+                // position (and `print` is the only builtin which takes one)
+                (name, _) => format!("{}({})", name, output_list(args)),
+            },
+            Expression::Member { object, member } => {
+                format!("{}.{}", output_operand(object), member)
+            }
+            Expression::MethodCall {
+                object, name, args, ..
+            } => format!("{}.{}({})", output_operand(object), name, output_list(args)),
+            Expression::Array(elements) => format!("[{}]", output_list(elements)),
+            Expression::Index { array, index } => {
+                format!("{}[{}]", array.output(), index.output())
+            }
+            Expression::Slice { array, start, stop } => format!(
+                "{}.slice({}, {})",
+                array.output(),
+                start.output(),
+                stop.output()
+            ),
+            Expression::UnaryOp { operator, operand } => {
+                format!("{}{}", operator.output(), output_operand(operand))
+            }
+            // Javascript doesn't have integer division, so the result has to be rounded
+            Expression::BinaryOp {
+                operator: Operator::IntegerDivide,
+                lhs,
+                rhs,
+            } => format!(
+                "Math.trunc({} / {})",
+                output_operand(lhs),
+                output_operand(rhs)
+            ),
+            Expression::BinaryOp { operator, lhs, rhs } => format!(
+                "{} {} {}",
+                output_operand(lhs),
+                operator.output(),
+                output_operand(rhs)
+            ),
+        }
+    }
+}
+
+impl JSCodegen for Statement {
+    fn output(&self) -> String {
+        match self {
+            Statement::ForStatement(for_statement) => {
+                let ident = &for_statement.ident;
+                // a (literal) negative step counts down, so the loop has to stop once the variable
+                // drops below `stop` instead
+                let comparison = match &for_statement.step {
+                    Some(Expression::Integer(integer)) if *integer < 0 => ">=",
+                    Some(Expression::UnaryOp {
+                        operator: Operator::Minus,
+                        ..
+                    }) => ">=",
+                    _ => "<=",
+                };
+                let step = for_statement
+                    .step
+                    .as_ref()
+                    .map_or("1".to_string(), JSCodegen::output);
+                format!(
+                    "for (let {ident} = {}; {ident} {} {}; {ident} += {}) {}",
+                    for_statement.start.output(),
+                    comparison,
+                    for_statement.stop.output(),
+                    step,
+                    output_block(&for_statement.block),
+                    ident = ident
+                )
+            }
+            Statement::ForEachStatement(for_each) => format!(
+                "for (const {} of {}) {}",
+                for_each.item,
+                for_each.iterable.output(),
+                output_block(&for_each.block)
+            ),
+            Statement::WhileStatement(while_statement) => format!(
+                "while ({}) {}",
+                while_statement.predicate.output(),
+                output_block(&while_statement.block)
+            ),
+            Statement::IfStatement(if_statement) => {
+                let mut output = std::iter::once(&if_statement.case_if)
+                    .chain(&if_statement.cases_elif)
+                    .map(|case| {
+                        format!(
+                            "if ({}) {}",
+                            case.predicate.output(),
+                            output_block(&case.block)
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" else ");
+                if let Some(case_else) = &if_statement.case_else {
+                    output.push_str(" else ");
+                    output.push_str(&output_block(&case_else.block));
+                }
+                output
+            }
+            Statement::AssignmentStatement(assignment) => format!(
+                "{} {} {};",
+                assignment.ident,
+                match assignment.operator {
+                    // this is an assignment, not a comparison
+                    Operator::Equals => "=".to_string(),
+                    ref operator => operator.output(),
+                },
+                assignment.expression.output()
+            ),
+            Statement::ElementAssignment(assignment) => format!(
+                "{}{} {} {};",
+                assignment.array,
+                assignment
+                    .indexes
+                    .iter()
+                    .map(|index| format!("[{}]", index.output()))
+                    .collect::<String>(),
+                match assignment.operator {
+                    Operator::Equals => "=".to_string(),
+                    ref operator => operator.output(),
+                },
+                assignment.expression.output()
+            ),
+            Statement::DoUntilStatement(do_until) => format!(
+                "do {} while (!({}));",
+                output_block(&do_until.block),
+                do_until.predicate.output()
+            ),
+            // Javascript's `switch` can only check for equality, so this is output as a chain of
+            // `if`s
+            Statement::SwitchStatement(switch) => {
+                let subject = output_operand(&switch.subject);
+                let mut cases = switch
+                    .cases
+                    .iter()
+                    .map(|case| {
+                        let predicate = match &case.pattern {
+                            SwitchCasePattern::Value(value) => {
+                                format!("{} === {}", subject, output_operand(value))
+                            }
+                            SwitchCasePattern::Comparison(operator, value) => format!(
+                                "{} {} {}",
+                                subject,
+                                operator.output(),
+                                output_operand(value)
+                            ),
+                        };
+                        format!("if ({}) {}", predicate, output_block(&case.block))
+                    })
+                    .collect::<Vec<_>>();
+                if let Some(default) = &switch.default {
+                    cases.push(output_block(&default.block));
+                }
+                cases.join(" else ")
+            }
+            Statement::FunctionDefinition(function) => format!(
+                "function {}({}) {}",
+                function.name,
+                function
+                    .parameters
+                    .iter()
+                    .map(|parameter| match &parameter.default {
+                        Some(default) => format!("{} = {}", parameter.name, default.output()),
+                        None => parameter.name.to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                output_block(&function.block)
+            ),
+            Statement::TryStatement(try_statement) => format!(
+                "try {} catch ({}) {}",
+                output_block(&try_statement.body),
+                try_statement.catch_var,
+                output_block(&try_statement.catch_block)
+            ),
+            Statement::Declaration(declaration) => {
+                // the variable is given the "zero" value of its type, so that it's never undefined
+                let value = match Type::builtin(&declaration.ty) {
+                    Some(ty) if ty.is_builtin("String") => "\"\"",
+                    Some(ty) if ty.is_builtin("Boolean") => "false",
+                    Some(ty) if ty.is_builtin("Integer") || ty.is_builtin("Real") => "0",
+                    _ => "undefined",
+                };
+                format!("let {} = {};", declaration.name, value)
+            }
+            // Javascript doesn't have types, so there's nothing to alias
+            Statement::TypeAlias(alias) => format!("// TYPE {} = {}", alias.name, alias.ty),
+            Statement::ConstDeclaration(declaration) => {
+                format!(
+                    "const {} = {};",
+                    declaration.name,
+                    declaration.value.output()
+                )
+            }
+            Statement::ArrayDeclaration(declaration) => {
+                format!(
+                    "let {} = new Array({});",
+                    declaration.name,
+                    declaration.size.output()
+                )
+            }
+            Statement::ReturnStatement(expression) => format!("return {};", expression.output()),
+            Statement::ThrowStatement(expression) => {
+                format!("throw new Error({});", expression.output())
+            }
+            Statement::PassStatement => ";".to_string(),
+            Statement::ExpressionStatement(expression) => format!("{};", expression.output()),
+        }
+    }
+}
+
+/// Outputs a block in curly brackets, indenting each statement inside it.
+fn output_block(block: &[Statement]) -> String {
+    let mut output = "{\n".to_string();
+    for statement in block {
+        for line in statement.output().lines() {
+            output.push_str("    ");
+            output.push_str(line);
+            output.push('\n');
+        }
+    }
+    output.push('}');
+    output
+}
+
+/// Outputs an operand of an operator, bracketing it if it's an operation itself (so that the
+/// precedence in the output is always the same as in the AST). Negative numbers are bracketed
+/// too, because Javascript doesn't allow `-2 ** 2`.
+fn output_operand(operand: &Expression) -> String {
+    match operand {
+        Expression::UnaryOp { .. } | Expression::BinaryOp { .. } => {
+            format!("({})", operand.output())
+        }
+        Expression::Integer(integer) if *integer < 0 => format!("({})", operand.output()),
+        Expression::Float(float) if float.is_sign_negative() => {
+            format!("({})", operand.output())
+        }
+        _ => operand.output(),
+    }
+}
+
+/// Outputs a comma separated list of expressions.
+fn output_list(expressions: &[Expression]) -> String {
+    expressions
+        .iter()
+        .map(JSCodegen::output)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,242 @@
+//! Unit tests for the Javascript code generator.
+
+use crate::js_codegen::JSCodegen;
+use crate::lexer::{lex, Operator, Punctuation, Token};
+use crate::parser::{parse, parse_expression};
+
+/// Lexes the assignment `x = <expression>` and outputs Javascript for the expression.
+fn output_expression(expression: &str) -> String {
+    let mut string = format!("x = {}", expression);
+    let tokens = lex(string.as_mut_str()).unwrap();
+    parse_expression(&tokens[2..]).unwrap().output()
+}
+
+#[test]
+pub fn test_outputs_slice() {
+    assert_eq!(output_expression("arr[1:3]"), "arr.slice(1, 3)");
+}
+
+#[test]
+pub fn test_outputs_conversions() {
+    assert_eq!(output_expression("int(\"42\")"), "parseInt(\"42\", 10)");
+    assert_eq!(output_expression("float(x)"), "parseFloat(x)");
+    assert_eq!(output_expression("real(x)"), "parseFloat(x)");
+    assert_eq!(output_expression("str(42)"), "String(42)");
+    assert_eq!(
+        output_expression("NOT bool(x)"),
+        "!(x.toLowerCase() === \"true\")"
+    );
+}
+
+#[test]
+pub fn test_outputs_index() {
+    assert_eq!(output_expression("arr[i]"), "arr[i]");
+    assert_eq!(output_expression("grid[i][j + 1]"), "grid[i][j + 1]");
+}
+
+#[test]
+pub fn test_outputs_precedence_with_brackets() {
+    let tokens = vec![
+        Token::Punctuation(Punctuation::OpenRoundBracket),
+        Token::Integer(1),
+        Token::Operator(Operator::Plus),
+        Token::Integer(2),
+        Token::Punctuation(Punctuation::CloseRoundBracket),
+        Token::Operator(Operator::Times),
+        Token::Integer(3),
+    ];
+    assert_eq!(parse_expression(&tokens).unwrap().output(), "(1 + 2) * 3");
+}
+
+#[test]
+pub fn test_outputs_comparisons_and_logic() {
+    assert_eq!(output_expression("a == \"b\""), "a === \"b\"");
+    assert_eq!(output_expression("NOT a AND b"), "(!a) && b");
+}
+
+#[test]
+pub fn test_outputs_print_as_console_log() {
+    assert_eq!(output_expression("print(a, 1)"), "console.log(a, 1)");
+}
+
+#[test]
+pub fn test_outputs_try_catch() {
+    let mut string = r#"
+        try
+            x = 1 / 0
+        catch e
+            print(e)
+        endtry
+    "#
+    .to_string();
+    let tokens = lex(string.as_mut_str()).unwrap();
+    let program = parse(tokens).unwrap();
+    assert_eq!(
+        program[0].output(),
+        "try {\n    x = 1 / 0;\n} catch (e) {\n    console.log(e);\n}"
+    );
+}
+
+#[test]
+pub fn test_outputs_throw() {
+    let mut string = "throw \"negative\"".to_string();
+    let tokens = lex(string.as_mut_str()).unwrap();
+    let program = parse(tokens).unwrap();
+    assert_eq!(program[0].output(), "throw new Error(\"negative\");");
+}
+
+#[test]
+pub fn test_outputs_sort() {
+    assert_eq!(
+        output_expression("SORT(scores)"),
+        "[...scores].sort((a, b) => (a < b ? -1 : a > b ? 1 : 0))"
+    );
+    assert_eq!(
+        output_expression("SORT(names)[0]"),
+        "[...names].sort((a, b) => (a < b ? -1 : a > b ? 1 : 0))[0]"
+    );
+}
+
+#[test]
+pub fn test_outputs_aggregates() {
+    assert_eq!(output_expression("MIN(scores)"), "Math.min(...scores)");
+    assert_eq!(output_expression("MAX(scores)"), "Math.max(...scores)");
+    assert_eq!(output_expression("MAX(a, b)"), "Math.max(a, b)");
+    assert_eq!(
+        output_expression("SUM(scores)"),
+        "scores.reduce((a, b) => a + b, 0)"
+    );
+}
+
+#[test]
+pub fn test_outputs_random() {
+    assert_eq!(output_expression("RANDOM()"), "Math.random()");
+    assert_eq!(
+        output_expression("RANDOM_INT(1, n + 1)"),
+        "Math.floor(Math.random() * ((n + 1) - 1 + 1)) + 1"
+    );
+}
+
+#[test]
+pub fn test_outputs_print_separator() {
+    assert_eq!(output_expression("print(a, b)"), "console.log(a, b)");
+    assert_eq!(
+        output_expression("print(a, b, separator=\", \")"),
+        "console.log([a, b].join(\", \"))"
+    );
+}
+
+#[test]
+pub fn test_outputs_declaration() {
+    let mut string = "DECLARE name : STRING\nDECLARE count : INTEGER\n".to_string();
+    let program = parse(lex(string.as_mut_str()).unwrap()).unwrap();
+    assert_eq!(program[0].output(), "let name = \"\";");
+    assert_eq!(program[1].output(), "let count = 0;");
+}
+
+#[test]
+pub fn test_outputs_element_assignment() {
+    let mut string = "names[0] = \"Ada\"\ngrid[i][j] += 1\n".to_string();
+    let program = parse(lex(string.as_mut_str()).unwrap()).unwrap();
+    assert_eq!(program[0].output(), "names[0] = \"Ada\";");
+    assert_eq!(program[1].output(), "grid[i][j] += 1;");
+}
+
+#[test]
+pub fn test_outputs_switch_as_if_chain() {
+    let mut string = r#"
+switch score:
+    case 0:
+        print("nothing")
+    case is != 10:
+        print("some")
+    default:
+        print("full marks")
+endswitch
+"#
+    .to_string();
+    let program = parse(lex(string.as_mut_str()).unwrap()).unwrap();
+    assert_eq!(
+        program[0].output(),
+        "if (score === 0) {\n    console.log(\"nothing\");\n} else if (score !== 10) {\n    console.log(\"some\");\n} else {\n    console.log(\"full marks\");\n}"
+    );
+}
+
+#[test]
+pub fn test_outputs_pass() {
+    let mut string = "if x == 1 then\n    pass\nendif\n".to_string();
+    let program = parse(lex(string.as_mut_str()).unwrap()).unwrap();
+    assert_eq!(program[0].output(), "if (x === 1) {\n    ;\n}");
+}
+
+#[test]
+pub fn test_outputs_division() {
+    assert_eq!(output_expression("5 / 2"), "5 / 2");
+    assert_eq!(output_expression("5 DIV 2"), "Math.trunc(5 / 2)");
+    assert_eq!(
+        output_expression("(a + b) DIV 2"),
+        "Math.trunc((a + b) / 2)"
+    );
+}
+
+#[test]
+pub fn test_outputs_modulo_and_power() {
+    assert_eq!(output_expression("7 MOD 3"), "7 % 3");
+    assert_eq!(output_expression("2 ^ 3 ^ 2"), "2 ** (3 ** 2)");
+    assert_eq!(output_expression("-2 ^ 2"), "-(2 ** 2)");
+}
+
+#[test]
+pub fn test_outputs_interpolated_string() {
+    assert_eq!(
+        output_expression(r#""{name} scored {score}`s""#),
+        "`${name} scored ${score}\\`s`"
+    );
+}
+
+#[test]
+pub fn test_outputs_string_switch() {
+    let mut string = r#"
+switch command:
+    case "start":
+        print("starting")
+    case "stop":
+        print("stopping")
+endswitch
+"#
+    .to_string();
+    let program = parse(lex(string.as_mut_str()).unwrap()).unwrap();
+    assert_eq!(
+        program[0].output(),
+        "if (command === \"start\") {\n    console.log(\"starting\");\n} else if (command === \"stop\") {\n    console.log(\"stopping\");\n}"
+    );
+}
+
+#[test]
+pub fn test_outputs_comparison_as_value() {
+    let mut string = "flag = x > 3\n".to_string();
+    let program = parse(lex(string.as_mut_str()).unwrap()).unwrap();
+    assert_eq!(program[0].output(), "flag = x > 3;");
+}
+
+#[test]
+pub fn test_outputs_const_sized_array() {
+    let mut string = "const N = 5\narray names[N]\n".to_string();
+    let program = parse(lex(string.as_mut_str()).unwrap()).unwrap();
+    assert_eq!(program[0].output(), "const N = 5;");
+    assert_eq!(program[1].output(), "let names = new Array(N);");
+}
+
+#[test]
+pub fn test_outputs_members_and_method_calls() {
+    assert_eq!(output_expression("a[0].b().c[1]"), "a[0].b().c[1]");
+    let mut string = "names.append(\"Ada\")\n".to_string();
+    let program = parse(lex(string.as_mut_str()).unwrap()).unwrap();
+    assert_eq!(program[0].output(), "names.append(\"Ada\");");
+}
+
+#[test]
+pub fn test_outputs_negative_numbers() {
+    assert_eq!(output_expression("3 - -2"), "3 - (-2)");
+    assert_eq!(output_expression("(-2) ^ 2"), "(-2) ** 2");
+}
@@ -4,7 +4,10 @@
 //!
 //! If you submit a bug fix, please add a test which will fail if the bug is present.
 
-use crate::lexer::lex;
+use crate::lexer::{
+    lex, lex_with_options, lex_with_spans, lex_with_stats, Keyword, LexError, LexOptions, LexStats,
+    Loc, Operator, Punctuation, StringPart, Token,
+};
 
 fn assert_parses_ok(string: &str) {
     let mut string = string.to_string();
@@ -24,7 +27,7 @@ fn assert_parses_err(string: &str) {
 #[test]
 pub fn test_lexes_functions() {
     assert_parses_ok(
-        &r#"
+        r#"
         function f(x, y, z)
             return x * y * z
         endfunction
@@ -32,24 +35,45 @@ pub fn test_lexes_functions() {
     );
 }
 
-// Procedures are not currently supported.
-/*
+#[test]
+pub fn test_lexes_default_parameter_values() {
+    assert_parses_ok(
+        r#"
+        function greet(name, greeting = "Hello", times = 1 + 1)
+            return greeting + name
+        endfunction
+        greet("world")
+        greet("world", "Goodbye")
+    "#,
+    );
+}
+
+#[test]
+pub fn test_rejects_non_default_parameter_after_default() {
+    assert_parses_err(
+        r#"
+        function greet(greeting = "Hello", name)
+            return greeting + name
+        endfunction
+    "#,
+    );
+}
+
 #[test]
 pub fn test_lexes_procedure_byref() {
     assert_parses_ok(
-        &r#"
+        r#"
         procedure someFunction12(arg1:byVal, arg2:byRef)
             arg2 += 1
         endprocedure
     "#,
     );
 }
-*/
 
 #[test]
 pub fn test_lexes_while_statement() {
     assert_parses_ok(
-        &r#"
+        r#"
         x = 12
         while x!=13
             x += 1
@@ -61,7 +85,7 @@ pub fn test_lexes_while_statement() {
 #[test]
 pub fn test_lexes_assignment() {
     assert_parses_ok(
-        &r#"
+        r#"
         fourtyTwo = 42
         fiftyFive = 12 + 8 * 3
         string = "string"
@@ -103,10 +127,34 @@ pub fn test_lexes_if_statement_with_complex_expression() {
 }
 
 #[test]
-pub fn test_lexes_if_else() {}
+pub fn test_lexes_if_else() {
+    assert_parses_ok(
+        r#"
+        if x == 1 then
+            print("one")
+        else
+            print("not one")
+        endif
+    "#,
+    );
+}
 
 #[test]
-pub fn test_lexes_if_elif_else() {}
+pub fn test_lexes_if_elif_else() {
+    assert_parses_ok(
+        r#"
+        if x == 1 then
+            print("one")
+        elseif x == 2 then
+            print("two")
+        elseif x == 3 then
+            print("three")
+        else
+            print("something else")
+        endif
+    "#,
+    );
+}
 
 #[test]
 pub fn test_lexes_superfluous_spaces() {}
@@ -114,7 +162,7 @@ pub fn test_lexes_superfluous_spaces() {}
 #[test]
 pub fn test_rejects_invalid_if() {
     assert_parses_err(
-        &r#"
+        r#"
         if then
             print("hello")
         endif
@@ -123,7 +171,941 @@ pub fn test_rejects_invalid_if() {
 }
 
 #[test]
-pub fn test_rejects_invalid_for() {}
+pub fn test_lexes_for_statement() {
+    assert_parses_ok(
+        r#"
+        for i = 0 to 9
+            print(i)
+        next i
+    "#,
+    );
+}
+
+#[test]
+pub fn test_lexes_for_each_statement() {
+    assert_parses_ok(
+        r#"
+        for each item in items
+            print(item)
+        next item
+    "#,
+    );
+}
+
+#[test]
+pub fn test_rejects_for_each_with_mismatched_next() {
+    assert_parses_err(
+        r#"
+        for each item in items
+            print(item)
+        next items
+    "#,
+    );
+}
+
+#[test]
+pub fn test_rejects_invalid_for() {
+    assert_parses_err(
+        r#"
+        for i = 0 to 9
+            print(i)
+        next j
+    "#,
+    );
+}
 
 #[test]
 pub fn test_lexes_indentation() {}
+
+#[test]
+pub fn test_emits_balanced_indentation_tokens() {
+    let mut string = r#"
+        function f(x)
+            if x == 1 then
+                return 2
+            endif
+            return x
+        endfunction
+    "#
+    .to_string();
+    let tokens = lex_with_options(
+        string.as_mut_str(),
+        LexOptions {
+            indentation_tokens: true,
+            ..LexOptions::default()
+        },
+    )
+    .unwrap();
+    let mut depth = 0;
+    for token in &tokens {
+        match token {
+            Token::Indent => depth += 1,
+            Token::Dedent => {
+                assert!(depth > 0, "dedent without a matching indent");
+                depth -= 1
+            }
+            _ => {}
+        }
+    }
+    assert_eq!(depth, 0);
+    assert_eq!(
+        tokens
+            .iter()
+            .filter(|token| **token == Token::Indent)
+            .count(),
+        2
+    );
+}
+
+#[test]
+pub fn test_does_not_emit_indentation_tokens_by_default() {
+    let mut string = r#"
+        function f(x)
+            return x
+        endfunction
+    "#
+    .to_string();
+    let tokens = lex(string.as_mut_str()).unwrap();
+    assert!(!tokens
+        .iter()
+        .any(|token| *token == Token::Indent || *token == Token::Dedent));
+}
+
+#[test]
+pub fn test_lexes_index_and_slice() {
+    assert_parses_ok(
+        r#"
+        first = arr[0]
+        middle = arr[1:3]
+    "#,
+    );
+}
+
+#[test]
+pub fn test_lexes_index_expressions() {
+    let ident = |name: &str| Token::Ident(name.to_string());
+    let open = Token::Punctuation(Punctuation::OpenSquareBracket);
+    let close = Token::Punctuation(Punctuation::CloseSquareBracket);
+    let mut string = "x = arr[0]\ny = grid[i][j]\nz = arr[i + 1]\n".to_string();
+    assert_eq!(
+        lex(string.as_mut_str()).unwrap(),
+        [
+            ident("x"),
+            Token::Operator(Operator::Equals),
+            ident("arr"),
+            open.clone(),
+            Token::Integer(0),
+            close.clone(),
+            ident("y"),
+            Token::Operator(Operator::Equals),
+            ident("grid"),
+            open.clone(),
+            ident("i"),
+            close.clone(),
+            open.clone(),
+            ident("j"),
+            close.clone(),
+            ident("z"),
+            Token::Operator(Operator::Equals),
+            ident("arr"),
+            open,
+            ident("i"),
+            Token::Operator(Operator::Plus),
+            Token::Integer(1),
+            close
+        ]
+    );
+    // the brackets have to match
+    assert_parses_err("x = arr[0\n");
+    assert_parses_err("x = arr0]\n");
+}
+
+#[test]
+pub fn test_lexes_array_literals() {
+    let mut string = "x = [1, f(a, b), [2]]\n".to_string();
+    let tokens = lex(string.as_mut_str()).unwrap();
+    assert_eq!(tokens.len(), 16);
+    assert_eq!(
+        tokens[15],
+        Token::Punctuation(Punctuation::CloseSquareBracket)
+    );
+    assert_parses_ok("for each x in [1, 2, 3]\n    print(x)\nnext x\n");
+    assert_parses_ok("print([1, 2], [])\n");
+}
+
+#[test]
+pub fn test_lexes_const_declarations() {
+    let mut string = "const MAX = 100\n".to_string();
+    assert_eq!(
+        lex(string.as_mut_str()).unwrap(),
+        [
+            Token::Keyword(Keyword::Const),
+            Token::Ident("MAX".to_string()),
+            Token::Operator(Operator::Equals),
+            Token::Integer(100)
+        ]
+    );
+    // `const` is still an ordinary name when it's assigned to
+    let mut string = "const = 1\n".to_string();
+    assert_eq!(
+        lex(string.as_mut_str()).unwrap()[0],
+        Token::Ident("const".to_string())
+    );
+    assert_parses_err("const MAX\n");
+}
+
+#[test]
+pub fn test_lexes_global_assignments() {
+    let mut string =
+        "function reset()\n    global counter = 0\n    counter = 1\nendfunction\n".to_string();
+    let tokens = lex(string.as_mut_str()).unwrap();
+    assert_eq!(
+        tokens[4..11],
+        [
+            Token::Keyword(Keyword::Global),
+            Token::Ident("counter".to_string()),
+            Token::Operator(Operator::Equals),
+            Token::Integer(0),
+            // an ordinary assignment doesn't have the keyword
+            Token::Ident("counter".to_string()),
+            Token::Operator(Operator::Equals),
+            Token::Integer(1)
+        ]
+    );
+    // `global` on its own is still a variable
+    let mut string = "global = 1\n".to_string();
+    assert_eq!(
+        lex(string.as_mut_str()).unwrap()[0],
+        Token::Ident("global".to_string())
+    );
+    assert_parses_err("global counter\n");
+}
+
+#[test]
+pub fn test_lexes_print_and_input() {
+    let ident = |name: &str| Token::Ident(name.to_string());
+    let mut string = "input userName\nprint(x)\nprint x, y\n".to_string();
+    assert_eq!(
+        lex(string.as_mut_str()).unwrap(),
+        [
+            Token::Keyword(Keyword::Input),
+            ident("userName"),
+            // the call form is still a call (so `print` is an identifier)
+            ident("print"),
+            Token::Punctuation(Punctuation::OpenRoundBracket),
+            ident("x"),
+            Token::Punctuation(Punctuation::CloseRoundBracket),
+            Token::Keyword(Keyword::Print),
+            ident("x"),
+            Token::Punctuation(Punctuation::Comma),
+            ident("y")
+        ]
+    );
+    // `input()` is still a call too
+    let mut string = "name = input()\n".to_string();
+    assert_eq!(lex(string.as_mut_str()).unwrap()[2], ident("input"));
+}
+
+#[test]
+pub fn test_lexes_power_operator() {
+    for source in ["result = 2 ^ 10\n", "result = 2^10\n"] {
+        assert_eq!(
+            lex(source.to_string().as_mut_str()).unwrap(),
+            [
+                Token::Ident("result".to_string()),
+                Token::Operator(Operator::Equals),
+                Token::Integer(2),
+                Token::Operator(Operator::Power),
+                Token::Integer(10)
+            ]
+        );
+    }
+}
+
+#[test]
+pub fn test_lexes_hexadecimal_and_binary_integers() {
+    let mut string = "color = 0xFF\nmask = 0b1010\nlow = -0x10 + 0X0a\n".to_string();
+    let tokens = lex(string.as_mut_str()).unwrap();
+    assert_eq!(tokens[2], Token::Integer(255));
+    assert_eq!(tokens[5], Token::Integer(10));
+    assert_eq!(tokens[8], Token::Integer(-16));
+    assert_eq!(tokens[10], Token::Integer(10));
+    for invalid in ["color = 0xZZ\n", "mask = 0b102\n", "color = 0x\n"] {
+        assert!(matches!(
+            lex(invalid.to_string().as_mut_str()),
+            Err(LexError::UnexpectedToken(_))
+        ));
+    }
+}
+
+#[test]
+pub fn test_lexes_input_without_trailing_newline() {
+    let with_newline = "function f(x)\n    if x then\n        return 1\n    endif\nendfunction\n";
+    let tokens = lex(with_newline.to_string().as_mut_str()).unwrap();
+    let without_newline = with_newline.trim_end();
+    assert_eq!(
+        lex(without_newline.to_string().as_mut_str()).unwrap(),
+        tokens
+    );
+    // something other than a newline where one should be is an error rather than a panic
+    assert!(matches!(
+        lex("if x then 1\n    print(x)\nendif\n"
+            .to_string()
+            .as_mut_str()),
+        Err(LexError::UnexpectedToken(_))
+    ));
+    assert!(matches!(
+        lex(
+            "if x then\n    print(x)\nelse if y then\n    print(y)\nendif\n"
+                .to_string()
+                .as_mut_str()
+        ),
+        Err(LexError::UnexpectedToken(_))
+    ));
+}
+
+#[test]
+pub fn test_ignores_byte_order_mark() {
+    let source = "function f(x)\n    return x\nendfunction\n";
+    let with_bom = format!("\u{FEFF}{}", source);
+    let spans = lex_with_spans(with_bom.clone().as_mut_str()).unwrap();
+    assert_eq!(
+        lex(with_bom.clone().as_mut_str()).unwrap(),
+        lex(source.to_string().as_mut_str()).unwrap()
+    );
+    // the mark doesn't take up a column either
+    assert_eq!(spans[0].1.start, Loc::new(1, 1));
+}
+
+#[test]
+pub fn test_lexes_do_until() {
+    let mut string = r#"
+        x = 0
+        do
+            x = x + 1
+        until x == 10
+    "#
+    .to_string();
+    let tokens = lex(string.as_mut_str()).unwrap();
+    assert_eq!(tokens[3], Token::Keyword(Keyword::Do));
+    assert_eq!(
+        tokens[9..],
+        [
+            Token::Keyword(Keyword::Until),
+            Token::Ident("x".to_string()),
+            Token::Operator(Operator::Comparison),
+            Token::Integer(10)
+        ]
+    );
+}
+
+#[test]
+pub fn test_lex_stats() {
+    let mut string = r#"
+        total = 0
+        function f(x)
+            if x == 1 then
+                return 2
+            endif
+            return x
+        endfunction
+        print(f(total))
+    "#
+    .to_string();
+    let (tokens, stats) = lex_with_stats(string.as_mut_str(), LexOptions::default()).unwrap();
+    assert_eq!(
+        stats,
+        LexStats {
+            statements: 6,
+            max_depth: 2
+        }
+    );
+    assert_eq!(tokens, lex(string.as_mut_str()).unwrap());
+}
+
+#[test]
+pub fn test_lexes_try_catch() {
+    let mut string = r#"
+        try
+            x = 1 / 0
+        catch e
+            print(e)
+        endtry
+    "#
+    .to_string();
+    let tokens = lex(string.as_mut_str()).unwrap();
+    assert_eq!(tokens[0], Token::Keyword(Keyword::Try));
+    assert!(tokens.contains(&Token::Keyword(Keyword::Catch)));
+    assert_eq!(tokens.last(), Some(&Token::Keyword(Keyword::EndTry)));
+}
+
+#[test]
+pub fn test_lexes_declared_return_type() {
+    let mut string = r#"
+        function area(r) returns REAL
+            return 3.14 * r * r
+        endfunction
+    "#
+    .to_string();
+    let tokens = lex(string.as_mut_str()).unwrap();
+    assert_eq!(
+        tokens[5..7],
+        [
+            Token::Keyword(Keyword::Returns),
+            Token::Ident("REAL".to_string())
+        ]
+    );
+}
+
+#[test]
+pub fn test_lexes_declaration() {
+    let mut string = "DECLARE count : INTEGER\ncount = 1\n".to_string();
+    let tokens = lex(string.as_mut_str()).unwrap();
+    assert_eq!(
+        tokens[..4],
+        [
+            Token::Keyword(Keyword::Declare),
+            Token::Ident("count".to_string()),
+            Token::Punctuation(Punctuation::Colon),
+            Token::Ident("INTEGER".to_string())
+        ]
+    );
+}
+
+#[test]
+pub fn test_lexes_type_alias() {
+    let mut string = "TYPE Score = INTEGER\n".to_string();
+    let tokens = lex(string.as_mut_str()).unwrap();
+    assert_eq!(
+        tokens[..4],
+        [
+            Token::Keyword(Keyword::Type),
+            Token::Ident("Score".to_string()),
+            Token::Operator(Operator::Equals),
+            Token::Ident("INTEGER".to_string())
+        ]
+    );
+}
+
+#[test]
+pub fn test_skips_whitespace_only_lines_in_blocks() {
+    let lex_source = |source: &str| lex(source.to_string().as_mut_str()).unwrap();
+    let expected = lex_source("function f(x)\n    y = x\n    return y\nendfunction\nprint(f(1))\n");
+    assert_eq!(
+        lex_source("function f(x)\n    y = x\n    \n    return y\nendfunction\nprint(f(1))\n"),
+        expected
+    );
+    assert_eq!(
+        lex_source("function f(x)\n    y = x\n  \n\n        \n    return y\n  \nendfunction\nprint(f(1))\n"),
+        expected
+    );
+}
+
+#[test]
+pub fn test_lexes_large_input() {
+    let program = "function f(x)\n    if x == 1 then\n        return 2\n    endif\n    return x\nendfunction\nprint(f(total))\n";
+    let tokens = lex(program.to_string().as_mut_str()).unwrap();
+    let mut large = program.repeat(200);
+    let expected = (0..200).flat_map(|_| tokens.clone()).collect::<Vec<_>>();
+    assert_eq!(lex(large.as_mut_str()).unwrap(), expected);
+}
+
+#[test]
+pub fn test_lexes_switch_with_comparisons() {
+    let mut string = r#"
+        switch score:
+            case 0:
+                print("nothing")
+            case is != 10:
+                print("some")
+            default:
+                print("full marks")
+        endswitch
+        print(score)
+    "#
+    .to_string();
+    let tokens = lex(string.as_mut_str()).unwrap();
+    assert_eq!(
+        tokens[..4],
+        [
+            Token::Keyword(Keyword::Switch),
+            Token::Ident("score".to_string()),
+            Token::Punctuation(Punctuation::Colon),
+            Token::Keyword(Keyword::Case),
+        ]
+    );
+    let comparison = tokens
+        .iter()
+        .position(|token| *token == Token::Keyword(Keyword::Is))
+        .unwrap();
+    assert_eq!(
+        tokens[comparison + 1..comparison + 4],
+        [
+            Token::Operator(Operator::NotEquals),
+            Token::Integer(10),
+            Token::Punctuation(Punctuation::Colon)
+        ]
+    );
+    assert!(tokens.contains(&Token::Keyword(Keyword::EndSwitch)));
+}
+
+#[test]
+pub fn test_lexes_pass() {
+    let mut string = r#"
+        if x == 1 then
+            pass
+        endif
+    "#
+    .to_string();
+    let tokens = lex(string.as_mut_str()).unwrap();
+    assert_eq!(
+        tokens[4..],
+        [
+            Token::Keyword(Keyword::Then),
+            Token::Keyword(Keyword::Pass),
+            Token::Keyword(Keyword::EndIf),
+        ]
+    );
+}
+
+#[test]
+pub fn test_lexes_integer_division() {
+    let mut string = "x = 5 DIV 2\n".to_string();
+    assert_eq!(
+        lex(string.as_mut_str()).unwrap(),
+        [
+            Token::Ident("x".to_string()),
+            Token::Operator(Operator::Equals),
+            Token::Integer(5),
+            Token::Operator(Operator::IntegerDivide),
+            Token::Integer(2),
+        ]
+    );
+}
+
+#[test]
+pub fn test_lexes_alternative_operator_spellings() {
+    let lex_alternative = |source: &str| {
+        let mut string = source.to_string();
+        lex_with_options(
+            string.as_mut_str(),
+            LexOptions {
+                alternative_operators: true,
+                ..LexOptions::default()
+            },
+        )
+        .unwrap()
+    };
+    assert_eq!(
+        lex_alternative("x = 2 ** 3\n")[2..],
+        [
+            Token::Integer(2),
+            Token::Operator(Operator::Power),
+            Token::Integer(3)
+        ]
+    );
+    assert_eq!(
+        lex_alternative("x = 7 MODULO 3\n")[2..],
+        [
+            Token::Integer(7),
+            Token::Operator(Operator::Modulo),
+            Token::Integer(3)
+        ]
+    );
+    // the usual spellings still work
+    assert_eq!(
+        lex_alternative("x = 2 ^ 3 MOD 3 * 2\n")[2..],
+        [
+            Token::Integer(2),
+            Token::Operator(Operator::Power),
+            Token::Integer(3),
+            Token::Operator(Operator::Modulo),
+            Token::Integer(3),
+            Token::Operator(Operator::Times),
+            Token::Integer(2)
+        ]
+    );
+}
+
+#[test]
+pub fn test_lexes_interpolated_strings() {
+    let mut string = r#"print("x = {x}!")"#.to_string();
+    assert_eq!(
+        lex(string.as_mut_str()).unwrap()[3],
+        Token::InterpolatedString(vec![
+            StringPart::Literal("x = ".to_string()),
+            StringPart::Placeholder("x".to_string()),
+            StringPart::Literal("!".to_string()),
+        ])
+    );
+    // escaped braces are just part of the string
+    let mut string = r#"print("\{x\}")"#.to_string();
+    assert_eq!(
+        lex(string.as_mut_str()).unwrap()[3],
+        Token::String("{x}".to_string())
+    );
+    for unbalanced in [r#"print("{x")"#, r#"print("x}")"#, r#"print("{}")"#] {
+        let mut string = unbalanced.to_string();
+        assert!(matches!(
+            lex(string.as_mut_str()),
+            Err(LexError::UnexpectedToken(_))
+        ));
+    }
+}
+
+#[test]
+pub fn test_lexes_for_statement_ending_in_endfor() {
+    assert_parses_ok(
+        r#"
+        for i = 0 to 9
+            print(i)
+        endfor
+        for each item in items
+            print(item)
+        endfor
+    "#,
+    );
+}
+
+#[test]
+pub fn test_lexes_comments() {
+    let mut string = "// says hello\nprint(\"hello\")\n".to_string();
+    let tokens = lex(string.as_mut_str()).unwrap();
+    assert_eq!(tokens[0], Token::Comment(" says hello".to_string()));
+    assert_eq!(tokens[1], Token::Ident("print".to_string()));
+}
+
+#[test]
+pub fn test_lexes_multi_line_comments() {
+    let mut string = "/* hi */\nprint(\"hello\")\n".to_string();
+    let tokens = lex(string.as_mut_str()).unwrap();
+    assert_eq!(tokens[0], Token::MultiLineComment(" hi ".to_string()));
+    assert_eq!(tokens[1], Token::Ident("print".to_string()));
+    let mut string = "/* says\n   hello\n*/\nprint(\"hello\")\n".to_string();
+    let spans = lex_with_spans(string.as_mut_str()).unwrap();
+    assert_eq!(
+        spans[0].0,
+        Token::MultiLineComment(" says\n   hello\n".to_string())
+    );
+    // the lines inside the comment are counted
+    assert_eq!(spans[0].1.stop, Loc::new(3, 3));
+    assert_eq!(spans[1].1.start, Loc::new(4, 1));
+    // a comment which isn't closed is an error rather than a panic
+    assert!(matches!(
+        lex("/* says hello\nprint(\"hello\")\n".to_string().as_mut_str()),
+        Err(LexError::UnexpectedEndOfInput)
+    ));
+}
+
+#[test]
+pub fn test_rejects_unmatched_brackets() {
+    assert_parses_err("print(1))\n");
+    assert_parses_err("x = (1 + 2))\n");
+}
+
+#[test]
+pub fn test_rejects_missing_punctuation() {
+    assert_parses_err("f(x\n");
+    assert_parses_err("f(x");
+    assert_parses_err("f(");
+    assert_parses_err("DECLARE x INTEGER\n");
+}
+
+#[test]
+pub fn test_lexes_mod_and_div() {
+    let mut string = "x = 10 MOD 3\ny = 10 DIV 3\n".to_string();
+    let tokens = lex(string.as_mut_str()).unwrap();
+    assert_eq!(tokens[3], Token::Operator(Operator::Modulo));
+    assert_eq!(tokens[8], Token::Operator(Operator::IntegerDivide));
+    // an identifier which starts with an operator's name is still an identifier
+    let mut string = "x = MODE + DIVISOR\n".to_string();
+    assert_eq!(
+        lex(string.as_mut_str()).unwrap()[2..],
+        [
+            Token::Ident("MODE".to_string()),
+            Token::Operator(Operator::Plus),
+            Token::Ident("DIVISOR".to_string())
+        ]
+    );
+}
+
+#[test]
+pub fn test_lexes_less_than_and_greater_than() {
+    let mut string = "if x > 5 then\n    print(x)\nendif\n".to_string();
+    assert_eq!(
+        lex(string.as_mut_str()).unwrap()[..5],
+        [
+            Token::Keyword(Keyword::If),
+            Token::Ident("x".to_string()),
+            Token::Operator(Operator::GreaterThan),
+            Token::Integer(5),
+            Token::Keyword(Keyword::Then)
+        ]
+    );
+    let mut string = "if x < 5 then\n    print(x)\nendif\n".to_string();
+    assert_eq!(
+        lex(string.as_mut_str()).unwrap()[2],
+        Token::Operator(Operator::LessThan)
+    );
+    let mut string = "while x < 10\n    x = x + 1\nendwhile\n".to_string();
+    assert_eq!(
+        lex(string.as_mut_str()).unwrap()[2],
+        Token::Operator(Operator::LessThan)
+    );
+}
+
+#[test]
+pub fn test_lexes_less_than_or_equal_as_one_operator() {
+    let mut string = "flag = x <= 10\n".to_string();
+    assert_eq!(
+        lex(string.as_mut_str()).unwrap()[2..],
+        [
+            Token::Ident("x".to_string()),
+            Token::Operator(Operator::LessThanOrEqual),
+            Token::Integer(10)
+        ]
+    );
+    let mut string = "flag = x >= 10\n".to_string();
+    assert_eq!(
+        lex(string.as_mut_str()).unwrap()[3],
+        Token::Operator(Operator::GreaterThanOrEqual)
+    );
+}
+
+#[test]
+pub fn test_lexes_argument_modifiers() {
+    let mut string = "procedure p(a:byRef, b:byVal)\n    a = b\nendprocedure\n".to_string();
+    assert_eq!(
+        lex(string.as_mut_str()).unwrap()[..9],
+        [
+            Token::Keyword(Keyword::Procedure),
+            Token::Ident("p".to_string()),
+            Token::Punctuation(Punctuation::OpenRoundBracket),
+            Token::Ident("a".to_string()),
+            Token::Punctuation(Punctuation::ByRef),
+            Token::Punctuation(Punctuation::Comma),
+            Token::Ident("b".to_string()),
+            Token::Punctuation(Punctuation::ByVal),
+            Token::Punctuation(Punctuation::CloseRoundBracket)
+        ]
+    );
+    assert_parses_ok("function f(arg:byVal)\n    return arg\nendfunction\n");
+}
+
+#[test]
+pub fn test_lexes_negative_numbers() {
+    let mut string = "x = -5\ny = 3 - -2\nz = -1.5\n".to_string();
+    assert_eq!(
+        lex(string.as_mut_str()).unwrap(),
+        [
+            Token::Ident("x".to_string()),
+            Token::Operator(Operator::Equals),
+            Token::Integer(-5),
+            Token::Ident("y".to_string()),
+            Token::Operator(Operator::Equals),
+            Token::Integer(3),
+            Token::Operator(Operator::Minus),
+            Token::Integer(-2),
+            Token::Ident("z".to_string()),
+            Token::Operator(Operator::Equals),
+            Token::Float(-1.5)
+        ]
+    );
+    // a `-` after an operand is a subtraction, and one before a power is applied after it
+    let mut string = "y = 3 -2\nz = -2 ^ 2\n".to_string();
+    assert_eq!(
+        lex(string.as_mut_str()).unwrap()[3..],
+        [
+            Token::Operator(Operator::Minus),
+            Token::Integer(2),
+            Token::Ident("z".to_string()),
+            Token::Operator(Operator::Equals),
+            Token::Operator(Operator::Minus),
+            Token::Integer(2),
+            Token::Operator(Operator::Power),
+            Token::Integer(2)
+        ]
+    );
+}
+
+#[test]
+pub fn test_lexes_single_quoted_strings() {
+    let options = LexOptions {
+        single_quoted_strings: true,
+        ..LexOptions::default()
+    };
+    let mut string = r#"x = 'it\'s'
+y = "say"
+"#
+    .to_string();
+    let tokens = lex_with_options(string.as_mut_str(), options.clone()).unwrap();
+    assert_eq!(
+        tokens[2..5],
+        [
+            Token::Punctuation(Punctuation::SingleQuote),
+            Token::String("it's".to_string()),
+            Token::Punctuation(Punctuation::SingleQuote)
+        ]
+    );
+    assert_eq!(tokens[7], Token::Punctuation(Punctuation::Quote));
+    // each string is output with the quotes it was written with, and lexes back to the same tokens
+    let mut output = format!(
+        "x = {}\ny = {}\n",
+        tokens[2..5]
+            .iter()
+            .map(Token::to_string)
+            .collect::<String>(),
+        tokens[7..10]
+            .iter()
+            .map(Token::to_string)
+            .collect::<String>()
+    );
+    assert_eq!(output, string);
+    assert_eq!(
+        lex_with_options(output.as_mut_str(), options).unwrap(),
+        tokens
+    );
+    // without the option, a single quote doesn't start a string
+    assert_parses_err("x = 'it'\n");
+}
+
+#[test]
+pub fn test_decodes_escape_sequences() {
+    let mut string = r#"x = "a\nb"
+y = "say \"hi\""
+z = "tab\there, backslash\\ and {x}\{"
+"#
+    .to_string();
+    let tokens = lex(string.as_mut_str()).unwrap();
+    assert_eq!(tokens[3], Token::String("a\nb".to_string()));
+    assert_eq!(tokens[8], Token::String("say \"hi\"".to_string()));
+    assert_eq!(
+        tokens[13],
+        Token::InterpolatedString(vec![
+            StringPart::Literal("tab\there, backslash\\ and ".to_string()),
+            StringPart::Placeholder("x".to_string()),
+            StringPart::Literal("{".to_string())
+        ])
+    );
+    // an escape (or a string) which isn't finished is an error rather than a panic
+    for unfinished in [r#"x = "abc\"#, r#"x = "abc"#] {
+        assert!(matches!(
+            lex(unfinished.to_string().as_mut_str()),
+            Err(LexError::UnexpectedEndOfInput)
+        ));
+    }
+}
+
+#[test]
+pub fn test_lexes_exponents() {
+    let mut string = "x = 1e10\ny = 6.02e23\nz = 1.5E-3\n".to_string();
+    let tokens = lex(string.as_mut_str()).unwrap();
+    assert_eq!(tokens[2], Token::Float(1e10));
+    assert_eq!(tokens[5], Token::Float(6.02e23));
+    assert_eq!(tokens[8], Token::Float(1.5e-3));
+    // an `e` which isn't followed by any digits isn't an exponent
+    assert_parses_err("x = 1e\n");
+}
+
+#[test]
+#[allow(clippy::approx_constant)]
+pub fn test_lexes_signed_floats_and_exponents() {
+    let mut string = "x = -3.14\ny = -1.5e-3\nz = 2e+5\nw = 1 - -2E-2\n".to_string();
+    let tokens = lex(string.as_mut_str()).unwrap();
+    assert_eq!(tokens[2], Token::Float(-3.14));
+    assert_eq!(tokens[5], Token::Float(-1.5e-3));
+    assert_eq!(tokens[8], Token::Float(2e5));
+    assert_eq!(
+        tokens[11..],
+        [
+            Token::Integer(1),
+            Token::Operator(Operator::Minus),
+            Token::Float(-2e-2)
+        ]
+    );
+    // the span of a negative number includes its sign
+    let mut string = "y = -1.5e-3\n".to_string();
+    let spans = lex_with_spans(string.as_mut_str()).unwrap();
+    assert_eq!(spans[2].1.start, Loc::new(1, 5));
+    assert_eq!(spans[2].1.stop, Loc::new(1, 12));
+}
+
+#[test]
+pub fn test_lexes_booleans() {
+    let mut string = "x = true\nflag = false\n".to_string();
+    assert_eq!(
+        lex(string.as_mut_str()).unwrap(),
+        [
+            Token::Ident("x".to_string()),
+            Token::Operator(Operator::Equals),
+            Token::Boolean(true),
+            Token::Ident("flag".to_string()),
+            Token::Operator(Operator::Equals),
+            Token::Boolean(false)
+        ]
+    );
+    // an identifier which starts with `true` is still an identifier
+    let mut string = "trueValue = 1\nx = trueValue\n".to_string();
+    assert_eq!(
+        lex(string.as_mut_str()).unwrap()[5],
+        Token::Ident("trueValue".to_string())
+    );
+    assert_parses_err("true = 1\n");
+}
+
+#[test]
+pub fn test_reports_expected_indentation() {
+    let mut string = "x = 1\nif x == 1 then\n    y = 2\n  z = 3\nendif\n".to_string();
+    match lex(string.as_mut_str()) {
+        Err(error @ LexError::IndentationError { .. }) => {
+            assert!(matches!(
+                error,
+                LexError::IndentationError {
+                    expected: 4,
+                    found: 2,
+                    ..
+                }
+            ));
+            assert_eq!(
+                error.to_string(),
+                "expected indentation of 4, found 2 on line 4"
+            );
+        }
+        result => panic!("expected an indentation error, found {:?}", result),
+    }
+}
+
+#[test]
+pub fn test_lexes_trailing_comments() {
+    let mut string = "// sets things up\nx = 1 // set x\nif x == 1 then // check x\n    print(x / 2) // halve it\nendif\n".to_string();
+    let comments = lex(string.as_mut_str())
+        .unwrap()
+        .into_iter()
+        .filter_map(|token| match token {
+            Token::Comment(comment) => Some(comment),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        comments,
+        [" sets things up", " set x", " check x", " halve it"]
+    );
+    let mut string = "x = 1 // set x\ny = 2\n".to_string();
+    let spans = lex_with_spans(string.as_mut_str()).unwrap();
+    assert_eq!(spans[3].0, Token::Comment(" set x".to_string()));
+    // the comment doesn't throw off the location of the next line
+    assert_eq!(spans[4].1.start, Loc::new(2, 1));
+}
+
+#[test]
+pub fn test_lexes_characters_longer_than_a_byte() {
+    let mut string = "// café ☕\nname = \"Zoë\" + \"☕\"\nprint(name)\n".to_string();
+    let spans = lex_with_spans(string.as_mut_str()).unwrap();
+    assert_eq!(spans[0].0, Token::Comment(" café ☕".to_string()));
+    assert_eq!(spans[4].0, Token::String("Zoë".to_string()));
+    assert_eq!(spans[8].0, Token::String("☕".to_string()));
+    // each character takes up one column, however many bytes it is
+    assert_eq!(spans[8].1.start, Loc::new(2, 17));
+    assert_eq!(spans[9].1.start, Loc::new(2, 18));
+    assert_eq!(spans[10].1.start, Loc::new(3, 1));
+}
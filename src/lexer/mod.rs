@@ -5,9 +5,10 @@ mod integration_tests;
 #[cfg(test)]
 mod unit_tests;
 
+use std::fmt;
 use thiserror::Error as ThisError;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Keyword {
     Function,
     EndFunction,
@@ -25,38 +26,82 @@ pub enum Keyword {
     Do,
     Until,
     For,
+    Each,
+    In,
     To,
+    Step,
     Next,
+    EndFor,
     Return,
+    Try,
+    Catch,
+    EndTry,
+    Throw,
+    Returns,
+    Declare,
+    Output,
+    Input,
+    Is,
+    Pass,
+    Const,
+    Array,
+    Procedure,
+    EndProcedure,
+    /// Marks an assignment to a variable outside of the function (`global counter = 0`).
+    Global,
+    /// `print x` (which does the same thing as `print(x)`).
+    Print,
+    /// Gives another name to a type (`TYPE Score = INTEGER`).
+    Type,
 }
 
 #[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq)]
 pub enum Punctuation {
     OpenRoundBracket,
     CloseRoundBracket,
+    OpenSquareBracket,
+    CloseSquareBracket,
     ByRef,
     ByVal,
     Colon,
     Comma,
     Quote,
+    /// The quote around a single quoted string (`'like this'`), which is only allowed with
+    /// `LexOptions::single_quoted_strings`.
+    SingleQuote,
+    /// The dot used to get at a field or method of a value (`point.x`).
+    Dot,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Operator {
     Equals,
     Times,
     Plus,
     Minus,
+    /// Division (`/`), which always gives a real number (so `5 / 2` is `2.5`).
     Divide,
+    /// Integer division (`DIV`), which rounds towards zero (so `5 DIV 2` is `2`).
+    IntegerDivide,
+    /// The remainder after integer division (`MOD`).
+    Modulo,
+    /// Exponentiation (`^`).
+    Power,
     Comparison,
     And,
     Or,
     Not,
     NotEquals,
-    Increment
+    LessThan,
+    GreaterThan,
+    LessThanOrEqual,
+    GreaterThanOrEqual,
+    Increment,
+    /// The arrow used for assignment in the AQA dialect (`count ← count + 1`).
+    Assign,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 /// A single token lexed from the input stream.
 pub enum Token {
     Keyword(Keyword),
@@ -65,21 +110,467 @@ pub enum Token {
     Operator(Operator),
     Integer(i64),
     String(String),
+    /// A string with values substituted into it (`"x = {x}"`).
+    InterpolatedString(Vec<StringPart>),
     Comment(String),
     MultiLineComment(String),
     Float(f64),
+    /// `true` or `false`.
+    Boolean(bool),
+    /// The start of an indented block (only emitted if `LexOptions::indentation_tokens` is set).
+    Indent,
+    /// The end of an indented block (only emitted if `LexOptions::indentation_tokens` is set).
+    Dedent,
+}
+
+/// What sort of token a `Token` is, without its payload (so tokens can be classified without
+/// cloning them).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TokenKind {
+    Keyword,
+    Ident,
+    Punctuation,
+    Operator,
+    Integer,
+    Float,
+    /// Both plain and interpolated strings.
+    String,
+    /// Both single-line and multi-line comments.
+    Comment,
+    Boolean,
+    Indent,
+    Dedent,
+    /// The end of the input (this isn't the kind of any token, but it's what the parser's
+    /// `Cursor::peek_kind` returns when there are no more tokens).
+    Eof,
+}
+
+impl Token {
+    pub fn kind(&self) -> TokenKind {
+        match self {
+            Token::Keyword(_) => TokenKind::Keyword,
+            Token::Ident(_) => TokenKind::Ident,
+            Token::Punctuation(_) => TokenKind::Punctuation,
+            Token::Operator(_) => TokenKind::Operator,
+            Token::Integer(_) => TokenKind::Integer,
+            Token::Float(_) => TokenKind::Float,
+            Token::String(_) | Token::InterpolatedString(_) => TokenKind::String,
+            Token::Comment(_) | Token::MultiLineComment(_) => TokenKind::Comment,
+            Token::Boolean(_) => TokenKind::Boolean,
+            Token::Indent => TokenKind::Indent,
+            Token::Dedent => TokenKind::Dedent,
+        }
+    }
+}
+
+impl fmt::Display for Keyword {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Keyword::Function => "function",
+            Keyword::EndFunction => "endfunction",
+            Keyword::If => "if",
+            Keyword::Then => "then",
+            Keyword::ElseIf => "elseif",
+            Keyword::Else => "else",
+            Keyword::EndIf => "endif",
+            Keyword::Switch => "switch",
+            Keyword::Case => "case",
+            Keyword::Default => "default",
+            Keyword::EndSwitch => "endswitch",
+            Keyword::While => "while",
+            Keyword::EndWhile => "endwhile",
+            Keyword::Do => "do",
+            Keyword::Until => "until",
+            Keyword::For => "for",
+            Keyword::Each => "each",
+            Keyword::In => "in",
+            Keyword::To => "to",
+            Keyword::Step => "step",
+            Keyword::Next => "next",
+            Keyword::EndFor => "endfor",
+            Keyword::Return => "return",
+            Keyword::Try => "try",
+            Keyword::Catch => "catch",
+            Keyword::EndTry => "endtry",
+            Keyword::Throw => "throw",
+            Keyword::Returns => "returns",
+            Keyword::Declare => "DECLARE",
+            Keyword::Output => "OUTPUT",
+            Keyword::Input => "INPUT",
+            Keyword::Is => "is",
+            Keyword::Pass => "pass",
+            Keyword::Const => "const",
+            Keyword::Array => "array",
+            Keyword::Procedure => "procedure",
+            Keyword::EndProcedure => "endprocedure",
+            Keyword::Global => "global",
+            Keyword::Print => "print",
+            Keyword::Type => "TYPE",
+        })
+    }
+}
+
+impl fmt::Display for Punctuation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Punctuation::OpenRoundBracket => "(",
+            Punctuation::CloseRoundBracket => ")",
+            Punctuation::OpenSquareBracket => "[",
+            Punctuation::CloseSquareBracket => "]",
+            Punctuation::ByRef => ":byRef",
+            Punctuation::ByVal => ":byVal",
+            Punctuation::Colon => ":",
+            Punctuation::Comma => ",",
+            Punctuation::Quote => "\"",
+            Punctuation::SingleQuote => "'",
+            Punctuation::Dot => ".",
+        })
+    }
+}
+
+impl Operator {
+    /// Whether the operator compares two values (as opposed to doing arithmetic on them). Inside
+    /// an expression `=` is a comparison.
+    pub fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            Operator::Equals
+                | Operator::Comparison
+                | Operator::NotEquals
+                | Operator::LessThan
+                | Operator::GreaterThan
+                | Operator::LessThanOrEqual
+                | Operator::GreaterThanOrEqual
+        )
+    }
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Operator::Equals => "=",
+            Operator::Times => "*",
+            Operator::Plus => "+",
+            Operator::Minus => "-",
+            Operator::Divide => "/",
+            Operator::IntegerDivide => "DIV",
+            Operator::Modulo => "MOD",
+            Operator::Power => "^",
+            Operator::Comparison => "==",
+            Operator::And => "AND",
+            Operator::Or => "OR",
+            Operator::Not => "NOT",
+            Operator::NotEquals => "!=",
+            Operator::LessThan => "<",
+            Operator::GreaterThan => ">",
+            Operator::LessThanOrEqual => "<=",
+            Operator::GreaterThanOrEqual => ">=",
+            Operator::Increment => "+=",
+            Operator::Assign => "←",
+        })
+    }
+}
+
+/// A piece of an interpolated string.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StringPart {
+    /// Some text which is output as it is.
+    Literal(String),
+    /// The name of a variable whose value is substituted in (the `x` in `"x = {x}"`).
+    Placeholder(String),
+}
+
+impl fmt::Display for StringPart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StringPart::Literal(literal) => f.write_str(&escape_string(literal)),
+            StringPart::Placeholder(name) => write!(f, "{{{}}}", name),
+        }
+    }
+}
+
+impl fmt::Display for Token {
+    /// Outputs the source code for this token.
+    ///
+    /// Note that the quotes around a string are separate tokens (`Punctuation::Quote`), so a
+    /// `Token::String` is output without quotes (but with any special characters escaped).
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Keyword(keyword) => write!(f, "{}", keyword),
+            Token::Ident(ident) => f.write_str(ident),
+            Token::Punctuation(punctuation) => write!(f, "{}", punctuation),
+            Token::Operator(operator) => write!(f, "{}", operator),
+            Token::Integer(integer) => write!(f, "{}", integer),
+            // escaping single quotes too means that the string can go between either kind of quote
+            Token::String(string) => f.write_str(&escape_string(string).replace('\'', "\\'")),
+            Token::InterpolatedString(parts) => {
+                for part in parts {
+                    write!(f, "{}", part)?;
+                }
+                Ok(())
+            }
+            Token::Comment(comment) => write!(f, "//{}", comment),
+            Token::MultiLineComment(comment) => write!(f, "/*{}*/", comment),
+            Token::Float(float) => write!(f, "{:?}", float),
+            Token::Boolean(boolean) => write!(f, "{}", boolean),
+            Token::Indent | Token::Dedent => Ok(()),
+        }
+    }
+}
+
+/// Escapes the characters in `string` which can't appear as they are inside a string literal, so
+/// that the output can be lexed again.
+pub fn escape_string(string: &str) -> String {
+    let mut output = String::with_capacity(string.len());
+    for character in string.chars() {
+        match character {
+            '"' => output.push_str("\\\""),
+            '\n' => output.push_str("\\n"),
+            '\t' => output.push_str("\\t"),
+            '\\' => output.push_str("\\\\"),
+            '{' => output.push_str("\\{"),
+            '}' => output.push_str("\\}"),
+            _ => output.push(character),
+        }
+    }
+    output
+}
+
+/// Splits the contents of a string literal into text and `{placeholders}`, returning `None` if the
+/// braces are unbalanced (or a placeholder doesn't contain a variable name).
+///
+/// Escape sequences in the text are decoded: `\n` is a newline, `\t` is a tab, and `\\`, `\"`,
+/// `\'`, `\{` and `\}` are the characters after the backslash. Any other backslash is kept as it
+/// is.
+fn split_interpolations(string: &str) -> Option<Vec<StringPart>> {
+    let mut parts = vec![];
+    let mut literal = String::new();
+    let mut chars = string.chars().peekable();
+    while let Some(next) = chars.next() {
+        match next {
+            '\\' => match chars.peek() {
+                Some('n') => {
+                    chars.next();
+                    literal.push('\n');
+                }
+                Some('t') => {
+                    chars.next();
+                    literal.push('\t');
+                }
+                Some('\\' | '"' | '\'' | '{' | '}') => literal.push(chars.next().unwrap()),
+                _ => literal.push('\\'),
+            },
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next()? {
+                        '}' => break,
+                        next => name.push(next),
+                    }
+                }
+                let name = name.trim();
+                if name.is_empty() || !name.chars().all(is_identifier_char) {
+                    return None;
+                }
+                if !literal.is_empty() {
+                    parts.push(StringPart::Literal(std::mem::take(&mut literal)));
+                }
+                parts.push(StringPart::Placeholder(name.to_string()));
+            }
+            '}' => return None,
+            next => literal.push(next),
+        }
+    }
+    if !literal.is_empty() {
+        parts.push(StringPart::Literal(literal));
+    }
+    Some(parts)
+}
+
+/// Options which change the lexer's behaviour.
+#[derive(Debug, Clone, Default)]
+pub struct LexOptions {
+    /// Emit `Token::Indent` and `Token::Dedent` at the start and end of each indented block, so
+    /// that block boundaries can be seen in the token stream.
+    pub indentation_tokens: bool,
+    /// Which exam board's pseudocode to accept.
+    pub dialect: Dialect,
+    /// Also accept the spellings of operators used by some textbooks (`**` for `^` and `MODULO`
+    /// for `MOD`).
+    pub alternative_operators: bool,
+    /// Open `if`, `elseif`, `else` and `while` blocks with a colon, as Python does (`if x > 0:`),
+    /// instead of with `then` (or nothing at all).
+    pub colon_blocks: bool,
+    /// Also accept strings in single quotes (`'like this'`). The quotes are lexed as
+    /// `Punctuation::SingleQuote`, so the style can be told apart from (and reproduced as) the
+    /// usual double quotes.
+    pub single_quoted_strings: bool,
+    /// Allow a `do` after the condition of a `while` loop (`while x > 0 do`).
+    pub while_do: bool,
+}
+
+/// The pseudocode of a particular exam board.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum Dialect {
+    /// OCR's pseudocode (this is the default).
+    #[default]
+    Ocr,
+    /// AQA's pseudocode, which assigns using an arrow (`count ← 0`) and can write `elseif` as
+    /// `else if`.
+    Aqa,
 }
 
 pub fn lex(input: &mut str) -> Result<Vec<Token>, LexError> {
-    let mut cursor = Cursor::new(input.to_string());
-    while !cursor.input.is_empty() {
-        cursor.lex_statement()?;
-        cursor.consume_whitespace();
+    lex_with_options(input, LexOptions::default())
+}
+
+pub fn lex_with_options(input: &mut str, options: LexOptions) -> Result<Vec<Token>, LexError> {
+    Ok(lex_to_cursor(input, options)?.output)
+}
+
+/// Some statistics about a lexed program (e.g. for a teaching dashboard).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LexStats {
+    /// The number of statements in the program (including those inside blocks).
+    pub statements: u32,
+    /// How deeply blocks were nested (this is zero if there were no blocks).
+    pub max_depth: u32,
+}
+
+/// Lexes the input, also returning some statistics about it.
+pub fn lex_with_stats(
+    input: &mut str,
+    options: LexOptions,
+) -> Result<(Vec<Token>, LexStats), LexError> {
+    let cursor = lex_to_cursor(input, options)?;
+    Ok((cursor.output, cursor.stats))
+}
+
+/// Lexes the input, pairing each token with the span of source code it came from.
+pub fn lex_with_spans(input: &mut str) -> Result<Vec<(Token, Span)>, LexError> {
+    let cursor = lex_to_cursor(input, LexOptions::default())?;
+    Ok(cursor.output.into_iter().zip(cursor.spans).collect())
+}
+
+/// Lexes the input, keeping track of where each token came from.
+pub fn lex_spanned(input: &mut str, options: LexOptions) -> Result<TokenStream, LexError> {
+    let cursor = lex_to_cursor(input, options)?;
+    Ok(TokenStream {
+        tokens: cursor.output,
+        spans: cursor.spans,
+        restarts: cursor.restarts,
+        options: cursor.options,
+    })
+}
+
+/// Lexes the source code again after it's been edited, reusing the tokens in `stream` (which were
+/// lexed from the source before the edit) up to the start of the first line which `edit` (the part
+/// of the old source which was replaced) touches. `input` is the whole of the new source.
+///
+/// The body of a block is lexed by the statement which opens it, so lexing can only be restarted
+/// at a top-level statement: an edit inside a block is re-lexed from the line which opens the
+/// (outermost) block. Everything after the restart is lexed again, so the result is always the same
+/// as lexing `input` from scratch with `lex_spanned`.
+pub fn relex_from(
+    stream: &TokenStream,
+    edit: Span,
+    input: &mut str,
+) -> Result<TokenStream, LexError> {
+    let restart = stream
+        .restarts
+        .partition_point(|(_, loc)| loc.line <= edit.start.line)
+        .saturating_sub(1);
+    let (index, loc) = match stream.restarts.get(restart) {
+        Some(&(index, loc)) if index > 0 => (index, loc),
+        // the first statement is lexed from the start, in case there's a byte order mark before it
+        _ => return lex_spanned(input, stream.options.clone()),
+    };
+    // the lines before the restart haven't changed, so the restart is at the start of the same
+    // line of the new source (and there are tokens before it, so that isn't the first line)
+    let offset = match input.match_indices('\n').nth(loc.line as usize - 2) {
+        Some((newline, _)) => newline + 1,
+        None => return lex_spanned(input, stream.options.clone()),
+    };
+    let mut cursor = Cursor::new(input[offset..].to_string());
+    cursor.location = loc;
+    cursor.options = stream.options.clone();
+    cursor.lex_statements()?;
+    Ok(TokenStream {
+        tokens: stream.tokens[..index]
+            .iter()
+            .cloned()
+            .chain(cursor.output)
+            .collect(),
+        spans: stream.spans[..index]
+            .iter()
+            .copied()
+            .chain(cursor.spans)
+            .collect(),
+        restarts: stream.restarts[..restart]
+            .iter()
+            .copied()
+            .chain(
+                cursor
+                    .restarts
+                    .into_iter()
+                    .map(|(restart, loc)| (index + restart, loc)),
+            )
+            .collect(),
+        options: cursor.options,
+    })
+}
+
+/// Lexed tokens together with their positions in the source code, so that tools (e.g. an editor)
+/// can work out which token is at a given position.
+#[derive(Debug, Clone)]
+pub struct TokenStream {
+    tokens: Vec<Token>,
+    /// The span of each token (these are in the same order as the tokens, and don't overlap).
+    spans: Vec<Span>,
+    /// Where lexing can be restarted by `relex_from`: the index of the first token of each
+    /// top-level statement which starts a line, and where it starts.
+    restarts: Vec<(usize, Loc)>,
+    /// The options the tokens were lexed with (which `relex_from` lexes with too).
+    options: LexOptions,
+}
+
+impl TokenStream {
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+    pub fn spans(&self) -> &[Span] {
+        &self.spans
+    }
+    /// Finds the token which contains `loc`, returning `None` if `loc` isn't inside a token (e.g.
+    /// if it's in some whitespace).
+    pub fn token_at(&self, loc: Loc) -> Option<&Token> {
+        let index = self.spans.partition_point(|span| span.stop <= loc);
+        match self.spans.get(index) {
+            Some(span) if span.start <= loc => Some(&self.tokens[index]),
+            _ => None,
+        }
+    }
+    /// Returns the tokens which overlap `span`.
+    pub fn tokens_in_span(&self, span: Span) -> &[Token] {
+        let start = self.spans.partition_point(|token| token.stop <= span.start);
+        let stop = self.spans.partition_point(|token| token.start < span.stop);
+        &self.tokens[start..stop.max(start)]
     }
-    Ok(cursor.output)
 }
 
-#[derive(Copy, Clone, Debug)]
+/// Lexes the input, returning the cursor (from which the output can be retrieved).
+fn lex_to_cursor(input: &mut str, options: LexOptions) -> Result<Cursor, LexError> {
+    let mut cursor = Cursor::new(input.to_string());
+    cursor.options = options;
+    cursor.lex_statements()?;
+    Ok(cursor)
+}
+
+/// A position in the source code.
+///
+/// Both `line` and `col` are one-based (as they are in most editors), so the first character of the
+/// input is at `Loc::new(1, 1)`. A tab counts as four columns.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Loc {
     line: u32,
     col: u32,
@@ -89,12 +580,39 @@ impl Loc {
     pub fn new(line: u32, col: u32) -> Self {
         Self { line, col }
     }
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+    pub fn col(&self) -> u32 {
+        self.col
+    }
+}
+
+/// Whether the character can be part of an identifier (e.g. `RANDOM_INT`).
+fn is_identifier_char(next: char) -> bool {
+    next.is_alphanumeric() || next == '_'
 }
 
-#[derive(Debug, Copy, Clone)]
+/// Works out how long the exponent (e.g. `e10` or `E-3`) at the start of `input` is, returning
+/// `None` if there isn't one there.
+fn exponent_len(input: &str) -> Option<usize> {
+    let after_e = input.strip_prefix(['e', 'E'])?;
+    let sign = usize::from(after_e.starts_with(['+', '-']));
+    let digits = after_e[sign..]
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .count();
+    if digits == 0 {
+        return None;
+    }
+    Some(1 + sign + digits)
+}
+
+/// The part of the source code from `start` up to (but not including) `stop`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Span {
-    start: Loc,
-    stop: Loc,
+    pub start: Loc,
+    pub stop: Loc,
 }
 
 impl Span {
@@ -105,19 +623,33 @@ impl Span {
 
 #[derive(Debug, Clone)]
 struct Cursor {
-    pub input: String,
+    /// All of the source code being lexed.
+    pub source: String,
+    /// How far (in bytes) into `source` the cursor is. The cursor moves along the source rather than
+    /// removing what it's lexed from the front of it, so that eating a character doesn't have to
+    /// copy the rest of the source.
+    pub position: usize,
     pub location: Loc,
     pub output: Vec<Token>,
+    /// The span of each token in `output`.
+    pub spans: Vec<Span>,
     pub current_indentation: u32,
-    /// The lexer maintains some internal state about how many opening brackets there are. This is
-    /// useful for the parsing of expressions.
-    pub current_parenthisis: u32,
+    /// The spans of the opening round brackets which haven't been closed yet. This is useful for
+    /// the parsing of expressions.
+    pub open_brackets: Vec<Span>,
+    /// How many blocks the cursor is currently inside.
+    pub current_depth: u32,
+    pub options: LexOptions,
+    pub stats: LexStats,
+    /// The index of the first token of each top-level statement which starts a line, and where it
+    /// starts (see `TokenStream::restarts`).
+    pub restarts: Vec<(usize, Loc)>,
 }
 
 #[derive(Debug, Clone)]
 pub struct SpannedToken {
-    span: Span,
-    token: String,
+    pub span: Span,
+    pub token: String,
 }
 
 impl SpannedToken {
@@ -131,24 +663,66 @@ impl SpannedToken {
 pub enum LexError {
     #[error("didn't expect this token")]
     UnexpectedToken(SpannedToken),
-    #[error("something's up with the indentation")]
-    /// This will be made more intelligible soon.
-    IndentationError,
+    /// A line is indented by the wrong amount (`expected` and `found` are numbers of spaces, with
+    /// a tab counting as four).
+    #[error("expected indentation of {expected}, found {found} on line {}", .loc.line)]
+    IndentationError { expected: u32, found: u32, loc: Loc },
     #[error("the input ended unexpectedly")]
     UnexpectedEndOfInput,
+    #[error("this `)` doesn't have a matching `(`")]
+    UnmatchedBracket(Span),
+    #[error("the `(` opened at line {} isn't closed", .0.start.line)]
+    UnclosedBracket(Span),
+    #[error("a parameter without a default value can't come after one with a default value")]
+    NonDefaultAfterDefault(SpannedToken),
 }
 
 impl Cursor {
     /// Creates a new cursor from a string input.
-    fn new(string: String) -> Self {
+    fn new(mut string: String) -> Self {
+        // editors on Windows sometimes start files with a byte order mark, which isn't part of the
+        // program
+        if string.starts_with('\u{FEFF}') {
+            string.remove(0);
+        }
         Self {
-            input: string,
-            output: vec![],
-            location: Loc::new(0, 0),
+            // tokens are usually a few characters long, so this saves reallocating the output as it
+            // grows (for all but very dense code)
+            output: Vec::with_capacity(string.len() / 4),
+            spans: Vec::with_capacity(string.len() / 4),
+            source: string,
+            position: 0,
+            location: Loc::new(1, 1),
             current_indentation: 0,
-            current_parenthisis: 0,
+            open_brackets: vec![],
+            current_depth: 0,
+            options: LexOptions::default(),
+            stats: LexStats::default(),
+            restarts: vec![],
         }
     }
+    /// The rest of the source code, which hasn't been lexed yet.
+    #[inline(always)]
+    fn input(&self) -> &str {
+        &self.source[self.position..]
+    }
+    /// Lexes top-level statements until the input runs out.
+    fn lex_statements(&mut self) -> Result<(), LexError> {
+        self.skip_blank_lines();
+        while !self.input().is_empty() {
+            // a comment can come after a statement on the same line, but nothing else can, so the
+            // rest of the input can be lexed on its own from the start of any line
+            if self.location.col == 1 {
+                self.restarts.push((self.output.len(), self.location));
+            }
+            // the whole program can be indented (as long as it's indented consistently), so blocks
+            // have to be indented relative to the line which opens them rather than to the margin
+            self.current_indentation = self.count_indents();
+            self.lex_statement()?;
+            self.skip_blank_lines();
+        }
+        Ok(())
+    }
     /// Lexes an application of a function.
     ///
     /// The term "application" originally comes from Alonzo Church's lambda calculus which is a way
@@ -159,10 +733,15 @@ impl Cursor {
         self.lex_identifier()?;
         self.lex_specific_punctuation(Punctuation::OpenRoundBracket)?;
         loop {
-            if self.peek().unwrap() != ')' {
+            if self.peek() != Some(')') {
                 self.lex_expression()?;
 
                 self.consume_spaces();
+                if matches!(self.peek(), None | Some('\n')) {
+                    return Err(LexError::UnclosedBracket(
+                        *self.open_brackets.last().unwrap(),
+                    ));
+                }
                 if self.peek().unwrap() != ')' {
                     self.lex_specific_punctuation(Punctuation::Comma)?;
                 }
@@ -173,55 +752,268 @@ impl Cursor {
         self.lex_specific_punctuation(Punctuation::CloseRoundBracket)?;
         Ok(())
     }
+    /// Builds an `UnexpectedToken` error for whatever comes next in the input (up to the next
+    /// space), so that the error can echo what was actually found.
+    fn unexpected_token(&self) -> LexError {
+        let token = match self.peek_token() {
+            Some(token) if !token.is_empty() => token.to_string(),
+            _ => match self.peek() {
+                Some(next) => next.to_string(),
+                None => return LexError::UnexpectedEndOfInput,
+            },
+        };
+        let start = self.save_loc();
+        let mut stop = start;
+        stop.col += token.chars().count() as u32;
+        LexError::UnexpectedToken(SpannedToken::new(Span::new(start, stop), token))
+    }
+    /// Adds a token (which started at `start` and ends here) to the output.
+    fn push(&mut self, token: Token, start: Loc) {
+        self.output.push(token);
+        self.spans.push(Span::new(start, self.location));
+    }
     /// Retrieves the current location of the cursor.
     fn save_loc(&self) -> Loc {
-        *&self.location
+        self.location
     }
     /// Lexes any assignment.
     /// This includes the use of the "syntactic sugar" `+=`, `*=`  and `-=`.
     fn lex_assignment_statement(&mut self) -> Result<(), LexError> {
         self.lex_identifier()?;
         self.consume_spaces();
-        if self.lex_specific_operator(Operator::Equals).is_err() {
+        if self.options.dialect == Dialect::Aqa && self.starts_with_operator("←") {
+            self.lex_specific_operator(Operator::Assign)?;
+        } else if self.lex_specific_operator(Operator::Equals).is_err() {
             self.lex_specific_operator(Operator::Increment)?;
         };
         self.consume_spaces();
         self.lex_expression()?;
         Ok(())
     }
+    /// Whether the input starts with an assignment (an identifier followed by `=`, `+=` or, in the
+    /// AQA dialect, `←`).
+    fn starts_with_assignment(&self) -> bool {
+        let ident = self
+            .input()
+            .chars()
+            .take_while(|next| is_identifier_char(*next));
+        let rest = self.input()[ident.map(char::len_utf8).sum::<usize>()..].trim_start_matches(' ');
+        (rest.starts_with('=') && !rest.starts_with("=="))
+            || rest.starts_with("+=")
+            || (self.options.dialect == Dialect::Aqa && rest.starts_with('←'))
+    }
+    /// Checks whether the input starts with a function being called (an identifier followed
+    /// directly by an opening bracket).
+    fn starts_with_application(&self) -> bool {
+        let ident = self
+            .input()
+            .chars()
+            .take_while(|next| is_identifier_char(*next));
+        self.input()[ident.map(char::len_utf8).sum::<usize>()..].starts_with('(')
+    }
     /// Lexes any valid statement.
     fn lex_statement(&mut self) -> Result<(), LexError> {
         self.consume_newlines();
         self.consume_spaces();
+        if self.input().starts_with("//") {
+            self.lex_comment();
+            return Ok(());
+        }
+        if self.input().starts_with("/*") {
+            return self.lex_multi_line_comment();
+        }
+        self.stats.statements += 1;
         if let Some(token) = self.peek_token() {
-            if token.contains('(') {
+            if self.starts_with_application() {
                 self.lex_application()?;
                 return Ok(());
             }
             match token {
                 "function" => self.lex_function()?,
+                "procedure" => self.lex_procedure()?,
                 "if" => self.lex_if_statement()?,
                 "switch" => self.lex_switch_statement()?,
                 "while" => self.lex_while_statement()?,
+                "do" if !self.starts_with_assignment() => self.lex_do_statement()?,
                 "for" => self.lex_for_statement()?,
                 "return" => self.lex_return_statement()?,
-                _ => self.lex_assignment_statement()?,
+                "try" => self.lex_try_statement()?,
+                "throw" => self.lex_throw_statement()?,
+                "DECLARE" => self.lex_declaration()?,
+                "TYPE" => self.lex_type_alias()?,
+                "OUTPUT" => self.lex_output_statement()?,
+                "INPUT" => self.lex_input_statement()?,
+                // `print(x)` and `input()` are function calls, which were lexed above
+                "print" if !self.starts_with_assignment() => self.lex_print_statement()?,
+                "input" if !self.starts_with_assignment() => self.lex_input_statement()?,
+                "pass" => self.lex_specific_keyword(Keyword::Pass)?,
+                "const" if !self.starts_with_assignment() => self.lex_const_declaration()?,
+                "global" if !self.starts_with_assignment() => self.lex_global_assignment()?,
+                "array" if !self.starts_with_assignment() => self.lex_array_declaration()?,
+                // `true` and `false` are values, so they can't be assigned to
+                _ if (self.starts_with_word("true") || self.starts_with_word("false"))
+                    && self.starts_with_assignment() =>
+                {
+                    return Err(self.unexpected_token())
+                }
+                _ if self.starts_with_assignment() => self.lex_assignment_statement()?,
+                // an expression on its own (e.g. the value a function returns when using
+                // `ParseOptions::implicit_returns`)
+                _ => self.lex_expression_statement()?,
             };
             Ok(())
         } else {
             Err(LexError::UnexpectedEndOfInput)
         }
     }
+    /// Lexes an expression on its own, which has to take up the rest of the line.
+    fn lex_expression_statement(&mut self) -> Result<(), LexError> {
+        self.lex_expression()?;
+        self.consume_spaces();
+        match self.peek() {
+            None | Some('\n') => Ok(()),
+            Some(_) if self.input().starts_with("//") => Ok(()),
+            Some(_) => Err(self.unexpected_token()),
+        }
+    }
+    /// Lexes a comment which takes up the rest of the line (`// like this`). This can be on a line of
+    /// its own, or after a statement (in which case it comes after the statement's tokens).
+    fn lex_comment(&mut self) {
+        let start = self.save_loc();
+        self.eat();
+        self.eat();
+        let mut comment = String::new();
+        while let Some(next) = self.peek() {
+            if next == '\n' {
+                break;
+            }
+            comment.push(next);
+            self.eat();
+        }
+        self.push(Token::Comment(comment), start);
+    }
+    /// Lexes a comment which starts with `/*` and goes on (possibly over several lines) until the
+    /// next `*/`.
+    fn lex_multi_line_comment(&mut self) -> Result<(), LexError> {
+        let start = self.save_loc();
+        self.eat();
+        self.eat();
+        let mut comment = String::new();
+        while !self.input().starts_with("*/") {
+            comment.push(self.eat().ok_or(LexError::UnexpectedEndOfInput)?);
+        }
+        self.eat();
+        self.eat();
+        self.push(Token::MultiLineComment(comment), start);
+        Ok(())
+    }
     fn lex_return_statement(&mut self) -> Result<(), LexError> {
         self.lex_specific_keyword(Keyword::Return)?;
         self.consume_spaces();
         self.lex_expression()?;
         Ok(())
     }
+    /// Lexes a `throw <message>` statement.
+    fn lex_throw_statement(&mut self) -> Result<(), LexError> {
+        self.lex_specific_keyword(Keyword::Throw)?;
+        self.consume_spaces();
+        self.lex_expression()?;
+        Ok(())
+    }
+    /// Lexes a `DECLARE <ident> : <TYPE>` statement.
+    fn lex_declaration(&mut self) -> Result<(), LexError> {
+        self.lex_specific_keyword(Keyword::Declare)?;
+        self.consume_spaces();
+        self.lex_identifier()?;
+        self.consume_spaces();
+        self.lex_specific_punctuation(Punctuation::Colon)?;
+        self.consume_spaces();
+        self.lex_identifier()?;
+        Ok(())
+    }
+    /// Lexes a `TYPE <ident> = <TYPE>` statement.
+    fn lex_type_alias(&mut self) -> Result<(), LexError> {
+        self.lex_specific_keyword(Keyword::Type)?;
+        self.consume_spaces();
+        self.lex_identifier()?;
+        self.consume_spaces();
+        self.lex_specific_operator(Operator::Equals)?;
+        self.consume_spaces();
+        self.lex_identifier()?;
+        Ok(())
+    }
+    /// Lexes a `global <ident> = <expression>` statement (an assignment to a global variable).
+    fn lex_global_assignment(&mut self) -> Result<(), LexError> {
+        self.lex_specific_keyword(Keyword::Global)?;
+        self.consume_spaces();
+        if !self.starts_with_assignment() {
+            return Err(self.unexpected_token());
+        }
+        self.lex_assignment_statement()
+    }
+    /// Lexes a `const <ident> = <expression>` statement.
+    fn lex_const_declaration(&mut self) -> Result<(), LexError> {
+        self.lex_specific_keyword(Keyword::Const)?;
+        self.consume_spaces();
+        self.lex_identifier()?;
+        self.consume_spaces();
+        self.lex_specific_operator(Operator::Equals)?;
+        self.consume_spaces();
+        self.lex_expression_statement()
+    }
+    /// Lexes an `array <ident>[<size>]` statement.
+    fn lex_array_declaration(&mut self) -> Result<(), LexError> {
+        self.lex_specific_keyword(Keyword::Array)?;
+        self.consume_spaces();
+        self.lex_identifier()?;
+        if self.peek() != Some('[') {
+            return Err(self.unexpected_token());
+        }
+        // the brackets around the size are lexed along with it, just as they are when indexing
+        self.lex_expression_statement()
+    }
+    /// Lexes an `OUTPUT <expression>, <expression>, ...` statement.
+    fn lex_output_statement(&mut self) -> Result<(), LexError> {
+        self.lex_specific_keyword(Keyword::Output)?;
+        self.consume_spaces();
+        self.lex_expression()?;
+        while self.peek() == Some(',') {
+            self.lex_specific_punctuation(Punctuation::Comma)?;
+            self.consume_spaces();
+            self.lex_expression()?;
+        }
+        Ok(())
+    }
+    /// Lexes a `print <expression>, ...` statement (without brackets around the arguments).
+    fn lex_print_statement(&mut self) -> Result<(), LexError> {
+        self.lex_specific_keyword(Keyword::Print)?;
+        self.consume_spaces();
+        self.lex_expression()?;
+        while self.peek() == Some(',') {
+            self.lex_specific_punctuation(Punctuation::Comma)?;
+            self.consume_spaces();
+            self.lex_expression()?;
+        }
+        Ok(())
+    }
+    /// Lexes an `INPUT <ident>` (or `input <ident>`) statement.
+    fn lex_input_statement(&mut self) -> Result<(), LexError> {
+        if self.starts_with_word("input") {
+            let start = self.save_loc();
+            for _ in 0.."input".len() {
+                self.eat();
+            }
+            self.push(Token::Keyword(Keyword::Input), start);
+        } else {
+            self.lex_specific_keyword(Keyword::Input)?;
+        }
+        self.consume_spaces();
+        self.lex_identifier()?;
+        Ok(())
+    }
     fn count_indents(&self) -> u32 {
         let mut count = 0;
-        let mut iterator = self.input.chars();
-        while let Some(next) = iterator.next() {
+        for next in self.input().chars() {
             if next == ' ' {
                 count += 1;
             } else if next == '\t' {
@@ -234,22 +1026,85 @@ impl Cursor {
     }
 
     /// Lexes code in an indented block.
+    ///
+    /// The block ends at the first line which is indented less than the block is; this line should
+    /// belong to an enclosing block (which will check its indentation).
     fn lex_block(&mut self) -> Result<(), LexError> {
-        self.current_indentation += self.count_indents();
+        let outer_indentation = self.current_indentation;
+        let indentation = self.count_indents();
+        if indentation <= outer_indentation {
+            return Err(self.block_indentation_error(outer_indentation, indentation));
+        }
+        self.current_indentation = indentation;
+        self.current_depth += 1;
+        self.stats.max_depth = self.stats.max_depth.max(self.current_depth);
+        if self.options.indentation_tokens {
+            self.push(Token::Indent, self.location);
+        }
         loop {
+            self.skip_blank_lines();
             let indents = self.count_indents();
-            if indents == self.current_indentation {
+            if indents == indentation {
                 self.consume_spaces();
                 self.lex_statement()?;
                 self.lex_newline()?;
+            } else if indents > outer_indentation && indents < indentation {
+                // this line is indented less than the block, but more than the enclosing one
+                return Err(LexError::IndentationError {
+                    expected: indentation,
+                    found: indents,
+                    loc: self.save_loc(),
+                });
+            } else if indents < indentation {
+                self.current_indentation = outer_indentation;
+                self.current_depth -= 1;
+                if self.options.indentation_tokens {
+                    self.push(Token::Dedent, self.location);
+                }
+                return Ok(());
             } else {
-                return if indents == (self.current_indentation - 2)
-                    || indents == (self.current_indentation - 4)
-                {
-                    return Ok(());
-                } else {
-                    Err(LexError::IndentationError)
-                };
+                return Err(LexError::IndentationError {
+                    expected: indentation,
+                    found: indents,
+                    loc: self.save_loc(),
+                });
+            }
+        }
+    }
+    /// Lexes the keyword which closes a block (e.g. `endwhile`). If the input has already ended
+    /// this does nothing, so that the parser can report which block wasn't closed (see
+    /// `ParseError::UnclosedBlock`).
+    fn lex_block_terminator(&mut self, keyword: Keyword) -> Result<(), LexError> {
+        self.consume_spaces();
+        if self.input().is_empty() {
+            return Ok(());
+        }
+        self.lex_specific_keyword(keyword)
+    }
+    /// The error for a block which isn't indented more than the line which opened it.
+    ///
+    /// Any amount of extra indentation is allowed, but four spaces is what's usually used, so that's
+    /// what the error asks for.
+    fn block_indentation_error(&self, outer_indentation: u32, found: u32) -> LexError {
+        LexError::IndentationError {
+            expected: outer_indentation + 4,
+            found,
+            loc: self.save_loc(),
+        }
+    }
+    /// Skips over lines which are empty or only contain whitespace, so that they don't affect where
+    /// a block is found to end.
+    fn skip_blank_lines(&mut self) {
+        loop {
+            let line = match self.input().find('\n') {
+                Some(end) => &self.input()[..=end],
+                None => self.input(),
+            };
+            if line.is_empty() || !line.chars().all(char::is_whitespace) {
+                return;
+            }
+            for _ in 0..line.chars().count() {
+                self.eat();
             }
         }
     }
@@ -282,26 +1137,40 @@ impl Cursor {
     /// Returns `None` if there are no more tokens in the stream.
     #[inline(always)]
     fn peek(&self) -> Option<char> {
-        self.input.chars().next()
+        self.input().chars().next()
+    }
+    /// Checks whether the input starts with `word` as a whole word (i.e. not followed by a
+    /// character which could continue an identifier), so that `ANDREW` isn't read as `AND`
+    /// followed by `REW`.
+    fn starts_with_word(&self, word: &str) -> bool {
+        self.input().starts_with(word)
+            && !self.input()[word.len()..].starts_with(is_identifier_char)
+    }
+    /// Checks whether the input starts with the given operator. Operators which are words (e.g.
+    /// `AND`) have to be whole words.
+    fn starts_with_operator(&self, operator: &str) -> bool {
+        if operator.chars().all(char::is_alphabetic) {
+            self.starts_with_word(operator)
+        } else {
+            self.input().starts_with(operator)
+        }
     }
     /// Retrieves the next "token" (anything up to the next space).
     #[inline(always)]
     fn peek_token(&self) -> Option<&str> {
-        self.input.split(|item| item == ' ' || item == '\n').next()
+        self.input().split([' ', '\n']).next()
     }
     /// Removes the next character and advances the position of the cursor.
     ///
     /// Returns `None` if there are no more tokens in the stream.
     #[inline(always)]
     fn eat(&mut self) -> Option<char> {
-        if self.input.is_empty() {
-            return None;
-        }
-        let result = self.input.remove(0);
+        let result = self.input().chars().next()?;
+        self.position += result.len_utf8();
         // increment location pointer
         if result == '\n' {
             self.location.line += 1;
-            self.location.col = 0;
+            self.location.col = 1;
         } else if result == '\t' {
             self.location.col += 4;
         } else {
@@ -314,21 +1183,17 @@ impl Cursor {
             ($self:ident, $( [ $string:expr => $keyword:ident ] ),+) => {
                 match keyword {
                     $($crate::lexer::Keyword::$keyword => {
-                        let start = $self.save_loc();
-                        if self.input.starts_with($string) {
+                        if $self.starts_with_word($string) {
+                            let start = $self.save_loc();
                             for _ in 0..$string.len() {
                                 self.eat();
                             }
-                            $self.output.push($crate::lexer::Token::Keyword(
+                            $self.push($crate::lexer::Token::Keyword(
                                 $crate::lexer::Keyword::$keyword
-                            ));
+                            ), start);
                             return Ok(())
                         } else {
-                            return Err($crate::lexer::LexError::UnexpectedToken(
-                                $crate::lexer::SpannedToken::new(
-                                    $crate::lexer::Span::new(start, $self.save_loc()), "".to_string()
-                                )
-                            ))
+                            return Err($self.unexpected_token())
                         }
                     })+
                 }
@@ -352,52 +1217,81 @@ impl Cursor {
             ["do" => Do],
             ["until" => Until],
             ["for" => For],
+            ["each" => Each],
+            ["in" => In],
             ["to" => To],
+            ["step" => Step],
             ["next" => Next],
-            ["return" => Return]
+            ["endfor" => EndFor],
+            ["return" => Return],
+            ["try" => Try],
+            ["catch" => Catch],
+            ["endtry" => EndTry],
+            ["throw" => Throw],
+            ["returns" => Returns],
+            ["DECLARE" => Declare],
+            ["OUTPUT" => Output],
+            ["INPUT" => Input],
+            ["is" => Is],
+            ["pass" => Pass],
+            ["const" => Const],
+            ["array" => Array],
+            ["procedure" => Procedure],
+            ["endprocedure" => EndProcedure],
+            ["global" => Global],
+            ["print" => Print],
+            ["TYPE" => Type]
         )
     }
-    fn lex_identifier(&mut self) -> Result<(), LexError> {
+    /// Lexes an identifier, returning its name.
+    fn lex_identifier(&mut self) -> Result<String, LexError> {
+        // digits can appear in an identifier (`item2`), but not at the start of one
+        if !self
+            .peek()
+            .is_some_and(|next| next.is_alphabetic() || next == '_')
+        {
+            return Err(self.unexpected_token());
+        }
+        let start = self.save_loc();
         let mut output = String::new();
         while let Some(next) = self.peek() {
-            if next.is_alphanumeric() {
+            if is_identifier_char(next) {
                 output.push(next);
                 self.eat();
             } else {
-                self.output.push(Token::Ident(output));
-                return Ok(());
+                break;
             }
         }
-        self.output.push(Token::Ident(output));
-        Ok(())
+        self.push(Token::Ident(output.clone()), start);
+        Ok(output)
     }
     /// Lexes the specified item of punctuation.
-    /// Note that this function will panic if it is used to lex a closing bracket if there is no
-    /// matching opening bracket (if `current_parenthisis` is none).
     fn lex_specific_punctuation(&mut self, punctuation: Punctuation) -> Result<(), LexError> {
         macro_rules! punctuation {
             ($self:ident, $punctuation:ident, $(($string:expr => $punct:ident)),+) => {
                 match $punctuation {
                     $(
                         $crate::lexer::Punctuation::$punct => {
-                            if $self.input.starts_with($string) {
+                            if $self.input().starts_with($string) {
+                                let start = $self.save_loc();
                                 for _ in 0..$string.len() {
                                     self.eat();
                                 }
-                                $self.output.push(
+                                $self.push(
                                     $crate::lexer::Token::Punctuation(
                                         $crate::lexer::Punctuation::$punct
-                                    )
+                                    ),
+                                    start
                                 );
                                 if $crate::lexer::Punctuation::$punct == $crate::lexer::Punctuation::OpenRoundBracket {
-                                    $self.current_parenthisis += 1;
+                                    let span = Span::new(start, $self.save_loc());
+                                    $self.open_brackets.push(span);
                                 } else if $crate::lexer::Punctuation::$punct == $crate::lexer::Punctuation::CloseRoundBracket {
-                                    $self.current_parenthisis -= 1;
+                                    $self.open_brackets.pop();
                                 }
                                 return Ok(())
                             } else {
-                                // todo fix this
-                                panic!("expected token")
+                                return Err($self.unexpected_token())
                             }
                         }
                     )*
@@ -407,39 +1301,70 @@ impl Cursor {
         punctuation!(self, punctuation,
             ("(" => OpenRoundBracket),
             (")" => CloseRoundBracket),
+            ("[" => OpenSquareBracket),
+            ("]" => CloseSquareBracket),
             (":byRef" => ByRef),
             (":byVal" => ByVal),
             (":" => Colon),
             ("," => Comma),
-            ("\"" => Quote)
+            ("\"" => Quote),
+            ("'" => SingleQuote),
+            ("." => Dot)
         );
-        Ok(())
     }
     /// Lexes `argument:byRef` and `argument:byVal`
     fn lex_optional_argument_modifier(&mut self) -> Result<(), LexError> {
-        if let Some(next) = self.peek() {
-            if next == ':' {
-                if self.lex_specific_punctuation(Punctuation::ByRef).is_err() {
-                    self.lex_specific_punctuation(Punctuation::ByVal)?;
-                }
-            }
+        if self.peek() == Some(':') && self.lex_specific_punctuation(Punctuation::ByRef).is_err() {
+            self.lex_specific_punctuation(Punctuation::ByVal)?;
         }
         Ok(())
     }
     /// Lexes a functions arguments.
+    ///
+    /// An argument may be given a default value (`greeting = "Hello"`). Once one argument has a
+    /// default value, all of the arguments which follow it must have one too. Each argument can
+    /// also be marked `:byRef` or `:byVal`.
     fn lex_function_arguments(&mut self) -> Result<(), LexError> {
         self.lex_specific_punctuation(Punctuation::OpenRoundBracket)?;
+        let mut seen_default = false;
         loop {
-            if self.peek().unwrap() == ')' {
-                break;
+            // inside the brackets the parameters can be split over several lines
+            self.consume_whitespace();
+            match self.peek() {
+                None => {
+                    return Err(LexError::UnclosedBracket(
+                        *self.open_brackets.last().unwrap(),
+                    ))
+                }
+                Some(')') => break,
+                Some(_) => {}
             }
-            self.lex_identifier()?;
-            self.consume_spaces();
-            if self.peek().unwrap() != ',' {
-                break;
+            let start = self.save_loc();
+            let name = self.lex_identifier()?;
+            self.lex_optional_argument_modifier()?;
+            self.consume_whitespace();
+            if self.peek() == Some('=') {
+                self.lex_specific_operator(Operator::Equals)?;
+                self.consume_whitespace();
+                self.lex_expression()?;
+                self.consume_whitespace();
+                seen_default = true;
+            } else if seen_default {
+                return Err(LexError::NonDefaultAfterDefault(SpannedToken::new(
+                    Span::new(start, self.save_loc()),
+                    name,
+                )));
+            }
+            match self.peek() {
+                None => {
+                    return Err(LexError::UnclosedBracket(
+                        *self.open_brackets.last().unwrap(),
+                    ))
+                }
+                Some(',') => {}
+                Some(_) => break,
             }
             self.lex_specific_punctuation(Punctuation::Comma)?;
-            self.consume_spaces();
         }
         self.lex_specific_punctuation(Punctuation::CloseRoundBracket)?;
         Ok(())
@@ -452,90 +1377,294 @@ impl Cursor {
         self.consume_spaces();
         self.lex_function_arguments()?;
         self.consume_spaces();
+        if self.starts_with_word("returns") {
+            self.lex_specific_keyword(Keyword::Returns)?;
+            self.consume_spaces();
+            self.lex_identifier()?;
+        }
         self.lex_newline()?;
         self.consume_newlines();
         self.lex_block()?;
         self.consume_spaces();
-        self.lex_specific_keyword(Keyword::EndFunction)?;
+        self.lex_block_terminator(Keyword::EndFunction)?;
+        Ok(())
+    }
+    /// Lexes a procedure definition, which is like a function definition except that it doesn't
+    /// return anything and its arguments can be passed by reference.
+    fn lex_procedure(&mut self) -> Result<(), LexError> {
+        self.lex_specific_keyword(Keyword::Procedure)?;
+        self.consume_spaces();
+        self.lex_identifier()?;
+        self.consume_spaces();
+        self.lex_function_arguments()?;
+        self.lex_newline()?;
+        self.consume_newlines();
+        self.lex_block()?;
+        self.consume_spaces();
+        self.lex_block_terminator(Keyword::EndProcedure)?;
+        Ok(())
+    }
+    /// Works out how long the float at the start of the input is, returning `None` if there isn't a
+    /// float there.
+    ///
+    /// A float is some digits followed by a dot and (optionally) some more digits, so `3.0` and `3.`
+    /// are both floats. A dot followed by a letter (e.g. the one in `3.value`) would be member access
+    /// rather than a decimal point, so it isn't part of the number.
+    ///
+    /// Either form can be followed by an exponent (`6.02e23` or `1.5E-3`), and some digits with an
+    /// exponent but no dot (`1e10`) are a float too.
+    fn float_len(&self) -> Option<usize> {
+        let integer_part = self
+            .input()
+            .chars()
+            .take_while(char::is_ascii_digit)
+            .count();
+        if integer_part == 0 {
+            return None;
+        }
+        let mantissa = match self.input()[integer_part..].strip_prefix('.') {
+            Some(after_dot) => {
+                if after_dot
+                    .starts_with(|next: char| is_identifier_char(next) && !next.is_ascii_digit())
+                {
+                    return None;
+                }
+                integer_part + 1 + after_dot.chars().take_while(char::is_ascii_digit).count()
+            }
+            None => integer_part,
+        };
+        match exponent_len(&self.input()[mantissa..]) {
+            Some(exponent) => Some(mantissa + exponent),
+            None if mantissa > integer_part => Some(mantissa),
+            None => None,
+        }
+    }
+    /// Lexes `true` or `false` (but not an identifier which starts with one of them, like
+    /// `trueValue`), returning whether there was one to lex.
+    fn lex_boolean(&mut self) -> bool {
+        for (word, boolean) in [("true", true), ("false", false)] {
+            if self.starts_with_word(word) {
+                let start = self.save_loc();
+                for _ in 0..word.len() {
+                    self.eat();
+                }
+                self.push(Token::Boolean(boolean), start);
+                return true;
+            }
+        }
+        false
+    }
+    /// Lexes an integer or a float.
+    fn lex_number(&mut self) -> Result<(), LexError> {
+        if self.float_len().is_some() {
+            self.lex_float()
+        } else {
+            self.lex_integer()
+        }
+    }
+    /// Checks whether the input starts with a negative number (e.g. the `-5` in `x = -5`), rather
+    /// than a minus sign.
+    ///
+    /// The `-` is only part of the number if it comes where an operand should (after an operator,
+    /// an opening bracket or a comma), so `3 -2` is still a subtraction. It also isn't part of a
+    /// number which is raised to a power, because `-2 ^ 2` means `-(2 ^ 2)`.
+    fn starts_with_negative_number(&self) -> bool {
+        let rest = match self.input().strip_prefix('-') {
+            Some(rest) if rest.starts_with(|next: char| next.is_ascii_digit()) => rest,
+            _ => return false,
+        };
+        let after_operand = matches!(
+            self.output.last(),
+            Some(Token::Operator(_))
+                | Some(Token::Punctuation(Punctuation::OpenRoundBracket))
+                | Some(Token::Punctuation(Punctuation::OpenSquareBracket))
+                | Some(Token::Punctuation(Punctuation::Comma))
+        );
+        let number = rest.trim_start_matches(|next: char| {
+            next.is_ascii_digit() || matches!(next, '.' | 'e' | 'E' | '+' | '-')
+        });
+        after_operand && !number.trim_start_matches(' ').starts_with('^')
+    }
+    /// Lexes a negative integer or float (the input should start with a `-` followed by a digit).
+    fn lex_negative_number(&mut self) -> Result<(), LexError> {
+        let start = self.save_loc();
+        self.eat();
+        self.lex_number()?;
+        match self.output.last_mut() {
+            Some(Token::Integer(integer)) => *integer = -*integer,
+            Some(Token::Float(float)) => *float = -*float,
+            _ => unreachable!("a number should have been lexed"),
+        }
+        if let Some(span) = self.spans.last_mut() {
+            span.start = start;
+        }
         Ok(())
     }
     fn lex_float(&mut self) -> Result<(), LexError> {
-        self.output.push(Token::Float(
-            self.peek_token()
-                .expect("missing token")
-                .parse::<f64>()
-                .expect("error parsing float"),
-        ));
+        let len = self.float_len().ok_or_else(|| self.unexpected_token())?;
+        let float = self.input()[..len]
+            .parse::<f64>()
+            .expect("error parsing float");
+        let start = self.save_loc();
+        for _ in 0..len {
+            self.eat();
+        }
+        self.push(Token::Float(float), start);
         Ok(())
     }
+    /// Lexes a string (in double quotes, or single ones with `LexOptions::single_quoted_strings`).
+    ///
+    /// A backslash escapes the character after it (see `split_interpolations`), so an escaped quote
+    /// doesn't end the string.
     fn lex_string(&mut self) -> Result<(), LexError> {
-        self.lex_specific_punctuation(Punctuation::Quote)?;
+        let (quote, punctuation) = match self.peek() {
+            Some('\'') if self.options.single_quoted_strings => ('\'', Punctuation::SingleQuote),
+            _ => ('"', Punctuation::Quote),
+        };
+        self.lex_specific_punctuation(punctuation.clone())?;
+        let start = self.save_loc();
         let mut output = String::new();
-        while self.peek().unwrap() != '"' {
-            output.push(self.peek().unwrap());
-            self.eat();
+        loop {
+            match self.peek().ok_or(LexError::UnexpectedEndOfInput)? {
+                next if next == quote => break,
+                '\\' => {
+                    self.eat();
+                    output.push('\\');
+                    output.push(self.eat().ok_or(LexError::UnexpectedEndOfInput)?);
+                }
+                next => {
+                    self.eat();
+                    output.push(next);
+                }
+            }
         }
-        self.output.push(Token::String(output));
-        self.lex_specific_punctuation(Punctuation::Quote)?;
+        let mut parts = match split_interpolations(&output) {
+            Some(parts) => parts,
+            None => {
+                return Err(LexError::UnexpectedToken(SpannedToken::new(
+                    Span::new(start, self.save_loc()),
+                    output,
+                )))
+            }
+        };
+        match parts.as_mut_slice() {
+            [] => self.push(Token::String(String::new()), start),
+            [StringPart::Literal(literal)] => {
+                self.push(Token::String(std::mem::take(literal)), start)
+            }
+            _ => self.push(Token::InterpolatedString(parts), start),
+        }
+        self.lex_specific_punctuation(punctuation)?;
         Ok(())
     }
     /// Lexes an expression
     fn lex_expression(&mut self) -> Result<(), LexError> {
-        let starting_brackets = *&self.current_parenthisis;
+        let starting_brackets = self.open_brackets.len();
+        let mut square_brackets = 0;
         self.consume_spaces();
         while let Some(item) = self.peek() {
             if item == '\n' {
-                return Ok(());
+                break;
+            }
+            // outside of square brackets (where it's part of a slice) a colon ends the expression
+            // (e.g. in `case 1:`)
+            match item {
+                '[' => square_brackets += 1,
+                // the end of an index (see `lex_indexes`)
+                ']' if square_brackets == 0 => break,
+                ']' => square_brackets -= 1,
+                ':' if square_brackets == 0 => break,
+                _ => {}
             }
             self.consume_spaces();
-            if item == '"' {
+            // a comment ends the line, so it isn't part of the expression (and the `/`s in it
+            // aren't divisions)
+            if self.input().starts_with("//") {
+                break;
+            }
+            // a comma at this level separates this expression from the next one (e.g. in the
+            // arguments to a function), so it isn't part of this expression (unless it separates
+            // the elements of an array, like `[1, 2]`)
+            if item == ',' && self.open_brackets.len() == starting_brackets && square_brackets == 0
+            {
+                break;
+            }
+            if item == '"' || (item == '\'' && self.options.single_quoted_strings) {
                 self.lex_string()?;
-            } else if item.is_alphabetic() || item == '(' || item == ')' {
+            } else if item.is_alphabetic() || item == '_' || item == '(' || item == ')' {
                 match item {
                     '(' => {
                         self.lex_specific_punctuation(Punctuation::OpenRoundBracket)?;
+                        continue;
                     }
                     ')' => {
-                        if self.current_parenthisis == starting_brackets {
-                            return Ok(());
+                        if self.open_brackets.is_empty() {
+                            let start = self.save_loc();
+                            self.eat();
+                            return Err(LexError::UnmatchedBracket(Span::new(
+                                start,
+                                self.save_loc(),
+                            )));
                         }
-                        if self.current_parenthisis < 1 {
-                            panic!(
-                                "unmatched brackets; this is going to be made into a proper error"
-                            )
+                        if self.open_brackets.len() == starting_brackets {
+                            break;
+                        }
+                        self.lex_specific_punctuation(Punctuation::CloseRoundBracket)?;
+                        continue;
+                    }
+                    _ => {
+                        // word operators (e.g. `NOT`) look just like identifiers
+                        if self.lex_any_operator().is_ok() {
+                            self.consume_spaces();
+                            continue;
+                        }
+                        if self.lex_boolean() {
+                            continue;
                         }
                     }
-                    _ => {}
                 }
-                if self
-                    .peek_token()
-                    .expect("unexpected end of input")
-                    .contains("(")
-                {
+                if self.starts_with_application() {
                     self.lex_application()?;
                 } else {
                     self.lex_identifier()?;
                 }
+                self.lex_indexes()?;
             } else if item.is_numeric() {
-                if self
-                    .peek_token()
-                    .expect("unexpected end of input")
-                    .contains(".")
-                {
-                    self.lex_float()?;
-                } else {
-                    self.lex_integer()?;
-                }
+                self.lex_number()?;
+            } else if self.starts_with_negative_number() {
+                self.lex_negative_number()?;
             } else {
                 self.consume_spaces();
-                if self.lex_any_punctuation().is_err() {
-                    if self.lex_any_operator().is_err() {
-                        return Ok(());
-                    };
+                if self.lex_any_punctuation().is_err() && self.lex_any_operator().is_err() {
+                    break;
                 }
                 self.consume_spaces();
             }
         }
+        // every bracket opened in the expression has to be closed by the end of it
+        match self.open_brackets.get(starting_brackets) {
+            Some(open) => Err(LexError::UnclosedBracket(*open)),
+            None => Ok(()),
+        }
+    }
+    /// Lexes any indexes (`[i]`) or slices (`[start:stop]`) directly after an identifier or a function
+    /// call. These can be chained (`grid[i][j]`).
+    fn lex_indexes(&mut self) -> Result<(), LexError> {
+        while self.peek() == Some('[') {
+            let start = self.save_loc();
+            self.lex_specific_punctuation(Punctuation::OpenSquareBracket)?;
+            let open = Span::new(start, self.save_loc());
+            self.lex_expression()?;
+            if self.peek() == Some(':') {
+                self.lex_specific_punctuation(Punctuation::Colon)?;
+                self.lex_expression()?;
+            }
+            self.consume_spaces();
+            if self.peek() != Some(']') {
+                return Err(LexError::UnclosedBracket(open));
+            }
+            self.lex_specific_punctuation(Punctuation::CloseSquareBracket)?;
+        }
         Ok(())
     }
     /// Lexes an if statement.
@@ -543,22 +1672,61 @@ impl Cursor {
         self.lex_specific_keyword(Keyword::If)?;
         self.consume_newlines();
         self.lex_expression()?;
-        self.consume_spaces();
-        self.lex_specific_keyword(Keyword::Then)?;
+        self.lex_then()?;
         self.lex_newline()?;
         self.lex_block()?;
-        if self.lex_specific_keyword(Keyword::ElseIf).is_ok() {
+        while self.lex_elseif() {
+            self.consume_spaces();
             self.lex_expression()?;
-            self.lex_specific_keyword(Keyword::Then)?;
+            self.lex_then()?;
+            self.lex_newline()?;
             self.lex_block()?;
         }
         if self.lex_specific_keyword(Keyword::Else).is_ok() {
+            if self.options.colon_blocks {
+                self.lex_specific_punctuation(Punctuation::Colon)?;
+            }
+            self.lex_newline()?;
             self.lex_block()?;
         }
         self.consume_spaces();
-        self.lex_specific_keyword(Keyword::EndIf)?;
+        self.lex_block_terminator(Keyword::EndIf)?;
         Ok(())
     }
+    /// Lexes an `elseif` (or, in the AQA dialect, an `else if` on one line, which becomes a single
+    /// `Keyword::ElseIf`), returning whether there was one.
+    ///
+    /// An `else` with an `if` on the line after it is an `else` block which contains an if
+    /// statement, rather than an `else if`.
+    fn lex_elseif(&mut self) -> bool {
+        self.consume_spaces();
+        if self.options.dialect == Dialect::Aqa && self.starts_with_word("else") {
+            let rest = self.input()["else".len()..].trim_start_matches([' ', '\t']);
+            if rest.starts_with("if") && !rest["if".len()..].starts_with(is_identifier_char) {
+                let start = self.save_loc();
+                for _ in 0.."else".len() {
+                    self.eat();
+                }
+                self.consume_spaces();
+                for _ in 0.."if".len() {
+                    self.eat();
+                }
+                self.push(Token::Keyword(Keyword::ElseIf), start);
+                return true;
+            }
+        }
+        self.lex_specific_keyword(Keyword::ElseIf).is_ok()
+    }
+    /// Lexes the `then` (or, with `LexOptions::colon_blocks`, the colon) which ends the condition
+    /// of an `if` or `elseif`.
+    fn lex_then(&mut self) -> Result<(), LexError> {
+        self.consume_spaces();
+        if self.options.colon_blocks {
+            self.lex_specific_punctuation(Punctuation::Colon)
+        } else {
+            self.lex_specific_keyword(Keyword::Then)
+        }
+    }
     /// Consumes as many newlines as is possible.
     fn consume_newlines(&mut self) {
         while let Some(next) = self.peek() {
@@ -570,34 +1738,75 @@ impl Cursor {
         }
     }
     /// Lexes a switch statement.
+    ///
+    /// The cases are indented inside the `switch`, and the code for each case is indented inside
+    /// that:
+    ///
+    /// ```text
+    /// switch entry:
+    ///     case "A":
+    ///         print("You selected A")
+    ///     case is != 0:
+    ///         print("You selected something else")
+    ///     default:
+    ///         print("Unrecognised selection")
+    /// endswitch
+    /// ```
     fn lex_switch_statement(&mut self) -> Result<(), LexError> {
         self.lex_specific_keyword(Keyword::Switch)?;
-        // for the moment expressions cannot be switched on
-        // this is only temporary
-        self.lex_identifier()?;
+        self.consume_spaces();
+        self.lex_expression()?;
         self.lex_specific_punctuation(Punctuation::Colon)?;
+        self.lex_newline()?;
 
+        let outer_indentation = self.current_indentation;
+        self.skip_blank_lines();
+        let indentation = self.count_indents();
+        if indentation <= outer_indentation {
+            return Err(self.block_indentation_error(outer_indentation, indentation));
+        }
+        self.current_indentation = indentation;
         loop {
-            self.lex_newline()?;
-            self.consume_newlines();
-            self.lex_indentation()?;
-            self.consume_spaces();
-            if self.lex_specific_keyword(Keyword::Case).is_err() {
+            self.skip_blank_lines();
+            let indents = self.count_indents();
+            if indents > outer_indentation && indents < indentation {
+                return Err(LexError::IndentationError {
+                    expected: indentation,
+                    found: indents,
+                    loc: self.save_loc(),
+                });
+            } else if indents < indentation {
                 break;
+            } else if indents > indentation {
+                return Err(LexError::IndentationError {
+                    expected: indentation,
+                    found: indents,
+                    loc: self.save_loc(),
+                });
             }
-            self.lex_expression()?;
-            self.set_indentation_level(2);
+            self.consume_spaces();
+            if self.starts_with_word("default") {
+                self.lex_specific_keyword(Keyword::Default)?;
+            } else {
+                self.lex_specific_keyword(Keyword::Case)?;
+                self.consume_spaces();
+                if self.starts_with_word("is") {
+                    self.lex_specific_keyword(Keyword::Is)?;
+                    self.consume_spaces();
+                }
+                // a comparison (e.g. `case != 0`) starts with an operator (this is allowed to fail,
+                // because most cases don't)
+                let _ = self.lex_any_operator();
+                self.lex_expression()?;
+            }
+            self.consume_spaces();
+            self.lex_specific_punctuation(Punctuation::Colon)?;
+            self.lex_newline()?;
             self.lex_block()?;
         }
+        self.current_indentation = outer_indentation;
 
-        self.lex_specific_keyword(Keyword::Default)?;
-        self.consume_spaces();
-        self.lex_specific_punctuation(Punctuation::Colon)?;
-        self.set_indentation_level(2);
-        self.lex_block()?;
-
-        self.lex_specific_keyword(Keyword::EndSwitch)?;
-
+        self.lex_block_terminator(Keyword::EndSwitch)?;
         Ok(())
     }
     /// Lexes a while statement
@@ -606,28 +1815,60 @@ impl Cursor {
         self.consume_spaces();
         self.lex_expression()?;
         self.consume_spaces();
+        if self.options.colon_blocks {
+            self.lex_specific_punctuation(Punctuation::Colon)?;
+        } else if self.options.while_do && self.starts_with_word("do") {
+            self.lex_specific_keyword(Keyword::Do)?;
+        }
         self.lex_newline()?;
         self.lex_block()?;
-        self.lex_specific_keyword(Keyword::EndWhile)?;
+        self.lex_block_terminator(Keyword::EndWhile)?;
         Ok(())
     }
+    /// Lexes a `try ... catch e ... endtry` statement.
+    fn lex_try_statement(&mut self) -> Result<(), LexError> {
+        self.lex_specific_keyword(Keyword::Try)?;
+        self.lex_newline()?;
+        self.lex_block()?;
+        self.lex_specific_keyword(Keyword::Catch)?;
+        self.consume_spaces();
+        self.lex_identifier()?;
+        self.lex_newline()?;
+        self.lex_block()?;
+        self.lex_block_terminator(Keyword::EndTry)?;
+        Ok(())
+    }
+    /// Lexes a `do ... until <condition>` loop.
     fn lex_do_statement(&mut self) -> Result<(), LexError> {
         self.lex_specific_keyword(Keyword::Do)?;
         self.lex_newline()?;
         self.lex_block()?;
-        self.lex_specific_keyword(Keyword::Until)?;
+        self.lex_block_terminator(Keyword::Until)?;
         self.lex_expression()?;
         Ok(())
     }
     /// Lexes an integer.
     fn lex_integer(&mut self) -> Result<(), LexError> {
         if let Some(next) = self.peek_token() {
-            match next.parse::<i64>() {
+            // the integer ends at the first character which can't be part of it (e.g. the `)` in
+            // `f(1)`)
+            let next = next
+                .split(|item: char| !item.is_alphanumeric())
+                .next()
+                .unwrap_or_default();
+            // hexadecimal (`0xFF`) and binary (`0b1010`) integers are written with a prefix
+            let integer = match next.get(..2) {
+                Some("0x" | "0X") => i64::from_str_radix(&next[2..], 16),
+                Some("0b" | "0B") => i64::from_str_radix(&next[2..], 2),
+                _ => next.parse::<i64>(),
+            };
+            match integer {
                 Ok(integer) => {
+                    let start = self.save_loc();
                     for _ in 0..next.len() {
                         self.eat();
                     }
-                    self.output.push(Token::Integer(integer));
+                    self.push(Token::Integer(integer), start);
                     return Ok(());
                 }
                 Err(_) => {
@@ -649,13 +1890,15 @@ impl Cursor {
         macro_rules! punctuation {
             ($self:ident, $(($string:expr => $punct:ident)),+) => {
                 $(
-                     if self.input.starts_with($string) {
-                        self.output.push(
-                            $crate::lexer::Token::Punctuation($crate::lexer::Punctuation::$punct)
-                        );
+                     if self.input().starts_with($string) {
+                        let start = $self.save_loc();
                         for _ in 0..$string.len() {
                            $self.eat();
                         }
+                        $self.push(
+                            $crate::lexer::Token::Punctuation($crate::lexer::Punctuation::$punct),
+                            start
+                        );
                         return Ok(());
                      }
                 )*
@@ -667,40 +1910,61 @@ impl Cursor {
         punctuation!(self,
             ("(" => OpenRoundBracket),
             (")" => CloseRoundBracket),
+            ("[" => OpenSquareBracket),
+            ("]" => CloseSquareBracket),
             (":byRef" => ByRef),
             (":byVal" => ByVal),
             (":" => Colon),
             ("," => Comma),
-            ("\"" => Quote)
+            ("\"" => Quote),
+            ("." => Dot)
         );
     }
     /// Lexes any operator
     fn lex_any_operator(&mut self) -> Result<(), LexError> {
+        if self.options.alternative_operators {
+            // these have to be checked first, so that `**` isn't read as two `*`s
+            if self.starts_with_operator("**") {
+                return self.lex_alternative_operator("**", Operator::Power);
+            } else if self.starts_with_operator("MODULO") {
+                return self.lex_alternative_operator("MODULO", Operator::Modulo);
+            }
+        }
         // todo: move this out and consolidate all of these definitions into one
         macro_rules! operators {
             ($self:ident, $(($string:expr => $op:ident)),+) => {
                 $(
-                    if self.input.starts_with($string) {
-                        self.output.push(
-                            $crate::lexer::Token::Operator($crate::lexer::Operator::$op)
-                        );
-                        for _ in 0..$string.len() {
+                    if $self.starts_with_operator($string) {
+                        let start = $self.save_loc();
+                        for _ in 0..$string.chars().count() {
                             $self.eat();
                         }
+                        $self.push(
+                            $crate::lexer::Token::Operator($crate::lexer::Operator::$op),
+                            start
+                        );
                         return Ok(());
                     }
                 )+
                 else {
-                    return Err($crate::lexer::LexError::UnexpectedEndOfInput); 
+                    return Err($crate::lexer::LexError::UnexpectedEndOfInput);
                 }
             }
         }
         operators!(self,
             ("==" => Comparison),
             ("!=" => NotEquals),
+            // these have to come before `<` and `>`, just as `==` has to come before `=`
+            ("<=" => LessThanOrEqual),
+            (">=" => GreaterThanOrEqual),
+            ("<" => LessThan),
+            (">" => GreaterThan),
             ("=" => Equals),
             ("*" => Times),
             ("/" => Divide),
+            ("DIV" => IntegerDivide),
+            ("MOD" => Modulo),
+            ("^" => Power),
             ("+=" => Increment),
             ("+" => Plus),
             ("-" => Minus),
@@ -709,32 +1973,38 @@ impl Cursor {
             ("NOT" => Not)
         );
     }
+    /// Lexes `spelling`, which is an alternative spelling of `operator`.
+    fn lex_alternative_operator(
+        &mut self,
+        spelling: &str,
+        operator: Operator,
+    ) -> Result<(), LexError> {
+        let start = self.save_loc();
+        for _ in 0..spelling.chars().count() {
+            self.eat();
+        }
+        self.push(Token::Operator(operator), start);
+        Ok(())
+    }
     /// Lexes a specific operator.
     fn lex_specific_operator(&mut self, operator: Operator) -> Result<(), LexError> {
         macro_rules! operators {
             ($self:ident, $operator:ident, $(($string:expr => $op:ident)),+) => {
-                let start = $self.save_loc();
                 match $operator {
                     $(
                         $crate::lexer::Operator::$op => {
-                            if self.input.starts_with($string) {
-                                for _ in 0..$string.len() {
+                            if $self.starts_with_operator($string) {
+                                let start = $self.save_loc();
+                                for _ in 0..$string.chars().count() {
                                     self.eat();
                                 }
-                                self.output.push($crate::lexer::Token::Operator(
+                                self.push($crate::lexer::Token::Operator(
                                     $crate::lexer::Operator::$op
-                                ));
+                                ), start);
                                 return Ok(())
                             }
                             else {
-                                return Err(
-                                    $crate::lexer::LexError::UnexpectedToken(
-                                        $crate::lexer::SpannedToken::new(
-                                            Span::new(start, self.save_loc()),
-                                            self.peek().unwrap().to_string()
-                                        )
-                                    )
-                                )
+                                return Err($self.unexpected_token())
                             }
                         }
                     )+
@@ -746,75 +2016,100 @@ impl Cursor {
                 ("=" => Equals),
                 ("==" => Comparison),
                 ("!=" => NotEquals),
+                ("<=" => LessThanOrEqual),
+                (">=" => GreaterThanOrEqual),
+                ("<" => LessThan),
+                (">" => GreaterThan),
                 ("*" => Times),
                 ("+=" => Increment),
                 ("+" => Plus),
                 ("-" => Minus),
                 ("/" => Divide),
+                ("DIV" => IntegerDivide),
+                ("MOD" => Modulo),
+                ("^" => Power),
                 ("AND" => And),
                 ("OR" => Or),
-                ("NOT" => Not)
+                ("NOT" => Not),
+                ("←" => Assign)
         );
     }
-    /// Lexes a for statement
+    /// Lexes a for statement (including `for each` statements).
     fn lex_for_statement(&mut self) -> Result<(), LexError> {
         self.lex_specific_keyword(Keyword::For)?;
-        self.lex_identifier()?;
+        self.consume_spaces();
+        if self.peek_token() == Some("each") {
+            return self.lex_for_each_statement();
+        }
+        let ident = self.lex_identifier()?;
+        self.consume_spaces();
         self.lex_specific_operator(Operator::Equals)?;
-        self.lex_integer()?;
+        self.consume_spaces();
+        self.lex_expression()?;
         self.lex_specific_keyword(Keyword::To)?;
+        self.consume_spaces();
+        self.lex_expression()?;
+        self.consume_spaces();
+        if self.starts_with_word("step") {
+            self.lex_specific_keyword(Keyword::Step)?;
+            self.consume_spaces();
+            self.lex_expression()?;
+        }
         self.lex_newline()?;
         self.lex_block()?;
-        // why do they actually do this???
-        self.lex_specific_keyword(Keyword::Next)?;
-        self.lex_identifier()?;
-        Ok(())
+        self.lex_next(&ident)
     }
-    fn set_indentation_level(&mut self, level: u32) {
-        self.current_indentation = level;
+    /// Lexes the rest of a `for each item in iterable` statement (the cursor should be positioned
+    /// after the `for`).
+    fn lex_for_each_statement(&mut self) -> Result<(), LexError> {
+        self.lex_specific_keyword(Keyword::Each)?;
+        self.consume_spaces();
+        let ident = self.lex_identifier()?;
+        self.lex_specific_keyword(Keyword::In)?;
+        self.consume_spaces();
+        self.lex_expression()?;
+        self.lex_newline()?;
+        self.lex_block()?;
+        self.lex_next(&ident)
     }
-    fn lex_newline(&mut self) -> Result<(), LexError> {
+    /// Lexes the `next <ident>` (or `endfor`) which ends a for loop.
+    ///
+    /// `next` has to name the loop variable (which is the only reason it's there), so a mismatch is
+    /// an error.
+    fn lex_next(&mut self, ident: &str) -> Result<(), LexError> {
         self.consume_spaces();
-        if let Some(token) = self.eat() {
-            if token == '\n' {
-                return Ok(());
-            } else {
-                panic!("expected a newline")
-            }
-        } else {
-            panic!("unexpected end of input")
+        if self.starts_with_word("endfor") {
+            return self.lex_specific_keyword(Keyword::EndFor);
         }
-    }
-    fn lex_two_spaces(&mut self) -> Result<(), LexError> {
-        let mut spaces = 1;
-        while spaces < 2 {
-            let next = self.peek().expect("unexpected end of input");
-            if next == ' ' {
-                spaces += 1;
-                self.eat();
-            } else {
-                panic!("expected a space. didn't get a space")
-            }
+        if self.input().is_empty() {
+            return self.lex_block_terminator(Keyword::Next);
+        }
+        self.lex_specific_keyword(Keyword::Next)?;
+        self.consume_spaces();
+        let start = self.save_loc();
+        let next = self.lex_identifier()?;
+        if next != ident {
+            return Err(LexError::UnexpectedToken(SpannedToken::new(
+                Span::new(start, self.save_loc()),
+                next,
+            )));
         }
         Ok(())
     }
-    /// Lexes a unit of indentation.
-    fn lex_indentation(&mut self) -> Result<(), LexError> {
-        let next = self.eat().expect("unexpected end of input");
-        if next == '\t' {
-            return Ok(());
-        } else {
-            if next == ' ' {
-                self.lex_two_spaces()?;
-                #[allow(unused)]
-                {
-                    self.lex_two_spaces();
-                }
-
+    fn lex_newline(&mut self) -> Result<(), LexError> {
+        self.consume_spaces();
+        if self.input().starts_with("//") {
+            self.lex_comment();
+        }
+        match self.peek() {
+            Some('\n') => {
+                self.eat();
                 Ok(())
-            } else {
-                panic!("expected some indentation")
             }
+            Some(_) => Err(self.unexpected_token()),
+            // the last line doesn't need a newline at the end of it (and if it's inside a block,
+            // the parser reports the block which wasn't closed)
+            None => Ok(()),
         }
     }
 }
@@ -1,3 +1,472 @@
 //! Unit tests for the functions in the lexer.
 //! These are tests of specific functions which are part of the lexer. These tests test isolated
 //! units of the lexer, rather than how the pieces work together.
+
+use crate::lexer::{
+    escape_string, lex, lex_spanned, lex_with_spans, relex_from, Cursor, Keyword, LexError,
+    LexOptions, Loc, Operator, Punctuation, Span, StringPart, Token, TokenKind,
+};
+
+/// Eats characters until the remaining input starts with `pattern`.
+fn advance_to(cursor: &mut Cursor, pattern: &str) {
+    while !cursor.input().starts_with(pattern) {
+        cursor.eat().expect("pattern not found in input");
+    }
+}
+
+#[test]
+pub fn test_location_starts_at_one() {
+    let cursor = Cursor::new("x = 1".to_string());
+    assert_eq!(cursor.location, Loc::new(1, 1));
+}
+
+#[test]
+pub fn test_location_on_later_lines() {
+    let mut cursor = Cursor::new("function f(x)\n    return x\nendfunction\n".to_string());
+    advance_to(&mut cursor, "return");
+    assert_eq!(cursor.location, Loc::new(2, 5));
+    advance_to(&mut cursor, "endfunction");
+    assert_eq!(cursor.location, Loc::new(3, 1));
+}
+
+#[test]
+pub fn test_tab_counts_as_four_columns() {
+    let mut cursor = Cursor::new("\tx = 1".to_string());
+    advance_to(&mut cursor, "x");
+    assert_eq!(cursor.location, Loc::new(1, 5));
+}
+
+#[test]
+pub fn test_error_location_on_later_line() {
+    let mut input = "function f(x)\n    return x\nendfunctio\n".to_string();
+    match lex(input.as_mut_str()) {
+        Err(LexError::UnexpectedToken(token)) => assert_eq!(token.span.start, Loc::new(3, 1)),
+        other => panic!("expected an unexpected token error, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn test_error_on_first_line_is_reported_as_line_one() {
+    let mut input = "if then\n    x = 1\nendif\n".to_string();
+    match lex(input.as_mut_str()) {
+        Err(LexError::UnexpectedToken(token)) => assert_eq!(token.span.start.line, 1),
+        other => panic!("expected an unexpected token error, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn test_keyword_error_echoes_what_was_found() {
+    let mut cursor = Cursor::new("fnction f(x)".to_string());
+    match cursor.lex_specific_keyword(Keyword::Function) {
+        Err(LexError::UnexpectedToken(token)) => {
+            assert_eq!(token.token, "fnction");
+            assert_eq!(token.span.start, Loc::new(1, 1));
+            assert_eq!(token.span.stop, Loc::new(1, 8));
+        }
+        other => panic!("expected an unexpected token error, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn test_operator_error_echoes_what_was_found() {
+    let mut cursor = Cursor::new("x 1".to_string());
+    match cursor.lex_specific_operator(Operator::Equals) {
+        Err(LexError::UnexpectedToken(token)) => assert_eq!(token.token, "x"),
+        other => panic!("expected an unexpected token error, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn test_operator_error_at_end_of_input() {
+    let mut cursor = Cursor::new("".to_string());
+    assert!(matches!(
+        cursor.lex_specific_operator(Operator::Equals),
+        Err(LexError::UnexpectedEndOfInput)
+    ));
+}
+
+#[test]
+pub fn test_word_operators_must_be_whole_words() {
+    let mut cursor = Cursor::new("ANDREW".to_string());
+    assert!(cursor.lex_any_operator().is_err());
+    assert!(cursor.lex_specific_operator(Operator::And).is_err());
+    let mut cursor = Cursor::new("AND REW".to_string());
+    assert!(cursor.lex_any_operator().is_ok());
+    assert_eq!(cursor.output, vec![Token::Operator(Operator::And)]);
+}
+
+#[test]
+pub fn test_lexes_identifier_starting_with_word_operator() {
+    let mut input = "ANDREW = 1".to_string();
+    assert_eq!(
+        lex(input.as_mut_str()).unwrap(),
+        vec![
+            Token::Ident("ANDREW".to_string()),
+            Token::Operator(Operator::Equals),
+            Token::Integer(1)
+        ]
+    );
+}
+
+#[test]
+pub fn test_lexes_word_operators_in_expressions() {
+    let mut input = "x = NOT y OR ORACLE".to_string();
+    assert_eq!(
+        lex(input.as_mut_str()).unwrap(),
+        vec![
+            Token::Ident("x".to_string()),
+            Token::Operator(Operator::Equals),
+            Token::Operator(Operator::Not),
+            Token::Ident("y".to_string()),
+            Token::Operator(Operator::Or),
+            Token::Ident("ORACLE".to_string())
+        ]
+    );
+}
+
+#[test]
+pub fn test_keywords_must_be_whole_words() {
+    let mut cursor = Cursor::new("functional".to_string());
+    assert!(cursor.lex_specific_keyword(Keyword::Function).is_err());
+    let mut cursor = Cursor::new("function f()".to_string());
+    assert!(cursor.lex_specific_keyword(Keyword::Function).is_ok());
+    assert_eq!(cursor.output, vec![Token::Keyword(Keyword::Function)]);
+}
+
+#[test]
+pub fn test_lexes_identifier_starting_with_keyword() {
+    let mut input = "functional = 1".to_string();
+    assert_eq!(
+        lex(input.as_mut_str()).unwrap(),
+        vec![
+            Token::Ident("functional".to_string()),
+            Token::Operator(Operator::Equals),
+            Token::Integer(1)
+        ]
+    );
+}
+
+#[test]
+pub fn test_escapes_special_characters() {
+    assert_eq!(escape_string("plain"), "plain");
+    assert_eq!(escape_string("say \"hi\""), r#"say \"hi\""#);
+    assert_eq!(escape_string("a\nb\tc"), r"a\nb\tc");
+    assert_eq!(escape_string(r"back\slash"), r"back\\slash");
+    assert_eq!(escape_string("{x}"), r"\{x\}");
+}
+
+#[test]
+pub fn test_displays_tokens_as_source() {
+    assert_eq!(
+        Token::Keyword(Keyword::EndFunction).to_string(),
+        "endfunction"
+    );
+    assert_eq!(Token::Operator(Operator::NotEquals).to_string(), "!=");
+    assert_eq!(Token::Float(1.0).to_string(), "1.0");
+    assert_eq!(Token::String("a\"b".to_string()).to_string(), r#"a\"b"#);
+}
+
+#[test]
+pub fn test_identifiers_can_contain_underscores() {
+    let mut string = "high_score = RANDOM_INT(1, 6)".to_string();
+    let tokens = lex(string.as_mut_str()).unwrap();
+    assert_eq!(tokens[0], Token::Ident("high_score".to_string()));
+    assert_eq!(tokens[2], Token::Ident("RANDOM_INT".to_string()));
+}
+
+/// Lexes the expression at the start of `input`, returning the tokens and whatever is left over.
+fn lex_expression(input: &str) -> (Vec<Token>, String) {
+    let mut cursor = Cursor::new(input.to_string());
+    cursor.lex_expression().unwrap();
+    let rest = cursor.input().to_string();
+    (cursor.output, rest)
+}
+
+#[test]
+pub fn test_lexes_float() {
+    assert_eq!(lex_expression("3.0").0, vec![Token::Float(3.0)]);
+    assert_eq!(
+        lex_expression("1.5*2").0,
+        vec![
+            Token::Float(1.5),
+            Token::Operator(Operator::Times),
+            Token::Integer(2)
+        ]
+    );
+}
+
+#[test]
+pub fn test_lexes_float_with_trailing_dot() {
+    assert_eq!(lex_expression("3.").0, vec![Token::Float(3.0)]);
+    assert_eq!(
+        lex_expression("3. + 1").0,
+        vec![
+            Token::Float(3.0),
+            Token::Operator(Operator::Plus),
+            Token::Integer(1)
+        ]
+    );
+}
+
+#[test]
+pub fn test_member_access_is_not_a_float() {
+    let mut cursor = Cursor::new("2*obj.value".to_string());
+    assert_eq!(cursor.float_len(), None);
+    cursor.lex_integer().unwrap();
+    assert_eq!(cursor.output, vec![Token::Integer(2)]);
+    let cursor = Cursor::new("2.value".to_string());
+    assert_eq!(cursor.float_len(), None);
+}
+
+#[test]
+pub fn test_lexes_bracketed_expression() {
+    assert_eq!(
+        lex_expression("(1 + 2) * 3").0,
+        vec![
+            Token::Punctuation(Punctuation::OpenRoundBracket),
+            Token::Integer(1),
+            Token::Operator(Operator::Plus),
+            Token::Integer(2),
+            Token::Punctuation(Punctuation::CloseRoundBracket),
+            Token::Operator(Operator::Times),
+            Token::Integer(3)
+        ]
+    );
+}
+
+#[test]
+pub fn test_finds_token_at_position() {
+    let mut input = "total = 12\nprint(total)\n".to_string();
+    let tokens = lex_spanned(&mut input, LexOptions::default()).unwrap();
+    assert_eq!(
+        tokens.token_at(Loc::new(1, 3)),
+        Some(&Token::Ident("total".to_string()))
+    );
+    assert_eq!(tokens.token_at(Loc::new(1, 10)), Some(&Token::Integer(12)));
+    assert_eq!(
+        tokens.token_at(Loc::new(2, 6)),
+        Some(&Token::Punctuation(Punctuation::OpenRoundBracket))
+    );
+    // the space between `total` and `=`
+    assert_eq!(tokens.token_at(Loc::new(1, 6)), None);
+}
+
+#[test]
+pub fn test_finds_tokens_in_span() {
+    let mut input = "total = 12\nprint(total)\n".to_string();
+    let tokens = lex_spanned(&mut input, LexOptions::default()).unwrap();
+    assert_eq!(
+        tokens.tokens_in_span(Span::new(Loc::new(1, 6), Loc::new(2, 3))),
+        [
+            Token::Operator(Operator::Equals),
+            Token::Integer(12),
+            Token::Ident("print".to_string())
+        ]
+    );
+    assert!(tokens
+        .tokens_in_span(Span::new(Loc::new(1, 6), Loc::new(1, 7)))
+        .is_empty());
+}
+
+/// The position of the byte at `offset` in `source` (which doesn't contain any tabs).
+fn loc_of(source: &str, offset: usize) -> Loc {
+    let before = &source[..offset];
+    let line = before.matches('\n').count() as u32 + 1;
+    let col = before[before.rfind('\n').map_or(0, |newline| newline + 1)..]
+        .chars()
+        .count() as u32
+        + 1;
+    Loc::new(line, col)
+}
+
+/// Replaces the first `old` in `source` with `new`, and checks that re-lexing the result with
+/// `relex_from` gives the same tokens and spans as lexing it from scratch.
+fn assert_relexes(source: &str, old: &str, new: &str) {
+    let options = LexOptions {
+        indentation_tokens: true,
+        ..LexOptions::default()
+    };
+    let stream = lex_spanned(&mut source.to_string(), options.clone()).unwrap();
+    let start = source.find(old).unwrap();
+    let edit = Span::new(loc_of(source, start), loc_of(source, start + old.len()));
+    let mut edited = source.replacen(old, new, 1);
+    let relexed = relex_from(&stream, edit, &mut edited).unwrap();
+    let expected = lex_spanned(&mut edited, options).unwrap();
+    assert_eq!(relexed.tokens(), expected.tokens());
+    assert_eq!(relexed.spans(), expected.spans());
+    assert_eq!(relexed.restarts, expected.restarts);
+}
+
+const RELEX_SOURCE: &str = "// totals
+total = 0
+
+function add(a, b)
+    if a > b then
+        return a + b
+    endif
+    return b + a
+endfunction
+
+for i = 1 to 3
+    total = add(total, i) // running total
+next i
+print(total)
+";
+
+#[test]
+pub fn test_relexes_after_edit() {
+    // inside and between top-level statements
+    assert_relexes(RELEX_SOURCE, "total = 0", "total = 10");
+    assert_relexes(RELEX_SOURCE, "print(total)", "print(total * 2)");
+    assert_relexes(RELEX_SOURCE, "\nfor", "\nx = 1\nfor");
+    // inside blocks
+    assert_relexes(RELEX_SOURCE, "return b + a", "return b");
+    assert_relexes(
+        RELEX_SOURCE,
+        "        return a + b\n",
+        "        pass\n        pass\n",
+    );
+    assert_relexes(RELEX_SOURCE, "running total", "sum so far");
+    // on the first line, and at the end
+    assert_relexes(RELEX_SOURCE, "// totals", "// the total");
+    assert_relexes(RELEX_SOURCE, "print(total)\n", "print(total)\nprint(0)\n");
+    assert_relexes(RELEX_SOURCE, "\nprint(total)\n", "");
+}
+
+#[test]
+pub fn test_relexing_reuses_earlier_tokens() {
+    let source = "x = 1\ny = 2\nz = 3\n";
+    let stream = lex_spanned(&mut source.to_string(), LexOptions::default()).unwrap();
+    // the first line is different here, but it's before the edit so it isn't lexed again
+    let mut edited = "x = 5\ny = 2\nz = 4\n".to_string();
+    let edit = Span::new(Loc::new(3, 5), Loc::new(3, 6));
+    let relexed = relex_from(&stream, edit, &mut edited).unwrap();
+    assert_eq!(relexed.tokens()[2], Token::Integer(1));
+    assert_eq!(relexed.tokens()[8], Token::Integer(4));
+    // an edit inside a block re-lexes from the line which opens it (so the changed condition is
+    // picked up too)
+    let source = "x = 1\nwhile x\n    x = 0\nendwhile\n";
+    let stream = lex_spanned(&mut source.to_string(), LexOptions::default()).unwrap();
+    let mut edited = "x = 2\nwhile y\n    x = 3\nendwhile\n".to_string();
+    let edit = Span::new(Loc::new(3, 9), Loc::new(3, 10));
+    let relexed = relex_from(&stream, edit, &mut edited).unwrap();
+    assert_eq!(relexed.tokens()[2], Token::Integer(1));
+    assert_eq!(relexed.tokens()[4], Token::Ident("y".to_string()));
+    assert_eq!(relexed.tokens()[7], Token::Integer(3));
+}
+
+#[test]
+pub fn test_records_spans_of_tokens() {
+    let mut input = "total = 12\nprint(\"hi\")\n".to_string();
+    let span = |line, start, stop| Span::new(Loc::new(line, start), Loc::new(line, stop));
+    assert_eq!(
+        lex_with_spans(&mut input).unwrap(),
+        [
+            (Token::Ident("total".to_string()), span(1, 1, 6)),
+            (Token::Operator(Operator::Equals), span(1, 7, 8)),
+            (Token::Integer(12), span(1, 9, 11)),
+            (Token::Ident("print".to_string()), span(2, 1, 6)),
+            (
+                Token::Punctuation(Punctuation::OpenRoundBracket),
+                span(2, 6, 7)
+            ),
+            (Token::Punctuation(Punctuation::Quote), span(2, 7, 8)),
+            (Token::String("hi".to_string()), span(2, 8, 10)),
+            (Token::Punctuation(Punctuation::Quote), span(2, 10, 11)),
+            (
+                Token::Punctuation(Punctuation::CloseRoundBracket),
+                span(2, 11, 12)
+            ),
+        ]
+    );
+}
+
+#[test]
+pub fn test_reports_unmatched_bracket() {
+    let mut input = "x = (1 + 2))\n".to_string();
+    match lex(&mut input) {
+        Err(LexError::UnmatchedBracket(span)) => {
+            assert_eq!(span, Span::new(Loc::new(1, 12), Loc::new(1, 13)))
+        }
+        other => panic!("expected an unmatched bracket, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn test_reports_unclosed_bracket() {
+    let mut input = "x = 1\ny = 2 * ((x + 1) * 3\n".to_string();
+    match lex(&mut input) {
+        Err(error @ LexError::UnclosedBracket(_)) => {
+            assert!(
+                matches!(error, LexError::UnclosedBracket(span) if span.start == Loc::new(2, 9))
+            );
+            assert_eq!(error.to_string(), "the `(` opened at line 2 isn't closed");
+        }
+        other => panic!("expected an unclosed bracket, got {:?}", other),
+    }
+    let mut input = "print(x, (y\n".to_string();
+    assert!(matches!(lex(&mut input), Err(LexError::UnclosedBracket(_))));
+}
+
+#[test]
+pub fn test_reports_unclosed_multi_line_signature() {
+    for source in ["function f(\n", "function f(a,\n", "function f(a\n"] {
+        let mut input = source.to_string();
+        match lex(&mut input) {
+            Err(LexError::UnclosedBracket(span)) => assert_eq!(span.start, Loc::new(1, 11)),
+            other => panic!(
+                "expected an unclosed bracket for {:?}, got {:?}",
+                source, other
+            ),
+        }
+    }
+}
+
+#[test]
+pub fn test_identifiers_can_contain_digits() {
+    let mut string = "my_var = 1\ncount2 += 1\ntotal = item2+_offset\n".to_string();
+    let tokens = lex(string.as_mut_str()).unwrap();
+    assert_eq!(tokens[0], Token::Ident("my_var".to_string()));
+    assert_eq!(tokens[3], Token::Ident("count2".to_string()));
+    assert_eq!(
+        tokens[8..],
+        [
+            Token::Ident("item2".to_string()),
+            Token::Operator(Operator::Plus),
+            Token::Ident("_offset".to_string())
+        ]
+    );
+    // but an identifier can't start with a digit
+    assert!(lex("2nd = 1\n".to_string().as_mut_str()).is_err());
+}
+
+#[test]
+pub fn test_token_kinds() {
+    let kinds = [
+        (Token::Keyword(Keyword::If), TokenKind::Keyword),
+        (Token::Ident("x".to_string()), TokenKind::Ident),
+        (
+            Token::Punctuation(Punctuation::Comma),
+            TokenKind::Punctuation,
+        ),
+        (Token::Operator(Operator::Plus), TokenKind::Operator),
+        (Token::Integer(1), TokenKind::Integer),
+        (Token::Float(1.5), TokenKind::Float),
+        (Token::String("hi".to_string()), TokenKind::String),
+        (
+            Token::InterpolatedString(vec![StringPart::Placeholder("x".to_string())]),
+            TokenKind::String,
+        ),
+        (Token::Comment(" hi".to_string()), TokenKind::Comment),
+        (
+            Token::MultiLineComment(" hi ".to_string()),
+            TokenKind::Comment,
+        ),
+        (Token::Boolean(true), TokenKind::Boolean),
+        (Token::Indent, TokenKind::Indent),
+        (Token::Dedent, TokenKind::Dedent),
+    ];
+    for (token, kind) in kinds {
+        assert_eq!(token.kind(), kind, "the kind of {:?}", token);
+    }
+}
@@ -0,0 +1,18 @@
+//! A compiler for the OCR A-level "pseudocode" specification.
+
+pub mod analysis;
+pub mod driver;
+pub mod edu_assignments;
+pub mod errorfmt;
+pub mod formatter;
+pub mod interpreter;
+pub mod js_codegen;
+pub mod lexer;
+pub mod lint;
+pub mod llvm_codegen;
+pub mod parser;
+pub mod tail_calls;
+pub mod type_checker;
+pub mod wasm_codegen;
+
+pub use driver::{compile, compile_check, run, CompileError, Target};
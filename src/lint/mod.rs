@@ -0,0 +1,115 @@
+//! Points out code which is valid, but which is probably confusing to read.
+//!
+//! Unlike the type checker, nothing found here stops a program from being compiled.
+
+#[cfg(test)]
+mod unit_tests;
+
+use crate::errorfmt::{Diagnostic, Severity};
+use crate::lexer::Operator;
+use crate::parser::visit::{walk_expression, walk_statement, Visitor};
+use crate::parser::{Expression, Statement};
+use std::collections::HashSet;
+
+/// A warning with the given code (the short name for the kind of thing the linter found).
+fn warning(code: &str, message: String) -> Diagnostic {
+    Diagnostic {
+        code: Some(code.to_string()),
+        ..Diagnostic::new(Severity::Warning, message)
+    }
+}
+
+/// Both `=` and `==` are used to compare values in the same program. Either is fine, but it's easier
+/// to read a program which sticks to one of them.
+fn inconsistent_equality() -> Diagnostic {
+    warning(
+        "inconsistent-equality",
+        "both `=` and `==` are used to compare values; pick one of them".to_string(),
+    )
+}
+
+/// A variable is used after being declared (with `DECLARE`), but before it's been given a value.
+fn uninitialised_variable(ident: &str) -> Diagnostic {
+    warning(
+        "uninitialised-variable",
+        format!("`{}` is used before it's been given a value", ident),
+    )
+}
+
+/// Lints a whole program, returning everything that was found (as warnings).
+pub fn lint(program: &[Statement]) -> Vec<Diagnostic> {
+    lint_located(program)
+        .into_iter()
+        .map(|(_, diagnostic)| diagnostic)
+        .collect()
+}
+
+/// The same as `lint`, except that each diagnostic comes with the index (in `program`) of the
+/// top-level statement it was found in. Mixed equality is found in the statement which first uses
+/// the second kind of comparison.
+pub fn lint_located(program: &[Statement]) -> Vec<(usize, Diagnostic)> {
+    let mut linter = Linter::default();
+    let mut diagnostics = vec![];
+    let mut mixed_equality = None;
+    for (index, statement) in program.iter().enumerate() {
+        linter.visit_statement(statement);
+        diagnostics.extend(
+            linter
+                .diagnostics
+                .drain(..)
+                .map(|diagnostic| (index, diagnostic)),
+        );
+        if linter.single_equals && linter.double_equals && mixed_equality.is_none() {
+            mixed_equality = Some(index);
+        }
+    }
+    if let Some(index) = mixed_equality {
+        diagnostics.push((index, inconsistent_equality()));
+    }
+    diagnostics
+}
+
+#[derive(Default)]
+struct Linter {
+    /// Whether `=` has been used as a comparison (assignments don't count).
+    single_equals: bool,
+    /// Whether `==` has been used.
+    double_equals: bool,
+    /// Variables which have been declared, but not given a value yet.
+    uninitialised: HashSet<String>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Visitor for Linter {
+    fn visit_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Declaration(declaration) => {
+                self.uninitialised.insert(declaration.name.clone());
+            }
+            Statement::AssignmentStatement(assignment) => {
+                walk_statement(self, statement);
+                self.uninitialised.remove(&assignment.ident);
+            }
+            _ => walk_statement(self, statement),
+        }
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::BinaryOp {
+                operator: Operator::Equals,
+                ..
+            } => self.single_equals = true,
+            Expression::BinaryOp {
+                operator: Operator::Comparison,
+                ..
+            } => self.double_equals = true,
+            // removing the variable means that it's only warned about once
+            Expression::Ident(ident) if self.uninitialised.remove(ident) => {
+                self.diagnostics.push(uninitialised_variable(ident))
+            }
+            _ => {}
+        }
+        walk_expression(self, expression)
+    }
+}
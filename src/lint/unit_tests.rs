@@ -0,0 +1,77 @@
+//! Unit tests for the linter.
+
+use crate::errorfmt::Severity;
+use crate::lexer::lex;
+use crate::lint::lint;
+use crate::parser::parse;
+
+/// The codes and messages of the warnings about some source code.
+fn lint_source(source: &str) -> Vec<(String, String)> {
+    let mut source = source.to_string();
+    lint(&parse(lex(&mut source).unwrap()).unwrap())
+        .into_iter()
+        .map(|diagnostic| {
+            assert_eq!(diagnostic.severity, Severity::Warning);
+            (diagnostic.code.unwrap(), diagnostic.message)
+        })
+        .collect()
+}
+
+fn uninitialised(ident: &str) -> (String, String) {
+    (
+        "uninitialised-variable".to_string(),
+        format!("`{}` is used before it's been given a value", ident),
+    )
+}
+
+#[test]
+pub fn test_warns_about_mixed_equality() {
+    let diagnostics = lint_source(
+        "x = 1
+if x == 1 then
+    print(x)
+endif
+while x = 1
+    x = 2
+endwhile",
+    );
+    assert_eq!(
+        diagnostics,
+        vec![(
+            "inconsistent-equality".to_string(),
+            "both `=` and `==` are used to compare values; pick one of them".to_string()
+        )]
+    );
+}
+
+#[test]
+pub fn test_assignment_is_not_a_comparison() {
+    let diagnostics = lint_source(
+        "x = 1
+if x == 1 then
+    print(x)
+endif",
+    );
+    assert!(diagnostics.is_empty());
+}
+
+#[test]
+pub fn test_warns_about_uninitialised_variable() {
+    let diagnostics = lint_source(
+        "DECLARE count : INTEGER
+DECLARE total : INTEGER
+total = 0
+print(count + total)
+print(count)",
+    );
+    assert_eq!(diagnostics, vec![uninitialised("count")]);
+}
+
+#[test]
+pub fn test_declared_variable_is_initialised_by_assignment() {
+    assert_eq!(
+        lint_source("DECLARE name : STRING\nprint(name)\n"),
+        vec![uninitialised("name")]
+    );
+    assert!(lint_source("DECLARE name : STRING\nname = input()\nprint(name)\n").is_empty());
+}
@@ -1,11 +1,3 @@
-mod edu_assignments;
-mod errorfmt;
-mod js_codegen;
-mod lexer;
-mod llvm_codegen;
-mod parser;
-mod type_checker;
-
 fn main() {
     println!("Hello, world!");
 }
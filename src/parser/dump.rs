@@ -0,0 +1,261 @@
+//! Outputs the structure of an AST as an indented tree (e.g. for debugging, or to show students how
+//! a program is understood by the compiler).
+//!
+//! Unlike the formatter, this doesn't output source code: each line is a node, and the node's
+//! children are indented underneath it.
+
+use super::{Block, Expression, Statement, SwitchCasePattern};
+use crate::lexer::StringPart;
+
+/// Outputs the tree for a whole program.
+pub fn dump_ast(program: &[Statement]) -> String {
+    let mut dumper = Dumper::default();
+    for statement in program {
+        dumper.statement(statement);
+    }
+    dumper.output
+}
+
+#[derive(Default)]
+struct Dumper {
+    output: String,
+    depth: usize,
+}
+
+impl Dumper {
+    /// Outputs a line at the current depth.
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.depth {
+            self.output.push_str("  ");
+        }
+        self.output.push_str(text);
+        self.output.push('\n');
+    }
+
+    /// Outputs a line, and then whatever `children` outputs indented underneath it.
+    fn node(&mut self, text: &str, children: impl FnOnce(&mut Self)) {
+        self.line(text);
+        self.depth += 1;
+        children(self);
+        self.depth -= 1;
+    }
+
+    fn block(&mut self, label: &str, block: &Block) {
+        self.node(label, |dumper| {
+            for statement in block {
+                dumper.statement(statement);
+            }
+        });
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::ForStatement(for_statement) => {
+                self.node(&format!("ForStatement {}", for_statement.ident), |dumper| {
+                    dumper.labelled("start", &for_statement.start);
+                    dumper.labelled("stop", &for_statement.stop);
+                    if let Some(step) = &for_statement.step {
+                        dumper.labelled("step", step);
+                    }
+                    dumper.block("block", &for_statement.block);
+                })
+            }
+            Statement::ForEachStatement(for_each) => {
+                self.node(&format!("ForEachStatement {}", for_each.item), |dumper| {
+                    dumper.labelled("iterable", &for_each.iterable);
+                    dumper.block("block", &for_each.block);
+                })
+            }
+            Statement::WhileStatement(while_statement) => self.node("WhileStatement", |dumper| {
+                dumper.labelled("predicate", &while_statement.predicate);
+                dumper.block("block", &while_statement.block);
+            }),
+            Statement::IfStatement(if_statement) => self.node("IfStatement", |dumper| {
+                let cases = std::iter::once(("if", &if_statement.case_if))
+                    .chain(if_statement.cases_elif.iter().map(|case| ("elseif", case)));
+                for (label, case) in cases {
+                    dumper.node(label, |dumper| {
+                        dumper.labelled("predicate", &case.predicate);
+                        dumper.block("block", &case.block);
+                    });
+                }
+                if let Some(case_else) = &if_statement.case_else {
+                    dumper.block("else", &case_else.block);
+                }
+            }),
+            Statement::AssignmentStatement(assignment) => self.node(
+                &format!(
+                    "AssignmentStatement {} {}",
+                    assignment.ident, assignment.operator
+                ),
+                |dumper| dumper.expression(&assignment.expression),
+            ),
+            Statement::ElementAssignment(assignment) => self.node(
+                &format!(
+                    "ElementAssignment {} {}",
+                    assignment.array, assignment.operator
+                ),
+                |dumper| {
+                    for index in &assignment.indexes {
+                        dumper.labelled("index", index);
+                    }
+                    dumper.labelled("value", &assignment.expression);
+                },
+            ),
+            Statement::DoUntilStatement(do_until) => self.node("DoUntilStatement", |dumper| {
+                dumper.block("block", &do_until.block);
+                dumper.labelled("predicate", &do_until.predicate);
+            }),
+            Statement::SwitchStatement(switch) => self.node("SwitchStatement", |dumper| {
+                dumper.labelled("subject", &switch.subject);
+                for case in &switch.cases {
+                    let (label, value) = match &case.pattern {
+                        SwitchCasePattern::Value(value) => ("case".to_string(), value),
+                        SwitchCasePattern::Comparison(operator, value) => {
+                            (format!("case {}", operator), value)
+                        }
+                    };
+                    dumper.node(&label, |dumper| {
+                        dumper.expression(value);
+                        dumper.block("block", &case.block);
+                    });
+                }
+                if let Some(default) = &switch.default {
+                    dumper.block("default", &default.block);
+                }
+            }),
+            Statement::FunctionDefinition(function) => {
+                let mut text = format!("FunctionDefinition {}", function.name);
+                if let Some(returns) = &function.returns {
+                    text.push_str(&format!(" returns {}", returns));
+                }
+                self.node(&text, |dumper| {
+                    for parameter in &function.parameters {
+                        let mut text = format!("parameter {}", parameter.name);
+                        if parameter.by_ref {
+                            text.push_str(":byRef");
+                        }
+                        match &parameter.default {
+                            Some(default) => {
+                                dumper.node(&text, |dumper| dumper.expression(default))
+                            }
+                            None => dumper.line(&text),
+                        }
+                    }
+                    dumper.block("block", &function.block);
+                })
+            }
+            Statement::TryStatement(try_statement) => self.node("TryStatement", |dumper| {
+                dumper.block("body", &try_statement.body);
+                dumper.block(
+                    &format!("catch {}", try_statement.catch_var),
+                    &try_statement.catch_block,
+                );
+            }),
+            Statement::Declaration(declaration) => self.line(&format!(
+                "Declaration {} : {}",
+                declaration.name, declaration.ty
+            )),
+            Statement::TypeAlias(alias) => {
+                self.line(&format!("TypeAlias {} = {}", alias.name, alias.ty))
+            }
+            Statement::ConstDeclaration(declaration) => self.node(
+                &format!("ConstDeclaration {}", declaration.name),
+                |dumper| dumper.expression(&declaration.value),
+            ),
+            Statement::ArrayDeclaration(declaration) => self.node(
+                &format!("ArrayDeclaration {}", declaration.name),
+                |dumper| dumper.expression(&declaration.size),
+            ),
+            Statement::ReturnStatement(expression) => {
+                self.node("ReturnStatement", |dumper| dumper.expression(expression))
+            }
+            Statement::ThrowStatement(expression) => {
+                self.node("ThrowStatement", |dumper| dumper.expression(expression))
+            }
+            Statement::PassStatement => self.line("PassStatement"),
+            Statement::ExpressionStatement(expression) => self
+                .node("ExpressionStatement", |dumper| {
+                    dumper.expression(expression)
+                }),
+        }
+    }
+
+    /// Outputs an expression underneath a label saying what it's for (e.g. `predicate`).
+    fn labelled(&mut self, label: &str, expression: &Expression) {
+        self.node(label, |dumper| dumper.expression(expression));
+    }
+
+    fn expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Integer(integer) => self.line(&format!("Integer {}", integer)),
+            Expression::Float(float) => self.line(&format!("Float {:?}", float)),
+            Expression::Boolean(boolean) => self.line(&format!("Boolean {}", boolean)),
+            Expression::String(string) => self.line(&format!("String {:?}", string)),
+            Expression::InterpolatedString(parts) => self.node("InterpolatedString", |dumper| {
+                for part in parts {
+                    match part {
+                        StringPart::Literal(literal) => dumper.line(&format!("{:?}", literal)),
+                        StringPart::Placeholder(name) => dumper.line(&format!("Ident {}", name)),
+                    }
+                }
+            }),
+            Expression::Ident(ident) => self.line(&format!("Ident {}", ident)),
+            Expression::FunctionCall {
+                name,
+                args,
+                named_args,
+                ..
+            } => self.node(&format!("FunctionCall {}", name), |dumper| {
+                for arg in args {
+                    dumper.expression(arg);
+                }
+                for (name, arg) in named_args {
+                    dumper.labelled(name, arg);
+                }
+            }),
+            Expression::Member { object, member } => self
+                .node(&format!("Member {}", member), |dumper| {
+                    dumper.expression(object)
+                }),
+            Expression::MethodCall {
+                object,
+                name,
+                args,
+                named_args,
+            } => self.node(&format!("MethodCall {}", name), |dumper| {
+                dumper.expression(object);
+                for arg in args {
+                    dumper.expression(arg);
+                }
+                for (name, arg) in named_args {
+                    dumper.labelled(name, arg);
+                }
+            }),
+            Expression::Array(elements) => self.node("Array", |dumper| {
+                for element in elements {
+                    dumper.expression(element);
+                }
+            }),
+            Expression::Index { array, index } => self.node("Index", |dumper| {
+                dumper.expression(array);
+                dumper.expression(index);
+            }),
+            Expression::Slice { array, start, stop } => self.node("Slice", |dumper| {
+                dumper.expression(array);
+                dumper.expression(start);
+                dumper.expression(stop);
+            }),
+            Expression::UnaryOp { operator, operand } => self
+                .node(&format!("UnaryOp {}", operator), |dumper| {
+                    dumper.expression(operand)
+                }),
+            Expression::BinaryOp { operator, lhs, rhs } => {
+                self.node(&format!("BinaryOp {}", operator), |dumper| {
+                    dumper.expression(lhs);
+                    dumper.expression(rhs);
+                })
+            }
+        }
+    }
+}
@@ -0,0 +1,1059 @@
+//! Tests which lex some source code and then parse the tokens.
+
+use crate::formatter;
+use crate::js_codegen;
+use crate::lexer::{lex, lex_spanned, lex_with_options, Dialect, LexOptions, Operator};
+use crate::parser::{
+    dump_ast, parse, parse_spanned, parse_with_options, Cursor, Expression, IfStatement, Parse,
+    ParseError, ParseOptions, Statement, SwitchCasePattern,
+};
+
+/// Lexes and parses a single statement.
+fn parse_statement(string: &str) -> Statement {
+    let mut string = string.to_string();
+    let tokens = lex(string.as_mut_str()).unwrap();
+    let mut cursor = Cursor::new(tokens);
+    let statement = Statement::parse(&mut cursor).unwrap();
+    assert!(cursor.peek().is_err(), "not all tokens were parsed");
+    statement
+}
+
+#[test]
+pub fn test_parses_for_statement() {
+    let statement = parse_statement(
+        r#"
+        for i = 0 to 9
+            print(i)
+        next i
+    "#,
+    );
+    match statement {
+        Statement::ForStatement(for_statement) => {
+            assert_eq!(for_statement.ident, "i");
+            assert_eq!(for_statement.start, Expression::Integer(0));
+            assert_eq!(for_statement.stop, Expression::Integer(9));
+            assert_eq!(for_statement.step, None);
+            assert_eq!(for_statement.block.len(), 1);
+        }
+        other => panic!("expected a for statement, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn test_parses_for_statement_with_step() {
+    let statement = parse_statement(
+        r#"
+        for i = 0 to n step 2
+            total += i
+        next i
+    "#,
+    );
+    match statement {
+        Statement::ForStatement(for_statement) => {
+            assert_eq!(for_statement.stop, Expression::Ident("n".to_string()));
+            assert_eq!(for_statement.step, Some(Expression::Integer(2)));
+            match &for_statement.block[0] {
+                Statement::AssignmentStatement(assignment) => {
+                    assert_eq!(assignment.ident, "total");
+                    assert_eq!(assignment.operator, Operator::Increment);
+                }
+                other => panic!("expected an assignment, got {:?}", other),
+            }
+        }
+        other => panic!("expected a for statement, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn test_parses_for_each_statement() {
+    match parse_statement("for each x in arr\n    print(x)\nnext x\n") {
+        Statement::ForEachStatement(for_each) => {
+            assert_eq!(for_each.item, "x");
+            assert_eq!(for_each.iterable, Expression::Ident("arr".to_string()));
+            assert_eq!(for_each.block.len(), 1);
+        }
+        other => panic!("expected a for each loop, got {:?}", other),
+    }
+    assert_round_trips("for each x in arr\n    print(x)\nnext x\n");
+}
+
+#[test]
+pub fn test_parses_array_literals() {
+    match parse_statement("x = [1, 2 + 3, []]\n") {
+        Statement::AssignmentStatement(assignment) => assert_eq!(
+            assignment.expression,
+            Expression::Array(vec![
+                Expression::Integer(1),
+                Expression::BinaryOp {
+                    operator: Operator::Plus,
+                    lhs: Box::new(Expression::Integer(2)),
+                    rhs: Box::new(Expression::Integer(3)),
+                },
+                Expression::Array(vec![]),
+            ])
+        ),
+        other => panic!("expected an assignment, got {:?}", other),
+    }
+    assert_round_trips("for each x in [1, 2, 3]\n    print(x)\nnext x\n");
+}
+
+#[test]
+pub fn test_parses_while_statement() {
+    let statement = parse_statement(
+        r#"
+        while x != 10
+            x += 1
+        endwhile
+    "#,
+    );
+    match statement {
+        Statement::WhileStatement(while_statement) => {
+            assert!(matches!(
+                while_statement.predicate,
+                Expression::BinaryOp {
+                    operator: Operator::NotEquals,
+                    ..
+                }
+            ));
+            assert_eq!(while_statement.block.len(), 1);
+        }
+        other => panic!("expected a while statement, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn test_parses_function_with_three_statement_block() {
+    let statement = parse_statement(
+        r#"
+        function greet(name, greeting = "Hello")
+            message = greeting + name
+            print(message)
+            return message
+        endfunction
+    "#,
+    );
+    match statement {
+        Statement::FunctionDefinition(function) => {
+            assert_eq!(function.name, "greet");
+            assert_eq!(function.parameters.len(), 2);
+            assert_eq!(function.parameters[0].default, None);
+            assert_eq!(
+                function.parameters[1].default,
+                Some(Expression::String("Hello".to_string()))
+            );
+            assert_eq!(function.block.len(), 3);
+            assert!(matches!(
+                function.block[2],
+                Statement::ReturnStatement(Expression::Ident(_))
+            ));
+        }
+        other => panic!("expected a function definition, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn test_parses_if_statement() {
+    let statement = parse_statement(
+        r#"
+        if x=="a" then
+            print("hello world!")
+        endif
+    "#,
+    );
+    match statement {
+        Statement::IfStatement(if_statement) => {
+            assert_eq!(if_statement.case_if.block.len(), 1);
+            assert!(if_statement.cases_elif.is_empty());
+            assert!(if_statement.case_else.is_none());
+        }
+        other => panic!("expected an if statement, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn test_parses_blocks_using_indentation_tokens() {
+    let mut string = r#"
+        function f(x)
+            if x == 1 then
+                return 2
+            else
+                x += 1
+            endif
+            return x
+        endfunction
+    "#
+    .to_string();
+    let tokens = lex_with_options(
+        string.as_mut_str(),
+        LexOptions {
+            indentation_tokens: true,
+            ..LexOptions::default()
+        },
+    )
+    .unwrap();
+    let mut cursor = Cursor::new(tokens);
+    match Statement::parse(&mut cursor).unwrap() {
+        Statement::FunctionDefinition(function) => {
+            assert_eq!(function.block.len(), 2);
+            match &function.block[0] {
+                Statement::IfStatement(if_statement) => {
+                    assert_eq!(if_statement.case_if.block.len(), 1);
+                    assert_eq!(if_statement.case_else.as_ref().unwrap().block.len(), 1);
+                }
+                other => panic!("expected an if statement, got {:?}", other),
+            }
+        }
+        other => panic!("expected a function definition, got {:?}", other),
+    }
+    assert!(cursor.peek().is_err());
+}
+
+#[test]
+pub fn test_parses_try_statement() {
+    let statement = parse_statement(
+        r#"
+        try
+            x = 1 / 0
+        catch e
+            print(e)
+        endtry
+    "#,
+    );
+    match statement {
+        Statement::TryStatement(try_statement) => {
+            match &try_statement.body[..] {
+                [Statement::AssignmentStatement(assignment)] => assert_eq!(
+                    assignment.expression,
+                    Expression::BinaryOp {
+                        operator: Operator::Divide,
+                        lhs: Box::new(Expression::Integer(1)),
+                        rhs: Box::new(Expression::Integer(0)),
+                    }
+                ),
+                other => panic!("expected a single assignment, got {:?}", other),
+            }
+            assert_eq!(try_statement.catch_var, "e");
+            assert_eq!(try_statement.catch_block.len(), 1);
+        }
+        other => panic!("expected a try statement, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn test_parses_throw_inside_try() {
+    let statement = parse_statement(
+        r#"
+        try
+            throw "something went wrong"
+        catch e
+            print(e)
+        endtry
+    "#,
+    );
+    match statement {
+        Statement::TryStatement(try_statement) => match &try_statement.body[..] {
+            [Statement::ThrowStatement(message)] => assert_eq!(
+                message,
+                &Expression::String("something went wrong".to_string())
+            ),
+            other => panic!("expected a single throw statement, got {:?}", other),
+        },
+        other => panic!("expected a try statement, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn test_parses_declared_return_type() {
+    let statement = parse_statement(
+        r#"
+        function area(r) returns REAL
+            return 3.14 * r * r
+        endfunction
+    "#,
+    );
+    match statement {
+        Statement::FunctionDefinition(function) => {
+            assert_eq!(function.returns, Some("REAL".to_string()));
+            assert_eq!(function.block.len(), 1);
+        }
+        other => panic!("expected a function definition, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn test_parses_named_arguments() {
+    let statement = parse_statement(r#"print(a, b, separator = ", ")"#);
+    match statement {
+        Statement::ExpressionStatement(Expression::FunctionCall {
+            args, named_args, ..
+        }) => {
+            assert_eq!(args.len(), 2);
+            assert_eq!(
+                named_args,
+                vec![(
+                    "separator".to_string(),
+                    Expression::String(", ".to_string())
+                )]
+            );
+        }
+        other => panic!("expected a call to print, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn test_only_parses_named_arguments_for_known_parameters() {
+    let comparison = |lhs: &str, rhs| Expression::BinaryOp {
+        operator: Operator::Equals,
+        lhs: Box::new(Expression::Ident(lhs.to_string())),
+        rhs: Box::new(rhs),
+    };
+    // `print` only has a `separator`, so anything else is a comparison
+    match parse_statement("print(x = 1)") {
+        Statement::ExpressionStatement(Expression::FunctionCall {
+            args, named_args, ..
+        }) => {
+            assert_eq!(args, vec![comparison("x", Expression::Integer(1))]);
+            assert!(named_args.is_empty());
+        }
+        other => panic!("expected a call to print, got {:?}", other),
+    }
+    let mut source = r#"
+        function f(a, b = g(1, 2), c = [3, 4])
+            return a
+        endfunction
+        x = f(a = b)
+        y = f(1, c = 2)
+        z = h(c = 2)
+    "#
+    .to_string();
+    let program = parse(lex(source.as_mut_str()).unwrap()).unwrap();
+    let call = |statement: &Statement| match statement {
+        Statement::AssignmentStatement(assignment) => match &assignment.expression {
+            Expression::FunctionCall {
+                args, named_args, ..
+            } => (args.clone(), named_args.clone()),
+            other => panic!("expected a call, got {:?}", other),
+        },
+        other => panic!("expected an assignment, got {:?}", other),
+    };
+    assert_eq!(
+        call(&program[1]),
+        (
+            vec![],
+            vec![("a".to_string(), Expression::Ident("b".to_string()))]
+        )
+    );
+    assert_eq!(
+        call(&program[2]),
+        (
+            vec![Expression::Integer(1)],
+            vec![("c".to_string(), Expression::Integer(2))]
+        )
+    );
+    // `h` isn't defined, so this is a comparison
+    assert_eq!(
+        call(&program[3]),
+        (vec![comparison("c", Expression::Integer(2))], vec![])
+    );
+}
+
+/// Parses a program, formats it, and checks that parsing the formatted program gives back the
+/// same AST.
+fn assert_round_trips(source: &str) {
+    let mut source = source.to_string();
+    let program = parse(lex(source.as_mut_str()).unwrap()).unwrap();
+    let formatted = formatter::program(&program);
+    let reparsed = match lex(formatted.clone().as_mut_str()) {
+        Ok(tokens) => parse(tokens).unwrap(),
+        Err(e) => panic!("couldn't lex formatted program ({:?}):\n{}", e, formatted),
+    };
+    assert_eq!(program, reparsed, "formatted as:\n{}", formatted);
+}
+
+#[test]
+pub fn test_round_trips_loops() {
+    assert_round_trips(
+        r#"
+        total = 0
+        for i = 1 to 10 step 2
+            total += i
+        next i
+        while total != 0
+            total = total - 1
+        endwhile
+    "#,
+    );
+}
+
+#[test]
+pub fn test_round_trips_conditionals() {
+    assert_round_trips(
+        r#"
+        if x == 1 then
+            print("one")
+        elseif x == 2 OR x == 3 then
+            print("two or three")
+        else
+            if NOT y then
+                print("neither")
+            endif
+        endif
+    "#,
+    );
+}
+
+#[test]
+pub fn test_round_trips_expressions() {
+    assert_round_trips(
+        r#"
+        a = (1 + 2) * 3 - 4 / 5
+        b = 1 + 2 * 3
+        c = -a + 2.5
+        d = arr[i + 1] + arr[1:3][0]
+        print(a, b, separator = ", ")
+    "#,
+    );
+}
+
+#[test]
+pub fn test_round_trips_functions() {
+    assert_round_trips(
+        r#"
+        function area(r, pi = 3.14) returns REAL
+            return pi * r * r
+        endfunction
+        function fizz(n)
+            for i = 1 to n
+                if i == 3 then
+                    print("fizz")
+                else
+                    print(i)
+                endif
+            next i
+            return n
+        endfunction
+    "#,
+    );
+}
+
+#[test]
+pub fn test_round_trips_try_catch() {
+    assert_round_trips(
+        r#"
+        try
+            if x == 0 then
+                throw "x is zero"
+            endif
+            y = 1 / x
+        catch e
+            print(e)
+        endtry
+    "#,
+    );
+}
+
+#[test]
+pub fn test_parses_declaration() {
+    match parse_statement("DECLARE count : INTEGER") {
+        Statement::Declaration(declaration) => {
+            assert_eq!(declaration.name, "count");
+            assert_eq!(declaration.ty, "INTEGER");
+        }
+        other => panic!("expected a declaration, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn test_parses_type_alias() {
+    match parse_statement("TYPE Score = INTEGER") {
+        Statement::TypeAlias(alias) => {
+            assert_eq!(alias.name, "Score");
+            assert_eq!(alias.ty, "INTEGER");
+        }
+        other => panic!("expected a type alias, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn test_parses_print_and_input_statements() {
+    assert_eq!(
+        parse_statement("input userName\n"),
+        parse_statement("userName = input()\n")
+    );
+    assert_eq!(
+        parse_statement("print x, 1\n"),
+        parse_statement("print(x, 1)\n")
+    );
+}
+
+/// Lexes (in the AQA dialect) and parses a single if statement.
+fn parse_aqa_if(source: &str) -> IfStatement {
+    let mut string = source.to_string();
+    let options = LexOptions {
+        dialect: Dialect::Aqa,
+        ..LexOptions::default()
+    };
+    match parse(lex_with_options(string.as_mut_str(), options).unwrap())
+        .unwrap()
+        .remove(0)
+    {
+        Statement::IfStatement(if_statement) => if_statement,
+        other => panic!("expected an if statement, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn test_parses_two_word_else_if() {
+    let if_statement = parse_aqa_if(
+        "if x == 1 then\n    print(1)\nelse if x == 2 then\n    print(2)\nelse\n    print(3)\nendif\n",
+    );
+    assert_eq!(if_statement.cases_elif.len(), 1);
+    assert!(if_statement.case_else.is_some());
+    // an `if` on the line after an `else` is a nested if statement
+    let if_statement = parse_aqa_if(
+        "if x == 1 then\n    print(1)\nelse\n    if x == 2 then\n        print(2)\n    endif\nendif\n",
+    );
+    assert!(if_statement.cases_elif.is_empty());
+    match &if_statement.case_else.unwrap().block[..] {
+        [Statement::IfStatement(nested)] => assert!(nested.case_else.is_none()),
+        other => panic!("expected a nested if statement, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn test_parses_do_until() {
+    match parse_statement("do\n    x = x + 1\nuntil x == 10\n") {
+        Statement::DoUntilStatement(do_until) => {
+            assert_eq!(do_until.block.len(), 1);
+            assert!(matches!(
+                do_until.predicate,
+                Expression::BinaryOp {
+                    operator: Operator::Comparison,
+                    ..
+                }
+            ));
+        }
+        other => panic!("expected a do ... until loop, got {:?}", other),
+    }
+    assert_round_trips("do\n    x = x + 1\nuntil x == 10\n");
+}
+
+#[test]
+pub fn test_parses_aqa_program() {
+    let mut string = r#"
+        count ← 0
+        INPUT name
+        while count != 3
+            OUTPUT name, count
+            count ← count + 1
+        endwhile
+    "#
+    .to_string();
+    let options = LexOptions {
+        dialect: Dialect::Aqa,
+        indentation_tokens: true,
+        ..LexOptions::default()
+    };
+    let program = parse(lex_with_options(string.as_mut_str(), options).unwrap()).unwrap();
+    assert_eq!(program.len(), 3);
+    match &program[0] {
+        Statement::AssignmentStatement(assignment) => {
+            assert_eq!(assignment.ident, "count");
+            assert_eq!(assignment.operator, Operator::Assign);
+        }
+        other => panic!("expected an assignment, got {:?}", other),
+    }
+    match &program[1] {
+        Statement::AssignmentStatement(assignment) => {
+            assert_eq!(assignment.ident, "name");
+            assert!(matches!(
+                &assignment.expression,
+                Expression::FunctionCall { name, .. } if name == "input"
+            ));
+        }
+        other => panic!("expected an assignment, got {:?}", other),
+    }
+    match &program[2] {
+        Statement::WhileStatement(while_statement) => match &while_statement.block[..] {
+            [Statement::ExpressionStatement(Expression::FunctionCall { name, args, .. }), Statement::AssignmentStatement(increment)] =>
+            {
+                assert_eq!(name, "print");
+                assert_eq!(args.len(), 2);
+                assert_eq!(increment.operator, Operator::Assign);
+            }
+            other => panic!("expected an OUTPUT and an assignment, got {:?}", other),
+        },
+        other => panic!("expected a while loop, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn test_arrow_assignment_is_only_for_aqa() {
+    let mut string = "count ← 0".to_string();
+    assert!(lex(string.as_mut_str()).is_err());
+}
+
+#[test]
+pub fn test_parses_switch_with_comparisons() {
+    let statement = parse_statement(
+        r#"
+        switch score:
+            case 0:
+                print("nothing")
+            case != 10:
+                print("some")
+            default:
+                print("full marks")
+        endswitch
+    "#,
+    );
+    match statement {
+        Statement::SwitchStatement(switch) => {
+            assert_eq!(switch.subject, Expression::Ident("score".to_string()));
+            assert_eq!(
+                switch.cases[0].pattern,
+                SwitchCasePattern::Value(Expression::Integer(0))
+            );
+            assert_eq!(
+                switch.cases[1].pattern,
+                SwitchCasePattern::Comparison(Operator::NotEquals, Expression::Integer(10))
+            );
+            assert!(switch.default.is_some());
+        }
+        other => panic!("expected a switch statement, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn test_round_trips_switch() {
+    assert_round_trips(
+        r#"
+        switch score:
+            case 0:
+                print("nothing")
+            case is != 10:
+                print("some")
+            default:
+                print("full marks")
+        endswitch
+    "#,
+    );
+}
+
+#[test]
+pub fn test_parses_pass_in_if_body() {
+    let statement = parse_statement(
+        r#"
+        if x == 1 then
+            pass
+        else
+            print(x)
+        endif
+    "#,
+    );
+    match statement {
+        Statement::IfStatement(if_statement) => {
+            assert_eq!(if_statement.case_if.block, vec![Statement::PassStatement]);
+        }
+        other => panic!("expected an if statement, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn test_round_trips_interpolated_strings() {
+    assert_round_trips(
+        r#"
+        print("x = {x}")
+        print("\{not interpolated\}")
+    "#,
+    );
+}
+
+#[test]
+pub fn test_parses_either_for_terminator() {
+    let with_next = parse_statement("for i = 0 to 9\n    print(i)\nnext i\n");
+    let with_endfor = parse_statement("for i = 0 to 9\n    print(i)\nendfor\n");
+    assert!(matches!(with_endfor, Statement::ForStatement(_)));
+    assert_eq!(with_next, with_endfor);
+}
+
+#[test]
+pub fn test_attaches_comments_to_functions() {
+    let mut string = r#"
+        // Works out the area of a circle.
+        // r is the radius.
+        function area(r)
+            // this comment isn't attached to anything
+            return 3.14 * r * r
+            // and neither is this one
+        endfunction
+        // nor this one
+        x = area(2)
+    "#
+    .to_string();
+    let program = parse(lex(string.as_mut_str()).unwrap()).unwrap();
+    assert_eq!(program.len(), 2);
+    match &program[0] {
+        Statement::FunctionDefinition(function) => {
+            assert_eq!(
+                function.doc.as_deref(),
+                Some("Works out the area of a circle.\nr is the radius.")
+            );
+            assert_eq!(function.block.len(), 1);
+        }
+        other => panic!("expected a function definition, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn test_parses_trailing_comments() {
+    let mut string = r#"
+        x = 1 // set x
+        if x == 1 then // check x
+            print(x / 2) // halve it
+        endif
+    "#
+    .to_string();
+    let program = parse(lex(string.as_mut_str()).unwrap()).unwrap();
+    assert_eq!(program.len(), 2);
+    match &program[1] {
+        Statement::IfStatement(_) => {}
+        other => panic!("expected an if statement, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn test_attaches_multi_line_comments_to_functions() {
+    let mut string = r#"
+/* Works out the area of a circle.
+   r is the radius. */
+function area(r)
+    return 3.14 * r * r
+endfunction
+"#
+    .to_string();
+    let program = parse(lex(string.as_mut_str()).unwrap()).unwrap();
+    match &program[0] {
+        Statement::FunctionDefinition(function) => assert_eq!(
+            function.doc.as_deref(),
+            Some("Works out the area of a circle.\nr is the radius.")
+        ),
+        other => panic!("expected a function definition, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn test_round_trips_doc_comments() {
+    assert_round_trips(
+        r#"
+        // Adds one.
+        function increment(x)
+            return x + 1
+        endfunction
+    "#,
+    );
+}
+
+#[test]
+pub fn test_rejects_nested_functions() {
+    let mut string = r#"
+        function outer(x)
+            function inner(y)
+                return y
+            endfunction
+            return inner(x)
+        endfunction
+    "#
+    .to_string();
+    match parse(lex(string.as_mut_str()).unwrap()) {
+        Err(ParseError::NestedFunction(name)) => assert_eq!(name, "inner"),
+        other => panic!("expected a nested function error, got {:?}", other),
+    }
+}
+
+/// Lexes and parses `source`, which should end inside a block.
+fn unclosed_block_error(source: &str) -> String {
+    let mut string = source.to_string();
+    match parse_spanned(&lex_spanned(string.as_mut_str(), LexOptions::default()).unwrap()) {
+        Err(error @ ParseError::UnclosedBlock { .. }) => error.to_string(),
+        other => panic!("expected an unclosed block error, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn test_reports_missing_endif() {
+    assert_eq!(
+        unclosed_block_error("x = 1\nif x == 1 then\n    print(x)\nelse\n    print(0)\n"),
+        "expected `endif` to close if at line 2, reached end of file"
+    );
+}
+
+#[test]
+pub fn test_reports_missing_endwhile() {
+    // the innermost block is the one reported
+    assert_eq!(
+        unclosed_block_error("if true then\n    while x < 3\n        x += 1\n"),
+        "expected `endwhile` to close while at line 2, reached end of file"
+    );
+}
+
+#[test]
+pub fn test_reports_missing_endfunction() {
+    assert_eq!(
+        unclosed_block_error("function f(x)\n    return x\n"),
+        "expected `endfunction` to close function at line 1, reached end of file"
+    );
+    // without spans the line isn't known
+    let mut string = "function f(x)\n    return x\n".to_string();
+    match parse(lex(string.as_mut_str()).unwrap()) {
+        Err(error @ ParseError::UnclosedBlock { .. }) => assert_eq!(
+            error.to_string(),
+            "expected `endfunction` to close function, reached end of file"
+        ),
+        other => panic!("expected an unclosed block error, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn test_parses_multi_line_function_signature() {
+    let statement = parse_statement(
+        r#"
+        function greet(
+            name,
+            greeting = "Hello",
+            punctuation = "!"
+        )
+            print(greeting, name, punctuation)
+        endfunction
+    "#,
+    );
+    match statement {
+        Statement::FunctionDefinition(function) => {
+            let names = function
+                .parameters
+                .iter()
+                .map(|parameter| parameter.name.as_str())
+                .collect::<Vec<_>>();
+            assert_eq!(names, ["name", "greeting", "punctuation"]);
+            assert_eq!(function.block.len(), 1);
+        }
+        other => panic!("expected a function definition, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn test_implicit_returns() {
+    let source = r#"
+        function square(x)
+            y = x * x
+            y + 0
+        endfunction
+        function cube(x)
+            return x * x * x
+        endfunction
+    "#;
+    let options = ParseOptions {
+        implicit_returns: true,
+    };
+    let mut string = source.to_string();
+    let program = parse_with_options(lex(string.as_mut_str()).unwrap(), options).unwrap();
+    let last_statements = program
+        .iter()
+        .map(|statement| match statement {
+            Statement::FunctionDefinition(function) => function.block.last().unwrap(),
+            other => panic!("expected a function definition, got {:?}", other),
+        })
+        .collect::<Vec<_>>();
+    assert!(matches!(
+        last_statements[..],
+        [Statement::ReturnStatement(_), Statement::ReturnStatement(_)]
+    ));
+    assert_eq!(
+        js_codegen::program(&program[..1]),
+        "function square(x) {\n    y = x * x;\n    return y + 0;\n}\n"
+    );
+    // without the option, the expression's value is thrown away
+    let mut string = source.to_string();
+    match &parse(lex(string.as_mut_str()).unwrap()).unwrap()[0] {
+        Statement::FunctionDefinition(function) => assert!(matches!(
+            function.block.last(),
+            Some(Statement::ExpressionStatement(_))
+        )),
+        other => panic!("expected a function definition, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn test_dumps_ast() {
+    let mut string = r#"
+        if x == 1 then
+            print("one")
+        else
+            y = x + 2
+        endif
+    "#
+    .to_string();
+    let program = parse(lex(string.as_mut_str()).unwrap()).unwrap();
+    assert_eq!(
+        dump_ast(&program),
+        r#"IfStatement
+  if
+    predicate
+      BinaryOp ==
+        Ident x
+        Integer 1
+    block
+      ExpressionStatement
+        FunctionCall print
+          String "one"
+  else
+    AssignmentStatement y =
+      BinaryOp +
+        Ident x
+        Integer 2
+"#
+    );
+}
+
+/// Lexes and parses the expression assigned in `x = <expression>`.
+fn parse_assigned(expression: &str) -> Expression {
+    match parse_statement(&format!("x = {}\n", expression)) {
+        Statement::AssignmentStatement(assignment) => assignment.expression,
+        statement => panic!("expected an assignment, found {:?}", statement),
+    }
+}
+
+fn member(object: Expression, member: &str) -> Expression {
+    Expression::Member {
+        object: Box::new(object),
+        member: member.to_string(),
+    }
+}
+
+fn method_call(object: Expression, name: &str) -> Expression {
+    Expression::MethodCall {
+        object: Box::new(object),
+        name: name.to_string(),
+        args: vec![],
+        named_args: vec![],
+    }
+}
+
+fn index(array: Expression, index: i64) -> Expression {
+    Expression::Index {
+        array: Box::new(array),
+        index: Box::new(Expression::Integer(index)),
+    }
+}
+
+#[test]
+pub fn test_parses_field_chain() {
+    let a = Expression::Ident("a".to_string());
+    assert_eq!(
+        parse_assigned("a.b.c.d"),
+        member(member(member(a.clone(), "b"), "c"), "d")
+    );
+    assert_eq!(
+        parse_assigned("a.b.c.d()"),
+        method_call(member(member(a, "b"), "c"), "d")
+    );
+}
+
+#[test]
+pub fn test_parses_mixed_index_member_and_call_chain() {
+    let a = Expression::Ident("a".to_string());
+    assert_eq!(
+        parse_assigned("a[0].b().c[1]"),
+        index(member(method_call(index(a, 0), "b"), "c"), 1)
+    );
+    // the chain binds more tightly than any operator
+    assert_eq!(
+        parse_assigned("p.x + p.y"),
+        Expression::BinaryOp {
+            operator: Operator::Plus,
+            lhs: Box::new(member(Expression::Ident("p".to_string()), "x")),
+            rhs: Box::new(member(Expression::Ident("p".to_string()), "y")),
+        }
+    );
+}
+
+#[test]
+pub fn test_parses_colon_blocks() {
+    let options = LexOptions {
+        colon_blocks: true,
+        ..LexOptions::default()
+    };
+    let mut colons = "if x > 0:\n    print(1)\nelseif x < 0:\n    print(2)\nelse:\n    print(3)\nendif\nwhile x > 0:\n    x = x - 1\nendwhile\n".to_string();
+    let mut keywords = "if x > 0 then\n    print(1)\nelseif x < 0 then\n    print(2)\nelse\n    print(3)\nendif\nwhile x > 0\n    x = x - 1\nendwhile\n".to_string();
+    assert_eq!(
+        parse(lex_with_options(colons.as_mut_str(), options.clone()).unwrap()).unwrap(),
+        parse(lex(keywords.as_mut_str()).unwrap()).unwrap()
+    );
+    // `then` isn't accepted in place of the colon
+    assert!(lex_with_options(keywords.as_mut_str(), options).is_err());
+}
+
+#[test]
+pub fn test_parses_by_value_parameters() {
+    match parse_statement("function f(arg:byVal)\n    return arg\nendfunction\n") {
+        Statement::FunctionDefinition(function) => {
+            assert_eq!(function.parameters[0].name, "arg");
+            assert!(!function.parameters[0].by_ref);
+        }
+        statement => panic!("expected a function definition, found {:?}", statement),
+    }
+}
+
+#[test]
+pub fn test_parses_by_reference_parameters() {
+    match parse_statement("function swap(a:byRef, b:byRef, c)\n    pass\nendfunction\n") {
+        Statement::FunctionDefinition(function) => assert_eq!(
+            function
+                .parameters
+                .iter()
+                .map(|parameter| parameter.by_ref)
+                .collect::<Vec<_>>(),
+            vec![true, true, false]
+        ),
+        statement => panic!("expected a function definition, found {:?}", statement),
+    }
+    assert_round_trips("function swap(a:byRef, b)\n    a = b\nendfunction\n");
+}
+
+#[test]
+pub fn test_parses_element_assignments() {
+    match parse_statement("grid[i][arr[0]] += 1") {
+        Statement::ElementAssignment(assignment) => {
+            assert_eq!(assignment.array, "grid");
+            assert_eq!(assignment.indexes.len(), 2);
+            assert_eq!(assignment.operator, Operator::Increment);
+            assert_eq!(assignment.expression, Expression::Integer(1));
+        }
+        statement => panic!("expected an element assignment, found {:?}", statement),
+    }
+    // a comparison on its own is still an expression
+    assert!(matches!(
+        parse_statement("grid[i] == 1"),
+        Statement::ExpressionStatement(_)
+    ));
+    assert_round_trips("names[0] = \"Ada\"\ngrid[i + 1][0] += 2\n");
+}
+
+#[test]
+pub fn test_parses_while_do() {
+    let options = LexOptions {
+        while_do: true,
+        ..LexOptions::default()
+    };
+    let mut with_do = "while x > 0 do\n    x = x - 1\nendwhile\n".to_string();
+    let mut without_do = "while x > 0\n    x = x - 1\nendwhile\n".to_string();
+    let expected = parse(lex(without_do.as_mut_str()).unwrap()).unwrap();
+    assert_eq!(
+        parse(lex_with_options(with_do.as_mut_str(), options.clone()).unwrap()).unwrap(),
+        expected
+    );
+    assert_eq!(
+        parse(lex_with_options(without_do.as_mut_str(), options).unwrap()).unwrap(),
+        expected
+    );
+}
@@ -2,20 +2,167 @@
 //!
 //! This AST can then be operated on to output LLVM IR or Javascript code.
 
-use crate::lexer::{Operator, Token};
+mod dump;
+#[cfg(test)]
+mod integration_tests;
+#[cfg(test)]
+mod unit_tests;
+pub mod visit;
+
+pub use dump::dump_ast;
+
+use crate::lexer::{
+    Keyword, Operator, Punctuation, Span, StringPart, Token, TokenKind, TokenStream,
+};
+use std::collections::HashMap;
 use thiserror::Error as ThisError;
 
+/// Options which change the parser's behaviour.
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    /// If a function ends with an expression on its own (rather than a `return`), return the value
+    /// of that expression (as some dialects do).
+    pub implicit_returns: bool,
+}
+
+/// The same as `parse`, except that the parser's behaviour can be changed with `options`.
+pub fn parse_with_options(
+    tokens: Vec<Token>,
+    options: ParseOptions,
+) -> Result<Vec<Statement>, ParseError> {
+    let mut program = parse(tokens)?;
+    if options.implicit_returns {
+        for statement in &mut program {
+            if let Statement::FunctionDefinition(function) = statement {
+                if let Some(last) = function.block.last_mut() {
+                    if let Statement::ExpressionStatement(expression) = last {
+                        *last = Statement::ReturnStatement(expression.clone());
+                    }
+                }
+            }
+        }
+    }
+    Ok(program)
+}
+
 /// A program consists of a series of statements.
 /// This function constructs an abstract syntax tree from the token outputted
 /// by the lexer.
-pub fn parse(tokens: Vec<Token>) -> Vec<Statement> {
-    todo!()
+pub fn parse(tokens: Vec<Token>) -> Result<Vec<Statement>, ParseError> {
+    Ok(parse_program(&mut Cursor::new(tokens))?.0)
+}
+
+/// Parses the output of `lexer::lex_spanned`. This is the same as `parse`, except that the errors
+/// can say which line things are on.
+pub fn parse_spanned(stream: &TokenStream) -> Result<Vec<Statement>, ParseError> {
+    parse_located(stream)
+        .map(|(program, _)| program)
+        .map_err(|(_, error)| error)
+}
+
+/// The statements in a program, along with the span of each of them.
+pub type LocatedProgram = (Vec<Statement>, Vec<Span>);
+
+/// Parses the output of `lexer::lex_spanned`, also returning the span of each of the (top-level)
+/// statements in the program. If there's an error, it comes with the span of the token it's about
+/// (or, if the input ended inside a block, the keyword which opened the block).
+pub fn parse_located(stream: &TokenStream) -> Result<LocatedProgram, (Option<Span>, ParseError)> {
+    let mut cursor = Cursor::with_spans(stream.tokens().to_vec(), stream.spans().to_vec());
+    parse_program(&mut cursor).map_err(|error| (cursor.error_span(&error), error))
+}
+
+/// Parses a whole program, returning its statements and the span of each of them (which is empty
+/// if the cursor doesn't know the spans).
+fn parse_program(cursor: &mut Cursor) -> Result<LocatedProgram, ParseError> {
+    let mut statements = vec![];
+    let mut spans = vec![];
+    loop {
+        let doc = parse_comments(cursor)?;
+        if cursor.peek_kind() == TokenKind::Eof {
+            return Ok((statements, spans));
+        }
+        let start = cursor.position;
+        statements.push(parse_documented_statement(cursor, doc)?);
+        spans.extend(cursor.span_from(start));
+    }
+}
+
+/// Eats any comments, returning their text (with one line for each line of the comments).
+fn parse_comments(cursor: &mut Cursor) -> Result<Option<String>, ParseError> {
+    let mut lines = vec![];
+    loop {
+        match cursor.peek() {
+            Ok(Token::Comment(comment)) => lines.push(comment.trim().to_string()),
+            Ok(Token::MultiLineComment(comment)) => {
+                lines.extend(comment.trim().lines().map(|line| line.trim().to_string()))
+            }
+            _ => break,
+        }
+        cursor.eat()?;
+    }
+    Ok(if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join("\n"))
+    })
+}
+
+/// Parses a statement which comes after the comments in `doc`. If it's a function definition, the
+/// comments become its documentation (otherwise they're thrown away).
+fn parse_documented_statement(
+    cursor: &mut Cursor,
+    doc: Option<String>,
+) -> Result<Statement, ParseError> {
+    let mut statement = Statement::parse(cursor)?;
+    if let Statement::FunctionDefinition(function) = &mut statement {
+        function.doc = doc;
+    }
+    Ok(statement)
+}
+
+/// Parses a standalone expression (e.g. for a calculator-style REPL).
+///
+/// All of the tokens must be part of the expression; any left over are an error.
+pub fn parse_expression(tokens: &[Token]) -> Result<Expression, ParseError> {
+    let mut cursor = Cursor::new(tokens.to_vec());
+    let expression = Expression::parse(&mut cursor)?;
+    if let Ok(token) = cursor.peek() {
+        return Err(ParseError::UnexpectedToken(token));
+    }
+    Ok(expression)
 }
 
 #[derive(ThisError, Debug)]
 pub enum ParseError {
     #[error("unexpected end of input")]
     UnexpectedEndOfInput,
+    /// The input ended inside a block (e.g. an `if` statement without its `endif`). The line of the
+    /// keyword which opened the block is only known if the tokens came with their spans (see
+    /// `parse_spanned`).
+    #[error(
+        "expected `{terminator}` to close {opener}{}, reached end of file",
+        at_line(.line)
+    )]
+    UnclosedBlock {
+        opener: Keyword,
+        terminator: Keyword,
+        line: Option<u32>,
+    },
+    #[error("didn't expect this token")]
+    UnexpectedToken(Token),
+    #[error("expected `next {expected}` (to match the loop variable), found `next {found}`")]
+    MismatchedLoopVariable { expected: String, found: String },
+    /// Functions have to be defined at the top level of a program (as they are in OCR's guide), so
+    /// they can't be defined inside another function (or any other block).
+    #[error("`{0}` is defined inside a block, but functions can only be defined at the top level")]
+    NestedFunction(String),
+}
+
+fn at_line(line: &Option<u32>) -> String {
+    match line {
+        Some(line) => format!(" at line {}", line),
+        None => String::new(),
+    }
 }
 
 /// This trait is used to parse tokens from the lexer's output.
@@ -25,26 +172,177 @@ pub trait Parse<OUTPUT = Self> {
 
 /// A cursor for reading from a stream of tokens.
 ///
-/// Unlike in the case of the lexer, this doesn't need to keep track of `Span`s
-//// because these are already inside the tokens.
+/// Unlike in the case of the lexer, this doesn't need to work out any `Span`s; it only knows where
+/// each token is if the tokens came from `lexer::lex_spanned` (for error messages).
 pub struct Cursor {
     tokens: Vec<Token>,
+    /// The index of the next token to be read.
+    position: usize,
+    /// The span of each token (this is empty if the spans aren't known).
+    spans: Vec<Span>,
+    /// The blocks which haven't been closed yet, innermost last.
+    open_blocks: Vec<OpenBlock>,
+    /// The names of the parameters of each function defined in the tokens, which are the names a
+    /// call to it can give its arguments (these are found before anything is parsed, so that a
+    /// function can be called above its definition).
+    parameters: HashMap<String, Vec<String>>,
+}
+
+/// Finds the names of the parameters of every function defined in some tokens (without parsing
+/// them).
+fn find_parameters(tokens: &[Token]) -> HashMap<String, Vec<String>> {
+    let mut functions = HashMap::new();
+    for (index, window) in tokens.windows(3).enumerate() {
+        let name = match window {
+            [Token::Keyword(Keyword::Function), Token::Ident(name), Token::Punctuation(Punctuation::OpenRoundBracket)] => {
+                name
+            }
+            _ => continue,
+        };
+        let mut parameters = vec![];
+        // each parameter's name comes first, followed by its default value (if it has one), which
+        // can contain brackets and commas of its own
+        let mut depth = 0;
+        let mut expecting_name = true;
+        for token in &tokens[index + 3..] {
+            match token {
+                Token::Punctuation(
+                    Punctuation::CloseRoundBracket | Punctuation::CloseSquareBracket,
+                ) if depth == 0 => break,
+                Token::Punctuation(
+                    Punctuation::OpenRoundBracket | Punctuation::OpenSquareBracket,
+                ) => depth += 1,
+                Token::Punctuation(
+                    Punctuation::CloseRoundBracket | Punctuation::CloseSquareBracket,
+                ) => depth -= 1,
+                Token::Punctuation(Punctuation::Comma) if depth == 0 => expecting_name = true,
+                Token::Ident(parameter) if expecting_name => {
+                    parameters.push(parameter.clone());
+                    expecting_name = false;
+                }
+                _ => {}
+            }
+        }
+        functions.insert(name.clone(), parameters);
+    }
+    functions
+}
+
+/// A block which has been opened (see `Cursor::open_block`).
+struct OpenBlock {
+    opener: Keyword,
+    terminator: Keyword,
+    /// The span of the opener (if the spans are known).
+    span: Option<Span>,
 }
 
 impl Cursor {
     /// Construct a new cursor from the token stream.
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens }
+        Self::with_spans(tokens, vec![])
+    }
+    /// Construct a new cursor from the token stream and the span of each token.
+    pub fn with_spans(tokens: Vec<Token>, spans: Vec<Span>) -> Self {
+        Self {
+            parameters: find_parameters(&tokens),
+            tokens,
+            position: 0,
+            spans,
+            open_blocks: vec![],
+        }
+    }
+    /// Whether a call to `function` can give an argument by naming the `parameter` it's for (as
+    /// in `print(a, b, separator = ", ")`).
+    fn takes_parameter(&self, function: &str, parameter: &str) -> bool {
+        match function {
+            "print" => parameter == "separator",
+            _ => self
+                .parameters
+                .get(function)
+                .is_some_and(|parameters| parameters.iter().any(|name| name == parameter)),
+        }
+    }
+    /// Records that the next token (`opener`) starts a block which is closed by `terminator`, so
+    /// that if the input ends before the block does the error can say which block wasn't closed.
+    pub fn open_block(&mut self, opener: Keyword, terminator: Keyword) {
+        let span = self.spans.get(self.position).copied();
+        self.open_blocks.push(OpenBlock {
+            opener,
+            terminator,
+            span,
+        });
+    }
+    /// The span covering the tokens from the one at index `start` up to (but not including) the
+    /// next token (if the spans are known).
+    fn span_from(&self, start: usize) -> Option<Span> {
+        let first = self.spans.get(start)?;
+        let last = self.spans.get(self.position.checked_sub(1)?)?;
+        Some(Span::new(first.start, last.stop))
+    }
+    /// Records that the innermost block has been closed.
+    pub fn close_block(&mut self) {
+        self.open_blocks.pop();
+    }
+    /// The error for reaching the end of the input inside the innermost block.
+    fn unclosed_block(&self) -> ParseError {
+        match self.open_blocks.last() {
+            Some(block) => ParseError::UnclosedBlock {
+                opener: block.opener,
+                terminator: block.terminator,
+                line: block.span.map(|span| span.start.line()),
+            },
+            None => ParseError::UnexpectedEndOfInput,
+        }
+    }
+    /// The part of the source code an error found at the cursor is about (if the spans are known).
+    /// This is the keyword which opened the unclosed block for `ParseError::UnclosedBlock`, and
+    /// otherwise the last token read (which is the unexpected one, for `UnexpectedToken`).
+    fn error_span(&self, error: &ParseError) -> Option<Span> {
+        match (error, self.open_blocks.last()) {
+            (ParseError::UnclosedBlock { .. }, Some(block)) => block.span,
+            _ => self.spans.get(self.position.checked_sub(1)?).copied(),
+        }
     }
     /// Retrieves the next token in the input stream, without advancing the position
     /// of the cursor. If the stream has been exhausted, it will return an error of
     /// instance of `ParseError`. Using the `?` operator provides an ergonomic way
     /// to propagate errors within implementations of `Parse`.
     pub fn peek(&self) -> Result<Token, ParseError> {
-        todo!()
+        self.tokens
+            .get(self.position)
+            .cloned()
+            .ok_or(ParseError::UnexpectedEndOfInput)
     }
+    /// The kind of the next token (`TokenKind::Eof` if the stream has been exhausted), which is
+    /// cheaper than `peek` because the token isn't cloned.
+    pub fn peek_kind(&self) -> TokenKind {
+        self.tokens
+            .get(self.position)
+            .map_or(TokenKind::Eof, Token::kind)
+    }
+    /// Retrieves the token `n` places after the next one, without advancing the position of the
+    /// cursor (so `peek_nth(0)` is equivalent to `peek()`).
+    pub fn peek_nth(&self, n: usize) -> Result<Token, ParseError> {
+        self.tokens
+            .get(self.position + n)
+            .cloned()
+            .ok_or(ParseError::UnexpectedEndOfInput)
+    }
+    /// Retrieves the next token in the input stream and advances the position of the cursor past
+    /// it.
     pub fn eat(&mut self) -> Result<Token, ParseError> {
-        todo!()
+        let token = self.peek()?;
+        self.position += 1;
+        Ok(token)
+    }
+    /// Eats the next token, returning an error if it isn't `expected`.
+    pub fn expect(&mut self, expected: Token) -> Result<(), ParseError> {
+        let token = self.eat()?;
+        if token == expected {
+            Ok(())
+        } else {
+            Err(ParseError::UnexpectedToken(token))
+        }
     }
 }
 
@@ -54,84 +352,1056 @@ impl Cursor {
 /// langauge soon.
 ///
 /// There are a lot of statements in this language :P
+#[derive(Debug, PartialEq)]
 pub enum Statement {
-    ForStatement,
-    WhileStatement,
-    IfStatement,
-    AssignmentStatement,
-    DoUntilStatement,
-    SwitchStatement,
+    ForStatement(ForStatement),
+    ForEachStatement(ForEachStatement),
+    WhileStatement(WhileStatement),
+    IfStatement(IfStatement),
+    AssignmentStatement(AssignmentStatement),
+    /// An assignment to an element of an array (e.g. `grid[i][j] = 0`).
+    ElementAssignment(ElementAssignment),
+    DoUntilStatement(DoUntilStatement),
+    SwitchStatement(SwitchStatement),
+    FunctionDefinition(FunctionDefinition),
+    TryStatement(TryStatement),
+    Declaration(Declaration),
+    TypeAlias(TypeAlias),
+    ConstDeclaration(ConstDeclaration),
+    ArrayDeclaration(ArrayDeclaration),
+    ReturnStatement(Expression),
+    /// Raises an error with the given message (which can be caught by a `TryStatement`).
+    ThrowStatement(Expression),
+    /// Does nothing; this is useful for stubbing out a block which hasn't been written yet.
+    PassStatement,
+    /// An expression on its own (e.g. a call to `print`).
+    ExpressionStatement(Expression),
 }
 
 impl Parse for Statement {
     fn parse(cursor: &mut Cursor) -> Result<Self, ParseError> {
-        todo!()
+        // only statements which start with a keyword need the token itself, so the others are
+        // told apart by kind
+        match cursor.peek_kind() {
+            TokenKind::Keyword => {}
+            TokenKind::Eof => return Err(ParseError::UnexpectedEndOfInput),
+            TokenKind::Ident
+                if matches!(
+                    cursor.peek_nth(1),
+                    Ok(Token::Operator(Operator::Equals))
+                        | Ok(Token::Operator(Operator::Increment))
+                        | Ok(Token::Operator(Operator::Assign))
+                ) =>
+            {
+                return Ok(Statement::AssignmentStatement(AssignmentStatement::parse(
+                    cursor,
+                )?))
+            }
+            TokenKind::Ident if is_element_assignment(cursor) => {
+                return Ok(Statement::ElementAssignment(ElementAssignment::parse(
+                    cursor,
+                )?))
+            }
+            _ => return Ok(Statement::ExpressionStatement(Expression::parse(cursor)?)),
+        }
+        Ok(match cursor.peek()? {
+            Token::Keyword(Keyword::For)
+                if cursor.peek_nth(1)? == Token::Keyword(Keyword::Each) =>
+            {
+                Statement::ForEachStatement(ForEachStatement::parse(cursor)?)
+            }
+            Token::Keyword(Keyword::For) => Statement::ForStatement(ForStatement::parse(cursor)?),
+            Token::Keyword(Keyword::While) => {
+                Statement::WhileStatement(WhileStatement::parse(cursor)?)
+            }
+            Token::Keyword(Keyword::If) => Statement::IfStatement(IfStatement::parse(cursor)?),
+            Token::Keyword(Keyword::Do) => {
+                Statement::DoUntilStatement(DoUntilStatement::parse(cursor)?)
+            }
+            Token::Keyword(Keyword::Switch) => {
+                Statement::SwitchStatement(SwitchStatement::parse(cursor)?)
+            }
+            Token::Keyword(Keyword::Function) => {
+                Statement::FunctionDefinition(FunctionDefinition::parse(cursor)?)
+            }
+            Token::Keyword(Keyword::Try) => Statement::TryStatement(TryStatement::parse(cursor)?),
+            // `OUTPUT a, b` (and `print a, b`) does the same thing as `print(a, b)`
+            Token::Keyword(Keyword::Output) | Token::Keyword(Keyword::Print) => {
+                cursor.eat()?;
+                let mut args = vec![Expression::parse(cursor)?];
+                while matches!(cursor.peek(), Ok(Token::Punctuation(Punctuation::Comma))) {
+                    cursor.eat()?;
+                    args.push(Expression::parse(cursor)?);
+                }
+                Statement::ExpressionStatement(Expression::FunctionCall {
+                    name: "print".to_string(),
+                    args,
+                    named_args: vec![],
+                    spans: Box::default(),
+                })
+            }
+            // and `INPUT x` (or `input x`) does the same thing as `x = input()`
+            Token::Keyword(Keyword::Input) => {
+                cursor.eat()?;
+                Statement::AssignmentStatement(AssignmentStatement {
+                    ident: parse_ident(cursor)?,
+                    operator: Operator::Equals,
+                    expression: Expression::FunctionCall {
+                        name: "input".to_string(),
+                        args: vec![],
+                        named_args: vec![],
+                        spans: Box::default(),
+                    },
+                })
+            }
+            Token::Keyword(Keyword::Declare) => Statement::Declaration(Declaration::parse(cursor)?),
+            Token::Keyword(Keyword::Type) => Statement::TypeAlias(TypeAlias::parse(cursor)?),
+            Token::Keyword(Keyword::Const) => {
+                Statement::ConstDeclaration(ConstDeclaration::parse(cursor)?)
+            }
+            Token::Keyword(Keyword::Array) => {
+                Statement::ArrayDeclaration(ArrayDeclaration::parse(cursor)?)
+            }
+            Token::Keyword(Keyword::Return) => {
+                cursor.eat()?;
+                Statement::ReturnStatement(Expression::parse(cursor)?)
+            }
+            Token::Keyword(Keyword::Throw) => {
+                cursor.eat()?;
+                Statement::ThrowStatement(Expression::parse(cursor)?)
+            }
+            Token::Keyword(Keyword::Pass) => {
+                cursor.eat()?;
+                Statement::PassStatement
+            }
+            _ => return Err(ParseError::UnexpectedToken(cursor.eat()?)),
+        })
+    }
+}
+
+/// Parses an identifier, returning its name.
+fn parse_ident(cursor: &mut Cursor) -> Result<String, ParseError> {
+    match cursor.eat()? {
+        Token::Ident(ident) => Ok(ident),
+        token => Err(ParseError::UnexpectedToken(token)),
     }
 }
 
 /// A block consists of zero or more statements.
 type Block = Vec<Statement>;
 
+/// Parses statements until it reaches a keyword which ends a block (e.g. `endwhile` or `else`).
+///
+/// The keyword which ends the block isn't consumed, so the caller should check that it's the one
+/// they were expecting.
+///
+/// If the lexer emitted `Token::Indent` and `Token::Dedent`, these are used to find the end of the
+/// block instead.
+///
+/// Reaching the end of the input is a `ParseError::UnclosedBlock` (for the block most recently
+/// opened with `Cursor::open_block`).
+pub fn parse_block(cursor: &mut Cursor) -> Result<Block, ParseError> {
+    let mut block = vec![];
+    if cursor.peek_kind() == TokenKind::Indent {
+        cursor.eat()?;
+        loop {
+            let doc = parse_comments(cursor)?;
+            match cursor.peek_kind() {
+                TokenKind::Dedent => break,
+                TokenKind::Eof => return Err(cursor.unclosed_block()),
+                _ => {}
+            }
+            block.push(parse_block_statement(cursor, doc)?);
+        }
+        cursor.eat()?;
+        return Ok(block);
+    }
+    loop {
+        let doc = parse_comments(cursor)?;
+        match cursor.peek().map_err(|_| cursor.unclosed_block())? {
+            Token::Keyword(Keyword::EndFunction)
+            | Token::Keyword(Keyword::ElseIf)
+            | Token::Keyword(Keyword::Else)
+            | Token::Keyword(Keyword::EndIf)
+            | Token::Keyword(Keyword::Case)
+            | Token::Keyword(Keyword::Default)
+            | Token::Keyword(Keyword::EndSwitch)
+            | Token::Keyword(Keyword::EndWhile)
+            | Token::Keyword(Keyword::Until)
+            | Token::Keyword(Keyword::Next)
+            | Token::Keyword(Keyword::EndFor)
+            | Token::Keyword(Keyword::Catch)
+            | Token::Keyword(Keyword::EndTry) => return Ok(block),
+            _ => block.push(parse_block_statement(cursor, doc)?),
+        }
+    }
+}
+
+/// Parses a statement inside a block, rejecting function definitions (see
+/// `ParseError::NestedFunction`).
+fn parse_block_statement(
+    cursor: &mut Cursor,
+    doc: Option<String>,
+) -> Result<Statement, ParseError> {
+    match parse_documented_statement(cursor, doc)? {
+        Statement::FunctionDefinition(function) => Err(ParseError::NestedFunction(function.name)),
+        statement => Ok(statement),
+    }
+}
+
+/// An assignment (`x = 1`, or using the "syntactic sugar" `x += 1`).
+#[derive(Debug, PartialEq)]
+pub struct AssignmentStatement {
+    pub ident: String,
+    /// `Operator::Equals`, `Operator::Increment` or (in the AQA dialect) `Operator::Assign`.
+    pub operator: Operator,
+    pub expression: Expression,
+}
+
+impl Parse for AssignmentStatement {
+    fn parse(cursor: &mut Cursor) -> Result<Self, ParseError> {
+        let ident = parse_ident(cursor)?;
+        let operator = match cursor.eat()? {
+            Token::Operator(operator @ Operator::Equals)
+            | Token::Operator(operator @ Operator::Increment)
+            | Token::Operator(operator @ Operator::Assign) => operator,
+            token => return Err(ParseError::UnexpectedToken(token)),
+        };
+        Ok(Self {
+            ident,
+            operator,
+            expression: Expression::parse(cursor)?,
+        })
+    }
+}
+
+/// An assignment to an element of an array (`names[0] = "Ada"`, or `grid[i][j] += 1` for an array
+/// of arrays).
+#[derive(Debug, PartialEq)]
+pub struct ElementAssignment {
+    pub array: String,
+    /// The index into the array, followed by the indexes into each of the arrays inside it.
+    pub indexes: Vec<Expression>,
+    /// `Operator::Equals`, `Operator::Increment` or (in the AQA dialect) `Operator::Assign`.
+    pub operator: Operator,
+    pub expression: Expression,
+}
+
+impl Parse for ElementAssignment {
+    fn parse(cursor: &mut Cursor) -> Result<Self, ParseError> {
+        let array = parse_ident(cursor)?;
+        let mut indexes = vec![];
+        while cursor.peek()? == Token::Punctuation(Punctuation::OpenSquareBracket) {
+            cursor.eat()?;
+            indexes.push(Expression::parse(cursor)?);
+            cursor.expect(Token::Punctuation(Punctuation::CloseSquareBracket))?;
+        }
+        let operator = match cursor.eat()? {
+            Token::Operator(operator @ Operator::Equals)
+            | Token::Operator(operator @ Operator::Increment)
+            | Token::Operator(operator @ Operator::Assign) => operator,
+            token => return Err(ParseError::UnexpectedToken(token)),
+        };
+        Ok(Self {
+            array,
+            indexes,
+            operator,
+            expression: Expression::parse(cursor)?,
+        })
+    }
+}
+
+/// Whether the next tokens (a variable followed by a `[`) start an assignment to an element of an
+/// array (`grid[i][j] = 0`) rather than an expression (`grid[i][j] == 0`). This looks past the
+/// indexes (which can contain brackets themselves) for an assignment operator.
+fn is_element_assignment(cursor: &Cursor) -> bool {
+    let mut depth = 0;
+    for n in 1.. {
+        match cursor.peek_nth(n) {
+            Ok(Token::Punctuation(Punctuation::OpenSquareBracket)) => depth += 1,
+            Ok(Token::Punctuation(Punctuation::CloseSquareBracket)) if depth > 0 => depth -= 1,
+            Ok(Token::Operator(Operator::Equals))
+            | Ok(Token::Operator(Operator::Increment))
+            | Ok(Token::Operator(Operator::Assign))
+                if depth == 0 =>
+            {
+                return true
+            }
+            // inside an index, anything could come next
+            Ok(_) if depth > 0 => {}
+            _ => return false,
+        }
+    }
+    false
+}
+
 /// A "do ... until ..." statement.
+#[derive(Debug, PartialEq)]
 pub struct DoUntilStatement {
-    predicate: Expression,
-    block: Block,
+    pub predicate: Expression,
+    pub block: Block,
+}
+
+impl Parse for DoUntilStatement {
+    fn parse(cursor: &mut Cursor) -> Result<Self, ParseError> {
+        cursor.open_block(Keyword::Do, Keyword::Until);
+        cursor.expect(Token::Keyword(Keyword::Do))?;
+        let block = parse_block(cursor)?;
+        cursor.expect(Token::Keyword(Keyword::Until))?;
+        cursor.close_block();
+        let predicate = Expression::parse(cursor)?;
+        Ok(Self { predicate, block })
+    }
 }
 
 /// A switch statement.
+#[derive(Debug, PartialEq)]
 pub struct SwitchStatement {
-    cases: Vec<SwitchCase>,
-    default: Vec<DefaultCase>,
+    /// The value which is compared against each case.
+    pub subject: Expression,
+    pub cases: Vec<SwitchCase>,
+    pub default: Option<DefaultCase>,
+}
+
+impl Parse for SwitchStatement {
+    fn parse(cursor: &mut Cursor) -> Result<Self, ParseError> {
+        cursor.open_block(Keyword::Switch, Keyword::EndSwitch);
+        cursor.expect(Token::Keyword(Keyword::Switch))?;
+        let subject = Expression::parse(cursor)?;
+        cursor.expect(Token::Punctuation(Punctuation::Colon))?;
+        let mut cases = vec![];
+        while cursor.peek()? == Token::Keyword(Keyword::Case) {
+            cursor.eat()?;
+            if cursor.peek()? == Token::Keyword(Keyword::Is) {
+                cursor.eat()?;
+            }
+            let pattern = match cursor.peek()? {
+                Token::Operator(operator) if operator.is_comparison() => {
+                    cursor.eat()?;
+                    SwitchCasePattern::Comparison(operator, Expression::parse(cursor)?)
+                }
+                _ => SwitchCasePattern::Value(Expression::parse(cursor)?),
+            };
+            cursor.expect(Token::Punctuation(Punctuation::Colon))?;
+            cases.push(SwitchCase {
+                pattern,
+                block: parse_block(cursor)?,
+            });
+        }
+        let default = if cursor.peek()? == Token::Keyword(Keyword::Default) {
+            cursor.eat()?;
+            cursor.expect(Token::Punctuation(Punctuation::Colon))?;
+            Some(DefaultCase {
+                block: parse_block(cursor)?,
+            })
+        } else {
+            None
+        };
+        cursor.expect(Token::Keyword(Keyword::EndSwitch))?;
+        cursor.close_block();
+        Ok(Self {
+            subject,
+            cases,
+            default,
+        })
+    }
 }
 
+#[derive(Debug, PartialEq)]
 pub struct SwitchCase {
-    predicate: Expression,
-    block: Block,
+    pub pattern: SwitchCasePattern,
+    pub block: Block,
 }
 
+/// What a case in a switch statement matches.
+#[derive(Debug, PartialEq)]
+pub enum SwitchCasePattern {
+    /// The subject is equal to the value (`case 1:`).
+    Value(Expression),
+    /// The subject compares to the value in the given way (`case != 0:` or `case is != 0:`).
+    Comparison(Operator, Expression),
+}
+
+#[derive(Debug, PartialEq)]
 pub struct DefaultCase {
-    block: Block,
+    pub block: Block,
 }
 
+#[derive(Debug, PartialEq)]
 pub struct IfStatement {
-    case_if: If,
-    cases_elif: Vec<If>,
-    case_else: Else,
+    pub case_if: If,
+    pub cases_elif: Vec<If>,
+    pub case_else: Option<Else>,
+}
+
+impl Parse for IfStatement {
+    fn parse(cursor: &mut Cursor) -> Result<Self, ParseError> {
+        cursor.open_block(Keyword::If, Keyword::EndIf);
+        cursor.expect(Token::Keyword(Keyword::If))?;
+        let case_if = If::parse(cursor)?;
+        let mut cases_elif = vec![];
+        while cursor.peek()? == Token::Keyword(Keyword::ElseIf) {
+            cursor.eat()?;
+            cases_elif.push(If::parse(cursor)?);
+        }
+        let case_else = if cursor.peek()? == Token::Keyword(Keyword::Else) {
+            cursor.eat()?;
+            skip_colon(cursor)?;
+            Some(Else {
+                block: parse_block(cursor)?,
+            })
+        } else {
+            None
+        };
+        cursor.expect(Token::Keyword(Keyword::EndIf))?;
+        cursor.close_block();
+        Ok(Self {
+            case_if,
+            cases_elif,
+            case_else,
+        })
+    }
 }
 
 /// In this form, `If` also handles "elif"  
+#[derive(Debug, PartialEq)]
 pub struct If {
-    predicate: Expression,
-    block: Block,
+    pub predicate: Expression,
+    pub block: Block,
 }
 
+impl Parse for If {
+    /// Parses the predicate and block (the `if` or `elseif` keyword should already have been
+    /// consumed).
+    fn parse(cursor: &mut Cursor) -> Result<Self, ParseError> {
+        let predicate = Expression::parse(cursor)?;
+        // the block is opened by `then`, or by a colon when lexed with `LexOptions::colon_blocks`
+        match cursor.eat()? {
+            Token::Keyword(Keyword::Then) | Token::Punctuation(Punctuation::Colon) => {}
+            token => return Err(ParseError::UnexpectedToken(token)),
+        }
+        Ok(Self {
+            predicate,
+            block: parse_block(cursor)?,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
 pub struct Else {
-    block: Block,
+    pub block: Block,
+}
+
+/// A function definition.
+#[derive(Debug, PartialEq)]
+pub struct FunctionDefinition {
+    pub name: String,
+    pub parameters: Vec<Parameter>,
+    /// The name of the type the function is declared to return (`function area(r) returns REAL`).
+    pub returns: Option<String>,
+    pub block: Block,
+    /// The comments directly above the function, which describe what it does.
+    pub doc: Option<String>,
+}
+
+/// A parameter in a function definition.
+#[derive(Debug, PartialEq)]
+pub struct Parameter {
+    pub name: String,
+    /// Whether the parameter is passed by reference (`name:byRef`), so that assigning to it inside
+    /// the function changes the caller's variable. Parameters are passed by value (which can be
+    /// written `name:byVal`) by default.
+    pub by_ref: bool,
+    /// The value the parameter takes if no argument is supplied for it.
+    pub default: Option<Expression>,
+}
+
+impl Parse for FunctionDefinition {
+    fn parse(cursor: &mut Cursor) -> Result<Self, ParseError> {
+        cursor.open_block(Keyword::Function, Keyword::EndFunction);
+        cursor.expect(Token::Keyword(Keyword::Function))?;
+        let name = parse_ident(cursor)?;
+        cursor.expect(Token::Punctuation(Punctuation::OpenRoundBracket))?;
+        let mut parameters = vec![];
+        if cursor.peek()? == Token::Punctuation(Punctuation::CloseRoundBracket) {
+            cursor.eat()?;
+        } else {
+            loop {
+                let name = parse_ident(cursor)?;
+                let by_ref = match cursor.peek()? {
+                    Token::Punctuation(Punctuation::ByRef) => {
+                        cursor.eat()?;
+                        true
+                    }
+                    Token::Punctuation(Punctuation::ByVal) => {
+                        cursor.eat()?;
+                        false
+                    }
+                    _ => false,
+                };
+                let default = if cursor.peek()? == Token::Operator(Operator::Equals) {
+                    cursor.eat()?;
+                    Some(Expression::parse(cursor)?)
+                } else {
+                    None
+                };
+                parameters.push(Parameter {
+                    name,
+                    by_ref,
+                    default,
+                });
+                match cursor.eat()? {
+                    Token::Punctuation(Punctuation::Comma) => continue,
+                    Token::Punctuation(Punctuation::CloseRoundBracket) => break,
+                    token => return Err(ParseError::UnexpectedToken(token)),
+                }
+            }
+        }
+        let returns = if cursor.peek()? == Token::Keyword(Keyword::Returns) {
+            cursor.eat()?;
+            Some(parse_ident(cursor)?)
+        } else {
+            None
+        };
+        let block = parse_block(cursor)?;
+        cursor.expect(Token::Keyword(Keyword::EndFunction))?;
+        cursor.close_block();
+        Ok(Self {
+            name,
+            parameters,
+            returns,
+            block,
+            doc: None,
+        })
+    }
+}
+
+/// A `try ... catch e ... endtry` statement.
+#[derive(Debug, PartialEq)]
+pub struct TryStatement {
+    pub body: Block,
+    /// The variable the error message is bound to inside `catch_block`.
+    pub catch_var: String,
+    pub catch_block: Block,
+}
+
+impl Parse for TryStatement {
+    fn parse(cursor: &mut Cursor) -> Result<Self, ParseError> {
+        cursor.open_block(Keyword::Try, Keyword::EndTry);
+        cursor.expect(Token::Keyword(Keyword::Try))?;
+        let body = parse_block(cursor)?;
+        cursor.expect(Token::Keyword(Keyword::Catch))?;
+        let catch_var = parse_ident(cursor)?;
+        let catch_block = parse_block(cursor)?;
+        cursor.expect(Token::Keyword(Keyword::EndTry))?;
+        cursor.close_block();
+        Ok(Self {
+            body,
+            catch_var,
+            catch_block,
+        })
+    }
+}
+
+/// Declares a variable without giving it a value (`DECLARE count : INTEGER`).
+#[derive(Debug, PartialEq)]
+pub struct Declaration {
+    pub name: String,
+    /// The name of the variable's type.
+    pub ty: String,
+}
+
+impl Parse for Declaration {
+    fn parse(cursor: &mut Cursor) -> Result<Self, ParseError> {
+        cursor.expect(Token::Keyword(Keyword::Declare))?;
+        let name = parse_ident(cursor)?;
+        cursor.expect(Token::Punctuation(Punctuation::Colon))?;
+        Ok(Self {
+            name,
+            ty: parse_ident(cursor)?,
+        })
+    }
+}
+
+/// Gives another name to a type (`TYPE Score = INTEGER`), which can be used anywhere in the program
+/// (including before the alias).
+#[derive(Debug, PartialEq)]
+pub struct TypeAlias {
+    pub name: String,
+    /// The name of the type which is being aliased.
+    pub ty: String,
+}
+
+impl Parse for TypeAlias {
+    fn parse(cursor: &mut Cursor) -> Result<Self, ParseError> {
+        cursor.expect(Token::Keyword(Keyword::Type))?;
+        let name = parse_ident(cursor)?;
+        cursor.expect(Token::Operator(Operator::Equals))?;
+        Ok(Self {
+            name,
+            ty: parse_ident(cursor)?,
+        })
+    }
+}
+
+/// Gives a name to a value which can't be changed (`const SIZE = 5`).
+#[derive(Debug, PartialEq)]
+pub struct ConstDeclaration {
+    pub name: String,
+    pub value: Expression,
+}
+
+impl Parse for ConstDeclaration {
+    fn parse(cursor: &mut Cursor) -> Result<Self, ParseError> {
+        cursor.expect(Token::Keyword(Keyword::Const))?;
+        let name = parse_ident(cursor)?;
+        cursor.expect(Token::Operator(Operator::Equals))?;
+        Ok(Self {
+            name,
+            value: Expression::parse(cursor)?,
+        })
+    }
+}
+
+/// Declares an array with a fixed number of elements (`array names[5]`).
+#[derive(Debug, PartialEq)]
+pub struct ArrayDeclaration {
+    pub name: String,
+    pub size: Expression,
+}
+
+impl Parse for ArrayDeclaration {
+    fn parse(cursor: &mut Cursor) -> Result<Self, ParseError> {
+        cursor.expect(Token::Keyword(Keyword::Array))?;
+        let name = parse_ident(cursor)?;
+        cursor.expect(Token::Punctuation(Punctuation::OpenSquareBracket))?;
+        let size = Expression::parse(cursor)?;
+        cursor.expect(Token::Punctuation(Punctuation::CloseSquareBracket))?;
+        Ok(Self { name, size })
+    }
 }
 
 /// A for statement.
+#[derive(Debug, PartialEq)]
 pub struct ForStatement {
-    ident: String,
-    start: u32,
-    stop: u32,
-    block: Block,
+    pub ident: String,
+    pub start: Expression,
+    pub stop: Expression,
+    /// The amount to increase the loop variable by on each iteration (if this is `None`, it's
+    /// increased by one).
+    pub step: Option<Expression>,
+    pub block: Block,
+}
+
+impl Parse for ForStatement {
+    fn parse(cursor: &mut Cursor) -> Result<Self, ParseError> {
+        cursor.open_block(Keyword::For, Keyword::Next);
+        cursor.expect(Token::Keyword(Keyword::For))?;
+        let ident = parse_ident(cursor)?;
+        cursor.expect(Token::Operator(Operator::Equals))?;
+        let start = Expression::parse(cursor)?;
+        cursor.expect(Token::Keyword(Keyword::To))?;
+        let stop = Expression::parse(cursor)?;
+        let step = if cursor.peek()? == Token::Keyword(Keyword::Step) {
+            cursor.eat()?;
+            Some(Expression::parse(cursor)?)
+        } else {
+            None
+        };
+        let block = parse_block(cursor)?;
+        parse_next(cursor, &ident)?;
+        cursor.close_block();
+        Ok(Self {
+            ident,
+            start,
+            stop,
+            step,
+            block,
+        })
+    }
+}
+
+/// Parses the `next <ident>` or `endfor` which closes a loop over `ident`.
+fn parse_next(cursor: &mut Cursor, ident: &str) -> Result<(), ParseError> {
+    if cursor.peek()? == Token::Keyword(Keyword::EndFor) {
+        cursor.eat()?;
+        return Ok(());
+    }
+    cursor.expect(Token::Keyword(Keyword::Next))?;
+    let next = parse_ident(cursor)?;
+    if next != ident {
+        return Err(ParseError::MismatchedLoopVariable {
+            expected: ident.to_string(),
+            found: next,
+        });
+    }
+    Ok(())
 }
 
+/// A `for each item in iterable` statement.
+#[derive(Debug, PartialEq)]
+pub struct ForEachStatement {
+    pub item: String,
+    pub iterable: Expression,
+    pub block: Block,
+}
+
+impl Parse for ForEachStatement {
+    fn parse(cursor: &mut Cursor) -> Result<Self, ParseError> {
+        cursor.open_block(Keyword::For, Keyword::Next);
+        cursor.expect(Token::Keyword(Keyword::For))?;
+        cursor.expect(Token::Keyword(Keyword::Each))?;
+        let item = parse_ident(cursor)?;
+        cursor.expect(Token::Keyword(Keyword::In))?;
+        let iterable = Expression::parse(cursor)?;
+        let block = parse_block(cursor)?;
+        parse_next(cursor, &item)?;
+        cursor.close_block();
+        Ok(Self {
+            item,
+            iterable,
+            block,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
 pub struct WhileStatement {
-    predicate: Expression,
-    block: Block,
+    pub predicate: Expression,
+    pub block: Block,
+}
+
+impl Parse for WhileStatement {
+    fn parse(cursor: &mut Cursor) -> Result<Self, ParseError> {
+        cursor.open_block(Keyword::While, Keyword::EndWhile);
+        cursor.expect(Token::Keyword(Keyword::While))?;
+        let predicate = Expression::parse(cursor)?;
+        skip_colon(cursor)?;
+        // and in some dialects the block starts with `do` (see `LexOptions::while_do`)
+        if cursor.peek()? == Token::Keyword(Keyword::Do) {
+            cursor.eat()?;
+        }
+        let block = parse_block(cursor)?;
+        cursor.expect(Token::Keyword(Keyword::EndWhile))?;
+        cursor.close_block();
+        Ok(Self { predicate, block })
+    }
 }
 
 /// An AST of sort `Expression`
-pub struct Expression {
-    /// The operator in question.
-    operator: Operator,
-    /// The operands on which the operator acts.
-    operands: Vec<Box<Expression>>,
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    String(String),
+    /// A string with the values of some variables substituted into it (`"x = {x}"`).
+    InterpolatedString(Vec<StringPart>),
+    Ident(String),
+    /// An array written out element by element (`[1, 2, 3]`).
+    Array(Vec<Expression>),
+    /// The application of a function to some arguments.
+    FunctionCall {
+        name: String,
+        args: Vec<Expression>,
+        /// Arguments given by name (e.g. the `separator` in `print(a, b, separator=", ")`).
+        named_args: NamedArgs,
+        /// Where the arguments are in the source code (this is boxed to keep expressions small).
+        spans: Box<ArgSpans>,
+    },
+    /// A field of a value (`point.x`).
+    Member {
+        object: Box<Expression>,
+        member: String,
+    },
+    /// The application of one of a value's methods to some arguments (`names.append("Ada")`).
+    MethodCall {
+        object: Box<Expression>,
+        name: String,
+        args: Vec<Expression>,
+        named_args: NamedArgs,
+    },
+    /// An element of an array (`array[index]`).
+    Index {
+        array: Box<Expression>,
+        index: Box<Expression>,
+    },
+    /// The part of an array from `start` up to (but not including) `stop` (`array[start:stop]`).
+    Slice {
+        array: Box<Expression>,
+        start: Box<Expression>,
+        stop: Box<Expression>,
+    },
+    /// An operator applied to a single operand (e.g. `NOT x`).
+    UnaryOp {
+        operator: Operator,
+        operand: Box<Expression>,
+    },
+    /// An operator applied to two operands (e.g. `x + y`).
+    BinaryOp {
+        operator: Operator,
+        lhs: Box<Expression>,
+        rhs: Box<Expression>,
+    },
 }
 
 impl Parse for Expression {
     fn parse(cursor: &mut Cursor) -> Result<Self, ParseError> {
-        todo!()
+        parse_expression_with_binding_power(cursor, 0)
+    }
+}
+
+/// The binding power of an operator used as a prefix (the higher it is, the more tightly the
+/// operator binds to its operand).
+fn prefix_binding_power(operator: &Operator) -> Option<u8> {
+    match operator {
+        Operator::Not => Some(5),
+        Operator::Minus => Some(13),
+        _ => None,
+    }
+}
+
+/// The left and right binding powers of an operator used as an infix.
+///
+/// Note that inside an expression `=` is a comparison (the lexer only treats it as an assignment at
+/// the start of a statement).
+fn infix_binding_power(operator: &Operator) -> Option<(u8, u8)> {
+    match operator {
+        Operator::Or => Some((1, 2)),
+        Operator::And => Some((3, 4)),
+        operator if operator.is_comparison() => Some((7, 8)),
+        Operator::Plus | Operator::Minus => Some((9, 10)),
+        Operator::Times | Operator::Divide | Operator::IntegerDivide | Operator::Modulo => {
+            Some((11, 12))
+        }
+        // this is right associative (`2 ^ 3 ^ 2` is `2 ^ (3 ^ 2)`), and binds more tightly than a
+        // unary minus (`-2 ^ 2` is `-(2 ^ 2)`)
+        Operator::Power => Some((15, 14)),
+        _ => None,
+    }
+}
+
+/// Parses an expression using Pratt's "top down operator precedence" algorithm. Only operators
+/// which bind more tightly than `min_binding_power` are consumed.
+fn parse_expression_with_binding_power(
+    cursor: &mut Cursor,
+    min_binding_power: u8,
+) -> Result<Expression, ParseError> {
+    let mut lhs = match cursor.eat()? {
+        Token::Integer(integer) => Expression::Integer(integer),
+        Token::Float(float) => Expression::Float(float),
+        Token::Boolean(boolean) => Expression::Boolean(boolean),
+        // a string ends with the same kind of quote it started with
+        Token::Punctuation(quote @ (Punctuation::Quote | Punctuation::SingleQuote)) => {
+            match cursor.eat()? {
+                Token::String(string) => {
+                    cursor.expect(Token::Punctuation(quote))?;
+                    Expression::String(string)
+                }
+                Token::InterpolatedString(parts) => {
+                    cursor.expect(Token::Punctuation(quote))?;
+                    Expression::InterpolatedString(parts)
+                }
+                token => return Err(ParseError::UnexpectedToken(token)),
+            }
+        }
+        Token::Ident(name) => {
+            if matches!(
+                cursor.peek(),
+                Ok(Token::Punctuation(Punctuation::OpenRoundBracket))
+            ) {
+                let (args, named_args, spans) = parse_arguments(cursor, Some(&name))?;
+                Expression::FunctionCall {
+                    name,
+                    args,
+                    named_args,
+                    spans: Box::new(spans),
+                }
+            } else {
+                Expression::Ident(name)
+            }
+        }
+        Token::Punctuation(Punctuation::OpenRoundBracket) => {
+            let expression = parse_expression_with_binding_power(cursor, 0)?;
+            cursor.expect(Token::Punctuation(Punctuation::CloseRoundBracket))?;
+            expression
+        }
+        Token::Punctuation(Punctuation::OpenSquareBracket) => parse_array(cursor)?,
+        Token::Operator(operator) => match prefix_binding_power(&operator) {
+            Some(binding_power) => Expression::UnaryOp {
+                operator,
+                operand: Box::new(parse_expression_with_binding_power(cursor, binding_power)?),
+            },
+            None => return Err(ParseError::UnexpectedToken(Token::Operator(operator))),
+        },
+        token => return Err(ParseError::UnexpectedToken(token)),
+    };
+    // indexes, fields and method calls can be chained (`a[0].b().c[1]`), each one applying to
+    // everything before it
+    loop {
+        lhs = match cursor.peek() {
+            Ok(Token::Punctuation(Punctuation::OpenSquareBracket)) => parse_index(cursor, lhs)?,
+            Ok(Token::Punctuation(Punctuation::Dot)) => parse_member(cursor, lhs)?,
+            _ => break,
+        };
+    }
+    while let Ok(Token::Operator(operator)) = cursor.peek() {
+        let (left_binding_power, right_binding_power) = match infix_binding_power(&operator) {
+            Some(binding_power) => binding_power,
+            None => break,
+        };
+        if left_binding_power < min_binding_power {
+            break;
+        }
+        cursor.eat()?;
+        let rhs = parse_expression_with_binding_power(cursor, right_binding_power)?;
+        lhs = Expression::BinaryOp {
+            operator,
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+        };
+    }
+    Ok(lhs)
+}
+
+/// Skips the colon which opens a block when lexed with `LexOptions::colon_blocks`, if there is one.
+fn skip_colon(cursor: &mut Cursor) -> Result<(), ParseError> {
+    if cursor.peek()? == Token::Punctuation(Punctuation::Colon) {
+        cursor.eat()?;
+    }
+    Ok(())
+}
+
+/// Parses an index (`[index]`) or a slice (`[start:stop]`) of `array`.
+fn parse_index(cursor: &mut Cursor, array: Expression) -> Result<Expression, ParseError> {
+    cursor.expect(Token::Punctuation(Punctuation::OpenSquareBracket))?;
+    let index = Expression::parse(cursor)?;
+    let expression = match cursor.eat()? {
+        Token::Punctuation(Punctuation::CloseSquareBracket) => {
+            return Ok(Expression::Index {
+                array: Box::new(array),
+                index: Box::new(index),
+            })
+        }
+        Token::Punctuation(Punctuation::Colon) => Expression::Slice {
+            array: Box::new(array),
+            start: Box::new(index),
+            stop: Box::new(Expression::parse(cursor)?),
+        },
+        token => return Err(ParseError::UnexpectedToken(token)),
+    };
+    cursor.expect(Token::Punctuation(Punctuation::CloseSquareBracket))?;
+    Ok(expression)
+}
+
+/// Parses a field (`.member`) or a method call (`.method(args)`) of `object`.
+fn parse_member(cursor: &mut Cursor, object: Expression) -> Result<Expression, ParseError> {
+    cursor.expect(Token::Punctuation(Punctuation::Dot))?;
+    let name = parse_ident(cursor)?;
+    if matches!(
+        cursor.peek(),
+        Ok(Token::Punctuation(Punctuation::OpenRoundBracket))
+    ) {
+        let (args, named_args, _) = parse_arguments(cursor, None)?;
+        Ok(Expression::MethodCall {
+            object: Box::new(object),
+            name,
+            args,
+            named_args,
+        })
+    } else {
+        Ok(Expression::Member {
+            object: Box::new(object),
+            member: name,
+        })
+    }
+}
+
+/// Arguments given by name, in the order they were given.
+pub type NamedArgs = Vec<(String, Expression)>;
+
+/// Where the arguments to a function call are in the source code. These are only known if the
+/// call was parsed from a `TokenStream` (see `parse_spanned`); otherwise this is empty.
+///
+/// Spans don't change what a program means, so they're ignored when comparing calls.
+#[derive(Debug, Clone, Default)]
+pub struct ArgSpans {
+    /// The span of each positional argument.
+    pub args: Vec<Span>,
+    /// The span of each named argument (including its name).
+    pub named: Vec<Span>,
+    /// The span of the closing bracket, which is where a missing argument would have gone.
+    pub close: Option<Span>,
+}
+
+impl PartialEq for ArgSpans {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+/// Parses the elements of an array literal (after its opening square bracket).
+fn parse_array(cursor: &mut Cursor) -> Result<Expression, ParseError> {
+    let mut elements = vec![];
+    if cursor.peek()? == Token::Punctuation(Punctuation::CloseSquareBracket) {
+        cursor.eat()?;
+        return Ok(Expression::Array(elements));
+    }
+    loop {
+        elements.push(Expression::parse(cursor)?);
+        match cursor.eat()? {
+            Token::Punctuation(Punctuation::Comma) => continue,
+            Token::Punctuation(Punctuation::CloseSquareBracket) => {
+                return Ok(Expression::Array(elements))
+            }
+            token => return Err(ParseError::UnexpectedToken(token)),
+        }
+    }
+}
+
+/// Parses the bracketed, comma separated arguments to a call to `function` (or to a method, if this
+/// is `None`).
+///
+/// An argument is only given by name if it's for a parameter which the function has; otherwise
+/// `name = value` is a comparison, as it is anywhere else in an expression.
+fn parse_arguments(
+    cursor: &mut Cursor,
+    function: Option<&str>,
+) -> Result<(Vec<Expression>, NamedArgs, ArgSpans), ParseError> {
+    cursor.expect(Token::Punctuation(Punctuation::OpenRoundBracket))?;
+    let mut args = vec![];
+    let mut named_args = vec![];
+    let mut spans = ArgSpans::default();
+    if cursor.peek()? == Token::Punctuation(Punctuation::CloseRoundBracket) {
+        cursor.eat()?;
+        spans.close = cursor.span_from(cursor.position - 1);
+        return Ok((args, named_args, spans));
+    }
+    loop {
+        let start = cursor.position;
+        match (cursor.peek()?, cursor.peek_nth(1)) {
+            (Token::Ident(name), Ok(Token::Operator(Operator::Equals)))
+                if function.is_some_and(|function| cursor.takes_parameter(function, &name)) =>
+            {
+                cursor.eat()?;
+                cursor.eat()?;
+                named_args.push((name, Expression::parse(cursor)?));
+                spans.named.extend(cursor.span_from(start));
+            }
+            // positional arguments have to come before named ones
+            (token, _) if !named_args.is_empty() => return Err(ParseError::UnexpectedToken(token)),
+            _ => {
+                args.push(Expression::parse(cursor)?);
+                spans.args.extend(cursor.span_from(start));
+            }
+        }
+        match cursor.eat()? {
+            Token::Punctuation(Punctuation::Comma) => continue,
+            Token::Punctuation(Punctuation::CloseRoundBracket) => {
+                spans.close = cursor.span_from(cursor.position - 1);
+                return Ok((args, named_args, spans));
+            }
+            token => return Err(ParseError::UnexpectedToken(token)),
+        }
     }
 }
@@ -0,0 +1,220 @@
+//! Unit tests for the parser.
+//! These tests feed hand-written token streams to the parser, so they don't depend on the lexer.
+
+use crate::lexer::{Keyword, Operator, Punctuation, Token, TokenKind};
+use crate::parser::{
+    parse_block, parse_expression, Cursor, Expression, Parse, ParseError, Statement,
+};
+
+fn binary(operator: Operator, lhs: Expression, rhs: Expression) -> Expression {
+    Expression::BinaryOp {
+        operator,
+        lhs: Box::new(lhs),
+        rhs: Box::new(rhs),
+    }
+}
+
+#[test]
+pub fn test_parses_precedence() {
+    let tokens = vec![
+        Token::Integer(1),
+        Token::Operator(Operator::Plus),
+        Token::Integer(2),
+        Token::Operator(Operator::Times),
+        Token::Integer(3),
+    ];
+    assert_eq!(
+        parse_expression(&tokens).unwrap(),
+        binary(
+            Operator::Plus,
+            Expression::Integer(1),
+            binary(
+                Operator::Times,
+                Expression::Integer(2),
+                Expression::Integer(3)
+            )
+        )
+    );
+}
+
+#[test]
+pub fn test_parses_left_associatively() {
+    let tokens = vec![
+        Token::Integer(1),
+        Token::Operator(Operator::Minus),
+        Token::Integer(2),
+        Token::Operator(Operator::Minus),
+        Token::Integer(3),
+    ];
+    assert_eq!(
+        parse_expression(&tokens).unwrap(),
+        binary(
+            Operator::Minus,
+            binary(
+                Operator::Minus,
+                Expression::Integer(1),
+                Expression::Integer(2)
+            ),
+            Expression::Integer(3)
+        )
+    );
+}
+
+#[test]
+pub fn test_parses_brackets_and_calls() {
+    let tokens = vec![
+        Token::Punctuation(Punctuation::OpenRoundBracket),
+        Token::Integer(1),
+        Token::Operator(Operator::Plus),
+        Token::Ident("f".to_string()),
+        Token::Punctuation(Punctuation::OpenRoundBracket),
+        Token::Ident("x".to_string()),
+        Token::Punctuation(Punctuation::Comma),
+        Token::Punctuation(Punctuation::Quote),
+        Token::String("y".to_string()),
+        Token::Punctuation(Punctuation::Quote),
+        Token::Punctuation(Punctuation::CloseRoundBracket),
+        Token::Punctuation(Punctuation::CloseRoundBracket),
+        Token::Operator(Operator::Times),
+        Token::Integer(3),
+    ];
+    assert_eq!(
+        parse_expression(&tokens).unwrap(),
+        binary(
+            Operator::Times,
+            binary(
+                Operator::Plus,
+                Expression::Integer(1),
+                Expression::FunctionCall {
+                    name: "f".to_string(),
+                    args: vec![
+                        Expression::Ident("x".to_string()),
+                        Expression::String("y".to_string())
+                    ],
+                    named_args: vec![],
+                    spans: Box::default()
+                }
+            ),
+            Expression::Integer(3)
+        )
+    );
+}
+
+#[test]
+pub fn test_rejects_trailing_tokens() {
+    let tokens = vec![
+        Token::Integer(1),
+        Token::Operator(Operator::Plus),
+        Token::Integer(2),
+        Token::Integer(3),
+    ];
+    assert!(matches!(
+        parse_expression(&tokens),
+        Err(ParseError::UnexpectedToken(Token::Integer(3)))
+    ));
+}
+
+#[test]
+pub fn test_rejects_incomplete_expression() {
+    let tokens = vec![Token::Integer(1), Token::Operator(Operator::Plus)];
+    assert!(matches!(
+        parse_expression(&tokens),
+        Err(ParseError::UnexpectedEndOfInput)
+    ));
+}
+
+#[test]
+pub fn test_rejects_mismatched_next() {
+    let tokens = vec![
+        Token::Keyword(Keyword::For),
+        Token::Ident("i".to_string()),
+        Token::Operator(Operator::Equals),
+        Token::Integer(0),
+        Token::Keyword(Keyword::To),
+        Token::Integer(9),
+        Token::Keyword(Keyword::Next),
+        Token::Ident("j".to_string()),
+    ];
+    assert!(matches!(
+        Statement::parse(&mut Cursor::new(tokens)),
+        Err(ParseError::MismatchedLoopVariable { .. })
+    ));
+}
+
+#[test]
+pub fn test_parses_block_up_to_terminator() {
+    let tokens = vec![
+        Token::Ident("x".to_string()),
+        Token::Operator(Operator::Equals),
+        Token::Integer(1),
+        Token::Ident("y".to_string()),
+        Token::Operator(Operator::Increment),
+        Token::Ident("x".to_string()),
+        Token::Ident("print".to_string()),
+        Token::Punctuation(Punctuation::OpenRoundBracket),
+        Token::Ident("y".to_string()),
+        Token::Punctuation(Punctuation::CloseRoundBracket),
+        Token::Keyword(Keyword::EndWhile),
+    ];
+    let mut cursor = Cursor::new(tokens);
+    let block = parse_block(&mut cursor).unwrap();
+    assert_eq!(block.len(), 3);
+    assert!(matches!(block[0], Statement::AssignmentStatement(_)));
+    assert!(matches!(block[1], Statement::AssignmentStatement(_)));
+    assert!(matches!(block[2], Statement::ExpressionStatement(_)));
+    // the terminating keyword is left for the caller
+    assert_eq!(cursor.peek().unwrap(), Token::Keyword(Keyword::EndWhile));
+}
+
+#[test]
+pub fn test_parses_index_and_slice() {
+    let ident = |name: &str| Token::Ident(name.to_string());
+    let tokens = vec![
+        ident("arr"),
+        Token::Punctuation(Punctuation::OpenSquareBracket),
+        Token::Integer(1),
+        Token::Punctuation(Punctuation::Colon),
+        Token::Integer(3),
+        Token::Punctuation(Punctuation::CloseSquareBracket),
+    ];
+    assert_eq!(
+        parse_expression(&tokens).unwrap(),
+        Expression::Slice {
+            array: Box::new(Expression::Ident("arr".to_string())),
+            start: Box::new(Expression::Integer(1)),
+            stop: Box::new(Expression::Integer(3)),
+        }
+    );
+    let tokens = vec![
+        ident("arr"),
+        Token::Punctuation(Punctuation::OpenSquareBracket),
+        ident("i"),
+        Token::Punctuation(Punctuation::CloseSquareBracket),
+        Token::Operator(Operator::Plus),
+        Token::Integer(1),
+    ];
+    assert_eq!(
+        parse_expression(&tokens).unwrap(),
+        binary(
+            Operator::Plus,
+            Expression::Index {
+                array: Box::new(Expression::Ident("arr".to_string())),
+                index: Box::new(Expression::Ident("i".to_string())),
+            },
+            Expression::Integer(1)
+        )
+    );
+}
+
+#[test]
+pub fn test_peeks_kinds() {
+    let mut cursor = Cursor::new(vec![
+        Token::Ident("x".to_string()),
+        Token::Operator(Operator::Equals),
+    ]);
+    assert_eq!(cursor.peek_kind(), TokenKind::Ident);
+    cursor.eat().unwrap();
+    assert_eq!(cursor.peek_kind(), TokenKind::Operator);
+    cursor.eat().unwrap();
+    assert_eq!(cursor.peek_kind(), TokenKind::Eof);
+}
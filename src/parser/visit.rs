@@ -0,0 +1,313 @@
+//! Walks over an AST.
+//!
+//! Implement `Visitor`, overriding the methods for the nodes you're interested in. The default
+//! implementations just visit every child of the node, so an overridden method should call the
+//! matching `walk_` function if it wants the children to be visited as well.
+//!
+//! `VisitorMut` does the same for passes which change the AST (with `walk_*_mut` functions).
+
+use super::{Expression, Statement, SwitchCasePattern};
+
+pub trait Visitor {
+    fn visit_statement(&mut self, statement: &Statement) {
+        walk_statement(self, statement)
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        walk_expression(self, expression)
+    }
+}
+
+/// Visits every statement in a block (or a whole program).
+pub fn walk_block<V: Visitor + ?Sized>(visitor: &mut V, block: &[Statement]) {
+    for statement in block {
+        visitor.visit_statement(statement);
+    }
+}
+
+/// Visits the expressions and blocks which make up a statement.
+pub fn walk_statement<V: Visitor + ?Sized>(visitor: &mut V, statement: &Statement) {
+    match statement {
+        Statement::ForStatement(for_statement) => {
+            visitor.visit_expression(&for_statement.start);
+            visitor.visit_expression(&for_statement.stop);
+            if let Some(step) = &for_statement.step {
+                visitor.visit_expression(step);
+            }
+            walk_block(visitor, &for_statement.block);
+        }
+        Statement::ForEachStatement(for_each) => {
+            visitor.visit_expression(&for_each.iterable);
+            walk_block(visitor, &for_each.block);
+        }
+        Statement::WhileStatement(while_statement) => {
+            visitor.visit_expression(&while_statement.predicate);
+            walk_block(visitor, &while_statement.block);
+        }
+        Statement::IfStatement(if_statement) => {
+            for case in std::iter::once(&if_statement.case_if).chain(&if_statement.cases_elif) {
+                visitor.visit_expression(&case.predicate);
+                walk_block(visitor, &case.block);
+            }
+            if let Some(case_else) = &if_statement.case_else {
+                walk_block(visitor, &case_else.block);
+            }
+        }
+        Statement::AssignmentStatement(assignment) => {
+            visitor.visit_expression(&assignment.expression)
+        }
+        Statement::ElementAssignment(assignment) => {
+            for index in &assignment.indexes {
+                visitor.visit_expression(index);
+            }
+            visitor.visit_expression(&assignment.expression)
+        }
+        Statement::DoUntilStatement(do_until) => {
+            walk_block(visitor, &do_until.block);
+            visitor.visit_expression(&do_until.predicate);
+        }
+        Statement::SwitchStatement(switch) => {
+            visitor.visit_expression(&switch.subject);
+            for case in &switch.cases {
+                match &case.pattern {
+                    SwitchCasePattern::Value(value) | SwitchCasePattern::Comparison(_, value) => {
+                        visitor.visit_expression(value)
+                    }
+                }
+                walk_block(visitor, &case.block);
+            }
+            if let Some(default) = &switch.default {
+                walk_block(visitor, &default.block);
+            }
+        }
+        Statement::FunctionDefinition(function) => {
+            for parameter in &function.parameters {
+                if let Some(default) = &parameter.default {
+                    visitor.visit_expression(default);
+                }
+            }
+            walk_block(visitor, &function.block);
+        }
+        Statement::TryStatement(try_statement) => {
+            walk_block(visitor, &try_statement.body);
+            walk_block(visitor, &try_statement.catch_block);
+        }
+        Statement::Declaration(_) | Statement::TypeAlias(_) | Statement::PassStatement => {}
+        Statement::ConstDeclaration(declaration) => visitor.visit_expression(&declaration.value),
+        Statement::ArrayDeclaration(declaration) => visitor.visit_expression(&declaration.size),
+        Statement::ReturnStatement(expression)
+        | Statement::ThrowStatement(expression)
+        | Statement::ExpressionStatement(expression) => visitor.visit_expression(expression),
+    }
+}
+
+/// Visits the subexpressions of an expression.
+pub fn walk_expression<V: Visitor + ?Sized>(visitor: &mut V, expression: &Expression) {
+    match expression {
+        Expression::Integer(_)
+        | Expression::Float(_)
+        | Expression::Boolean(_)
+        | Expression::String(_)
+        | Expression::InterpolatedString(_)
+        | Expression::Ident(_) => {}
+        Expression::Array(elements) => {
+            for element in elements {
+                visitor.visit_expression(element);
+            }
+        }
+        Expression::FunctionCall {
+            args, named_args, ..
+        } => {
+            for arg in args {
+                visitor.visit_expression(arg);
+            }
+            for (_, arg) in named_args {
+                visitor.visit_expression(arg);
+            }
+        }
+        Expression::Member { object, .. } => visitor.visit_expression(object),
+        Expression::MethodCall {
+            object,
+            args,
+            named_args,
+            ..
+        } => {
+            visitor.visit_expression(object);
+            for arg in args {
+                visitor.visit_expression(arg);
+            }
+            for (_, arg) in named_args {
+                visitor.visit_expression(arg);
+            }
+        }
+        Expression::Index { array, index } => {
+            visitor.visit_expression(array);
+            visitor.visit_expression(index);
+        }
+        Expression::Slice { array, start, stop } => {
+            visitor.visit_expression(array);
+            visitor.visit_expression(start);
+            visitor.visit_expression(stop);
+        }
+        Expression::UnaryOp { operand, .. } => visitor.visit_expression(operand),
+        Expression::BinaryOp { lhs, rhs, .. } => {
+            visitor.visit_expression(lhs);
+            visitor.visit_expression(rhs);
+        }
+    }
+}
+
+pub trait VisitorMut {
+    fn visit_statement_mut(&mut self, statement: &mut Statement) {
+        walk_statement_mut(self, statement)
+    }
+
+    fn visit_expression_mut(&mut self, expression: &mut Expression) {
+        walk_expression_mut(self, expression)
+    }
+}
+
+/// Visits every statement in a block (or a whole program), so that they can be changed.
+pub fn walk_block_mut<V: VisitorMut + ?Sized>(visitor: &mut V, block: &mut [Statement]) {
+    for statement in block {
+        visitor.visit_statement_mut(statement);
+    }
+}
+
+/// Visits the expressions and blocks which make up a statement, so that they can be changed.
+pub fn walk_statement_mut<V: VisitorMut + ?Sized>(visitor: &mut V, statement: &mut Statement) {
+    match statement {
+        Statement::ForStatement(for_statement) => {
+            visitor.visit_expression_mut(&mut for_statement.start);
+            visitor.visit_expression_mut(&mut for_statement.stop);
+            if let Some(step) = &mut for_statement.step {
+                visitor.visit_expression_mut(step);
+            }
+            walk_block_mut(visitor, &mut for_statement.block);
+        }
+        Statement::ForEachStatement(for_each) => {
+            visitor.visit_expression_mut(&mut for_each.iterable);
+            walk_block_mut(visitor, &mut for_each.block);
+        }
+        Statement::WhileStatement(while_statement) => {
+            visitor.visit_expression_mut(&mut while_statement.predicate);
+            walk_block_mut(visitor, &mut while_statement.block);
+        }
+        Statement::IfStatement(if_statement) => {
+            for case in
+                std::iter::once(&mut if_statement.case_if).chain(&mut if_statement.cases_elif)
+            {
+                visitor.visit_expression_mut(&mut case.predicate);
+                walk_block_mut(visitor, &mut case.block);
+            }
+            if let Some(case_else) = &mut if_statement.case_else {
+                walk_block_mut(visitor, &mut case_else.block);
+            }
+        }
+        Statement::AssignmentStatement(assignment) => {
+            visitor.visit_expression_mut(&mut assignment.expression)
+        }
+        Statement::ElementAssignment(assignment) => {
+            for index in &mut assignment.indexes {
+                visitor.visit_expression_mut(index);
+            }
+            visitor.visit_expression_mut(&mut assignment.expression)
+        }
+        Statement::DoUntilStatement(do_until) => {
+            walk_block_mut(visitor, &mut do_until.block);
+            visitor.visit_expression_mut(&mut do_until.predicate);
+        }
+        Statement::SwitchStatement(switch) => {
+            visitor.visit_expression_mut(&mut switch.subject);
+            for case in &mut switch.cases {
+                match &mut case.pattern {
+                    SwitchCasePattern::Value(value) | SwitchCasePattern::Comparison(_, value) => {
+                        visitor.visit_expression_mut(value)
+                    }
+                }
+                walk_block_mut(visitor, &mut case.block);
+            }
+            if let Some(default) = &mut switch.default {
+                walk_block_mut(visitor, &mut default.block);
+            }
+        }
+        Statement::FunctionDefinition(function) => {
+            for parameter in &mut function.parameters {
+                if let Some(default) = &mut parameter.default {
+                    visitor.visit_expression_mut(default);
+                }
+            }
+            walk_block_mut(visitor, &mut function.block);
+        }
+        Statement::TryStatement(try_statement) => {
+            walk_block_mut(visitor, &mut try_statement.body);
+            walk_block_mut(visitor, &mut try_statement.catch_block);
+        }
+        Statement::Declaration(_) | Statement::TypeAlias(_) | Statement::PassStatement => {}
+        Statement::ConstDeclaration(declaration) => {
+            visitor.visit_expression_mut(&mut declaration.value)
+        }
+        Statement::ArrayDeclaration(declaration) => {
+            visitor.visit_expression_mut(&mut declaration.size)
+        }
+        Statement::ReturnStatement(expression)
+        | Statement::ThrowStatement(expression)
+        | Statement::ExpressionStatement(expression) => visitor.visit_expression_mut(expression),
+    }
+}
+
+/// Visits the subexpressions of an expression, so that they can be changed.
+pub fn walk_expression_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expression: &mut Expression) {
+    match expression {
+        Expression::Integer(_)
+        | Expression::Float(_)
+        | Expression::Boolean(_)
+        | Expression::String(_)
+        | Expression::InterpolatedString(_)
+        | Expression::Ident(_) => {}
+        Expression::Array(elements) => {
+            for element in elements {
+                visitor.visit_expression_mut(element);
+            }
+        }
+        Expression::FunctionCall {
+            args, named_args, ..
+        } => {
+            for arg in args {
+                visitor.visit_expression_mut(arg);
+            }
+            for (_, arg) in named_args {
+                visitor.visit_expression_mut(arg);
+            }
+        }
+        Expression::Member { object, .. } => visitor.visit_expression_mut(object),
+        Expression::MethodCall {
+            object,
+            args,
+            named_args,
+            ..
+        } => {
+            visitor.visit_expression_mut(object);
+            for arg in args {
+                visitor.visit_expression_mut(arg);
+            }
+            for (_, arg) in named_args {
+                visitor.visit_expression_mut(arg);
+            }
+        }
+        Expression::Index { array, index } => {
+            visitor.visit_expression_mut(array);
+            visitor.visit_expression_mut(index);
+        }
+        Expression::Slice { array, start, stop } => {
+            visitor.visit_expression_mut(array);
+            visitor.visit_expression_mut(start);
+            visitor.visit_expression_mut(stop);
+        }
+        Expression::UnaryOp { operand, .. } => visitor.visit_expression_mut(operand),
+        Expression::BinaryOp { lhs, rhs, .. } => {
+            visitor.visit_expression_mut(lhs);
+            visitor.visit_expression_mut(rhs);
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! Rewrites tail-recursive functions as loops.
+//!
+//! A function which returns a call to itself (`return f(...)`) at the end of its body, or at the end
+//! of a branch of an `if` or `switch` there, doesn't need a new stack frame for the call: it can just
+//! update its parameters and start again from the top. This isn't run by the driver, so it's up to
+//! the caller whether to use it.
+
+#[cfg(test)]
+mod unit_tests;
+
+use crate::analysis::always_returns;
+use crate::lexer::{Operator, StringPart};
+use crate::parser::visit::{walk_block, walk_expression, walk_statement, Visitor};
+use crate::parser::{AssignmentStatement, Expression, Statement, WhileStatement};
+use std::collections::{HashMap, HashSet};
+
+/// Rewrites every tail-recursive function in the program as a loop.
+pub fn eliminate_tail_calls(program: &mut [Statement]) {
+    let mut names = Names::default();
+    walk_block(&mut names, program);
+    for statement in program {
+        if let Statement::FunctionDefinition(function) = statement {
+            // every path has to end in a `return`, because once the body is a loop, getting to the
+            // end of it would start the function again rather than leaving it
+            if !always_returns(&function.block) {
+                continue;
+            }
+            let mut tail_calls = TailCalls {
+                function: function.name.clone(),
+                parameters: function
+                    .parameters
+                    .iter()
+                    .map(|parameter| parameter.name.clone())
+                    .collect(),
+                names: &mut names.0,
+                temporaries: HashMap::new(),
+                found: false,
+            };
+            tail_calls.rewrite(&mut function.block);
+            if !tail_calls.found {
+                continue;
+            }
+            let block = std::mem::take(&mut function.block);
+            let forever = Expression::Boolean(true);
+            function.block = vec![Statement::WhileStatement(WhileStatement {
+                predicate: forever,
+                block,
+            })];
+        }
+    }
+}
+
+/// Replaces the tail calls in one function.
+struct TailCalls<'a> {
+    function: String,
+    parameters: Vec<String>,
+    /// Every name used in the program, which the temporaries mustn't clash with.
+    names: &'a mut HashSet<String>,
+    /// The temporary variable which holds the next value of each parameter.
+    temporaries: HashMap<String, String>,
+    /// Whether any tail calls have been replaced.
+    found: bool,
+}
+
+impl TailCalls<'_> {
+    /// Replaces a tail call at the end of a block (or at the end of each branch of an `if` or
+    /// `switch` at the end of it) with updates to the parameters.
+    fn rewrite(&mut self, block: &mut Vec<Statement>) {
+        match block.last_mut() {
+            Some(Statement::ReturnStatement(Expression::FunctionCall {
+                name,
+                args,
+                named_args,
+                ..
+            })) if *name == self.function
+                && named_args.is_empty()
+                && args.len() == self.parameters.len() =>
+            {
+                let args = std::mem::take(args);
+                block.pop();
+                self.update_parameters(block, args);
+                if block.is_empty() {
+                    block.push(Statement::PassStatement);
+                }
+                self.found = true;
+            }
+            Some(Statement::IfStatement(if_statement)) => {
+                for case in
+                    std::iter::once(&mut if_statement.case_if).chain(&mut if_statement.cases_elif)
+                {
+                    self.rewrite(&mut case.block);
+                }
+                if let Some(case_else) = &mut if_statement.case_else {
+                    self.rewrite(&mut case_else.block);
+                }
+            }
+            Some(Statement::SwitchStatement(switch)) => {
+                for case in &mut switch.cases {
+                    self.rewrite(&mut case.block);
+                }
+                if let Some(default) = &mut switch.default {
+                    self.rewrite(&mut default.block);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Adds statements to the end of a block which set the parameters to the arguments of a tail
+    /// call.
+    fn update_parameters(&mut self, block: &mut Vec<Statement>, args: Vec<Expression>) {
+        // the arguments are all worked out before any of the parameters are changed, because
+        // they can depend on each other (e.g. `return f(y, x)`)
+        let mut updates = self
+            .parameters
+            .clone()
+            .into_iter()
+            .zip(args)
+            .filter(|(parameter, arg)| *arg != Expression::Ident(parameter.clone()))
+            .collect::<Vec<_>>();
+        if updates.len() == 1 {
+            let (parameter, arg) = updates.pop().unwrap();
+            block.push(assign(&parameter, arg));
+        } else {
+            let mut assignments = vec![];
+            for (parameter, arg) in updates {
+                let next = self.temporary(&parameter);
+                block.push(assign(&next, arg));
+                assignments.push(assign(&parameter, Expression::Ident(next)));
+            }
+            block.extend(assignments);
+        }
+    }
+
+    /// The temporary variable for the next value of a parameter (`n_next` for `n`, unless the
+    /// program already uses that name).
+    fn temporary(&mut self, parameter: &str) -> String {
+        if let Some(temporary) = self.temporaries.get(parameter) {
+            return temporary.clone();
+        }
+        let mut temporary = format!("{}_next", parameter);
+        let mut suffix = 1;
+        while self.names.contains(&temporary) {
+            suffix += 1;
+            temporary = format!("{}_next{}", parameter, suffix);
+        }
+        self.names.insert(temporary.clone());
+        self.temporaries
+            .insert(parameter.to_string(), temporary.clone());
+        temporary
+    }
+}
+
+/// Finds every name which is used in a program.
+#[derive(Default)]
+struct Names(HashSet<String>);
+
+impl Visitor for Names {
+    fn visit_statement(&mut self, statement: &Statement) {
+        let name = match statement {
+            Statement::ForStatement(for_statement) => Some(&for_statement.ident),
+            Statement::ForEachStatement(for_each) => Some(&for_each.item),
+            Statement::AssignmentStatement(assignment) => Some(&assignment.ident),
+            Statement::ElementAssignment(assignment) => Some(&assignment.array),
+            Statement::TryStatement(try_statement) => Some(&try_statement.catch_var),
+            Statement::Declaration(declaration) => Some(&declaration.name),
+            Statement::ConstDeclaration(declaration) => Some(&declaration.name),
+            Statement::ArrayDeclaration(declaration) => Some(&declaration.name),
+            Statement::FunctionDefinition(function) => {
+                self.0.extend(
+                    function
+                        .parameters
+                        .iter()
+                        .map(|parameter| parameter.name.clone()),
+                );
+                Some(&function.name)
+            }
+            _ => None,
+        };
+        self.0.extend(name.cloned());
+        walk_statement(self, statement)
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Ident(name) | Expression::FunctionCall { name, .. } => {
+                self.0.insert(name.clone());
+            }
+            Expression::InterpolatedString(parts) => {
+                for part in parts {
+                    if let StringPart::Placeholder(name) = part {
+                        self.0.insert(name.clone());
+                    }
+                }
+            }
+            _ => {}
+        }
+        walk_expression(self, expression)
+    }
+}
+
+/// Builds the statement `ident = expression`.
+fn assign(ident: &str, expression: Expression) -> Statement {
+    Statement::AssignmentStatement(AssignmentStatement {
+        ident: ident.to_string(),
+        operator: Operator::Equals,
+        expression,
+    })
+}
@@ -0,0 +1,162 @@
+//! Unit tests for the tail call transform.
+
+use crate::js_codegen;
+use crate::lexer::lex;
+use crate::parser::visit::{walk_expression, Visitor};
+use crate::parser::{parse, Expression, Statement};
+use crate::tail_calls::eliminate_tail_calls;
+
+fn transform(source: &str) -> Vec<Statement> {
+    let mut source = source.to_string();
+    let mut program = parse(lex(&mut source).unwrap()).unwrap();
+    eliminate_tail_calls(&mut program);
+    program
+}
+
+/// Finds the names of all the functions which are called.
+#[derive(Default)]
+struct Calls(Vec<String>);
+
+impl Visitor for Calls {
+    fn visit_expression(&mut self, expression: &Expression) {
+        if let Expression::FunctionCall { name, .. } = expression {
+            self.0.push(name.clone());
+        }
+        walk_expression(self, expression)
+    }
+}
+
+#[test]
+pub fn test_rewrites_tail_call_as_loop() {
+    let program = transform(
+        r#"
+function factorial(n, total)
+    if n == 0 then
+        return total
+    endif
+    return factorial(n - 1, total * n)
+endfunction
+"#,
+    );
+    let function = match &program[0] {
+        Statement::FunctionDefinition(function) => function,
+        other => panic!("expected a function definition, got {:?}", other),
+    };
+    assert!(matches!(function.block[..], [Statement::WhileStatement(_)]));
+    let mut calls = Calls::default();
+    calls.visit_statement(&program[0]);
+    assert!(calls.0.is_empty(), "still calls {:?}", calls.0);
+    assert_eq!(
+        js_codegen::program(&program),
+        r#"function factorial(n, total) {
+    while (true) {
+        if (n === 0) {
+            return total;
+        }
+        n_next = n - 1;
+        total_next = total * n;
+        n = n_next;
+        total = total_next;
+    }
+}
+"#
+    );
+}
+
+#[test]
+pub fn test_leaves_other_recursion_alone() {
+    let source = r#"
+function factorial(n)
+    if n == 0 then
+        return 1
+    endif
+    return n * factorial(n - 1)
+endfunction
+"#;
+    let mut original = source.to_string();
+    assert_eq!(
+        transform(source),
+        parse(lex(&mut original).unwrap()).unwrap()
+    );
+}
+
+#[test]
+pub fn test_rewrites_tail_calls_in_branches() {
+    let program = transform(
+        r#"
+function factorial(n, total)
+    if n == 0 then
+        return total
+    else
+        return factorial(n - 1, total * n)
+    endif
+endfunction
+"#,
+    );
+    assert_eq!(
+        js_codegen::program(&program),
+        r#"function factorial(n, total) {
+    while (true) {
+        if (n === 0) {
+            return total;
+        } else {
+            n_next = n - 1;
+            total_next = total * n;
+            n = n_next;
+            total = total_next;
+        }
+    }
+}
+"#
+    );
+}
+
+#[test]
+pub fn test_temporaries_do_not_clash_with_variables() {
+    let program = transform(
+        r#"
+function sum(n, total)
+    n_next = n - 1
+    if n == 0 then
+        return total
+    endif
+    return sum(n_next, total + n)
+endfunction
+total_next = 0
+"#,
+    );
+    assert_eq!(
+        js_codegen::program(&program[..1]),
+        r#"function sum(n, total) {
+    while (true) {
+        n_next = n - 1;
+        if (n === 0) {
+            return total;
+        }
+        n_next2 = n_next;
+        total_next2 = total + n;
+        n = n_next2;
+        total = total_next2;
+    }
+}
+"#
+    );
+}
+
+#[test]
+pub fn test_leaves_functions_which_can_finish_without_returning_alone() {
+    // once the body is a loop, getting to the end of it would go round again
+    let source = r#"
+function countdown(n)
+    if n > 0 then
+        print(n)
+        return countdown(n - 1)
+    endif
+endfunction
+"#;
+    let mut original = source.to_string();
+    assert_eq!(
+        transform(source),
+        parse(lex(&mut original).unwrap()).unwrap()
+    );
+}
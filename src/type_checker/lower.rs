@@ -0,0 +1,163 @@
+//! Rewrites the parts of a (checked) program which depend on what the type checker knows about it,
+//! so that the code generators don't have to work any of it out themselves.
+
+use crate::lexer::Operator;
+use crate::parser::visit::{walk_block_mut, walk_expression_mut, walk_statement_mut, VisitorMut};
+use crate::parser::{ArgSpans, Expression, Statement};
+use crate::type_checker::{Checker, Type};
+
+/// Lowers a program which has been checked (with `check`). This:
+///
+/// * turns string repetition (`"-" * 10` or `10 * "-"`) into a call to the string's `repeat`
+///   method
+/// * converts what `input` reads to the type of the variable it's assigned to, if that's a number
+///   or a boolean (so `age = input()` becomes `age = int(input())` if `age` is an integer)
+/// * passes the arguments to the program's functions by position, rather than by name (using the
+///   default values of any parameters which are left out before a named argument)
+/// * replaces type aliases in declarations with the types they stand for (so `DECLARE s : Score`
+///   becomes `DECLARE s : Integer`, where `TYPE Score = INTEGER`)
+pub fn lower(program: &mut [Statement]) {
+    rewrite(program, &mut Lowering)
+}
+
+/// Replaces integer expressions which only use literals and constants (e.g. `SIZE * 2`, where
+/// `const SIZE = 3`) with their values. The values are worked out in the same way as when the type
+/// checker checks indexes, so a constant declared in a function is only substituted inside it.
+pub fn fold_constants(program: &mut [Statement]) {
+    rewrite(program, &mut ConstantFolding)
+}
+
+/// A change made to each part of a checked program by `rewrite`.
+trait Rewrite {
+    /// Changes a statement, before anything it binds is recorded (and before the statements and
+    /// expressions inside it are visited).
+    fn statement(&mut self, _checker: &Checker, _statement: &mut Statement) {}
+
+    /// Changes an expression, before its subexpressions are visited.
+    fn expression(&mut self, _checker: &Checker, _expression: &mut Expression) {}
+}
+
+struct Lowering;
+
+impl Rewrite for Lowering {
+    fn statement(&mut self, checker: &Checker, statement: &mut Statement) {
+        if let Statement::Declaration(declaration) = statement {
+            if let Ok(Type::Named(named)) = checker.resolve(&declaration.ty) {
+                declaration.ty = named.name;
+            }
+        } else if let Statement::AssignmentStatement(assignment) = statement {
+            if let Some(conversion) =
+                checker.input_conversion(&assignment.ident, &assignment.expression)
+            {
+                let input = std::mem::replace(&mut assignment.expression, Expression::Integer(0));
+                assignment.expression = Expression::FunctionCall {
+                    name: conversion.to_string(),
+                    args: vec![input],
+                    named_args: vec![],
+                    spans: Box::new(ArgSpans::default()),
+                };
+            }
+        }
+    }
+
+    fn expression(&mut self, checker: &Checker, expression: &mut Expression) {
+        if let Expression::FunctionCall {
+            name,
+            args,
+            named_args,
+            ..
+        } = expression
+        {
+            let parameters = match checker.signatures.get(name) {
+                Some(parameters) => parameters,
+                None => return,
+            };
+            for (parameter, default) in &parameters[args.len().min(parameters.len())..] {
+                if named_args.is_empty() {
+                    break;
+                }
+                match named_args.iter().position(|(named, _)| named == parameter) {
+                    Some(index) => args.push(named_args.remove(index).1),
+                    None => args.extend(default.clone()),
+                }
+            }
+        } else if let Expression::BinaryOp {
+            operator: Operator::Times,
+            lhs,
+            rhs,
+        } = expression
+        {
+            let (string, count) = if checker.is_string(lhs) {
+                (lhs, rhs)
+            } else if checker.is_string(rhs) {
+                (rhs, lhs)
+            } else {
+                return;
+            };
+            *expression = Expression::MethodCall {
+                object: std::mem::replace(string, Box::new(Expression::Integer(0))),
+                name: "repeat".to_string(),
+                args: vec![*std::mem::replace(count, Box::new(Expression::Integer(0)))],
+                named_args: vec![],
+            };
+        }
+    }
+}
+
+struct ConstantFolding;
+
+impl Rewrite for ConstantFolding {
+    fn expression(&mut self, checker: &Checker, expression: &mut Expression) {
+        if let Some(value) = checker.fold(expression) {
+            *expression = Expression::Integer(value);
+        }
+    }
+}
+
+/// Makes a change to every statement and expression in a checked program (outermost first, and
+/// then to whatever is inside what the change leaves there), with a checker which knows about the
+/// variables and constants in scope at each one.
+fn rewrite(program: &mut [Statement], change: &mut impl Rewrite) {
+    let mut rewriter = Rewriter {
+        checker: Checker::for_program(program),
+        change,
+    };
+    walk_block_mut(&mut rewriter, program)
+}
+
+struct Rewriter<'a, R> {
+    checker: Checker,
+    change: &'a mut R,
+}
+
+impl<R: Rewrite> VisitorMut for Rewriter<'_, R> {
+    fn visit_statement_mut(&mut self, statement: &mut Statement) {
+        self.change.statement(&self.checker, statement);
+        match statement {
+            Statement::FunctionDefinition(function) => {
+                let return_type = function.returns.as_deref().and_then(|name| {
+                    Some((function.name.clone(), self.checker.resolve(name).ok()?))
+                });
+                let outer = self.checker.enter_function(function, return_type);
+                walk_statement_mut(self, statement);
+                self.checker.leave_function(outer);
+            }
+            Statement::TryStatement(try_statement) => {
+                walk_block_mut(self, &mut try_statement.body);
+                self.checker
+                    .bind_variable(&try_statement.catch_var, Type::builtin("String"));
+                walk_block_mut(self, &mut try_statement.catch_block);
+            }
+            _ => {
+                // the program has already been checked, so this can't fail
+                let _ = self.checker.bind(statement);
+                walk_statement_mut(self, statement)
+            }
+        }
+    }
+
+    fn visit_expression_mut(&mut self, expression: &mut Expression) {
+        self.change.expression(&self.checker, expression);
+        walk_expression_mut(self, expression)
+    }
+}
@@ -1,13 +1,186 @@
 //! Checks that the types are correct
 
+mod lower;
+#[cfg(test)]
+mod unit_tests;
+
+pub use lower::{fold_constants, lower};
+
+use crate::analysis::always_returns;
+use crate::formatter;
+use crate::lexer::{Operator, Span};
+use crate::parser::visit::{walk_block, walk_expression, walk_statement, Visitor};
+use crate::parser::{
+    ArgSpans, Expression, FunctionDefinition, NamedArgs, Statement, SwitchCasePattern,
+};
 use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use thiserror::Error as ThisError;
+
+/// The types which are built into the language.
+const BUILTIN_TYPES: [&str; 4] = ["Integer", "Real", "String", "Boolean"];
+
+/// Checks the types in a program, returning the first error found. This checks that:
+///
+/// * the types named in declarations and return types exist
+/// * functions which declare a return type (`function area(r) returns REAL`) return values of that
+///   type, and can't reach the end of their body without returning
+/// * the cases of a switch have the same type as the value being switched on
+/// * `for each` loops go over arrays (and the loop variable has the type of the array's elements)
+/// * the variable in a `catch` holds a string (the error message), and `throw` is given one
+/// * both operands of `DIV` are integers
+/// * `MIN`, `MAX` and `SUM` of an array are only used on arrays of numbers
+/// * strings are only repeated (`"-" * 10` or `10 * "-"`) by integers
+/// * only values of the same type are compared
+/// * the operands of `AND`, `OR` and `NOT` are booleans
+/// * constant indexes into arrays of a known size are in bounds
+/// * calls to the program's functions pass an argument for each parameter (unless it has a default
+///   value), and no more
+/// * arguments given by name are for parameters which haven't been given one already, and only
+///   leave out earlier parameters whose default values are literals (because they're passed by
+///   position in the output)
+pub fn check(program: &[Statement]) -> Result<(), TypeError> {
+    match check_located(program).into_iter().next() {
+        Some((_, error)) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// The same as `check`, except that this returns every error (in the order they were found), each
+/// with the index (in `program`) of the top-level statement it was found in. An error stops the
+/// rest of the expressions in the statement it's in being checked, but not the statements inside
+/// it or after it.
+pub fn check_located(program: &[Statement]) -> Vec<(usize, TypeError)> {
+    let mut checker = Checker::for_program(program);
+    let mut errors = vec![];
+    for (index, statement) in program.iter().enumerate() {
+        checker.visit_statement(statement);
+        errors.extend(checker.errors.drain(..).map(|error| (index, error)));
+    }
+    errors
+}
+
+#[derive(ThisError, Debug)]
+/// An error found while checking types.
+pub enum TypeError {
+    #[error("there isn't a type called `{0}`")]
+    UnknownType(String),
+    /// A type alias refers to itself (possibly through other aliases), so it can't be expanded.
+    #[error("the type `{0}` is defined in terms of itself")]
+    RecursiveType(String),
+    #[error("`{function}` should return {expected}, but this returns {found}")]
+    MismatchedReturnType {
+        function: String,
+        expected: Box<Type>,
+        found: Box<Type>,
+    },
+    #[error("`for each` can only loop over an array, but this is {0}")]
+    NonArrayIteration(Box<Type>),
+    /// `throw` is given the error message, which `catch` binds as a string.
+    #[error("`throw` needs an error message (a string), but this is {0}")]
+    NonStringThrow(Box<Type>),
+    /// `MIN`, `MAX` or `SUM` is given a single value which isn't an array of integers or reals.
+    #[error("`{function}` needs an array of numbers, but this is {found}")]
+    NonNumericArray { function: String, found: Box<Type> },
+    #[error("`DIV` can only divide integers, but this is {0}")]
+    NonIntegerDivision(Box<Type>),
+    /// A string can be repeated with `*` (`"-" * 10`), but only by an integer.
+    #[error("a string can only be repeated a whole number of times, but this repeats it by {0}")]
+    NonIntegerRepetition(Box<Type>),
+    #[error("{lhs} and {rhs} can't be compared, because they're different types of value")]
+    MismatchedComparison { lhs: Box<Type>, rhs: Box<Type> },
+    #[error("the switch is on a value of type {expected}, but this case compares it to {found}")]
+    MismatchedSwitchCase {
+        expected: Box<Type>,
+        found: Box<Type>,
+    },
+    /// `AND`, `OR` and `NOT` only work on booleans (so `1 AND 2` isn't allowed, even though some
+    /// languages would treat any non-zero number as true).
+    #[error("`{operator}` only works on true or false values, but `{operand}` is {found}")]
+    NonBooleanOperand {
+        operator: Operator,
+        operand: String,
+        found: Box<Type>,
+    },
+    /// A function with a declared return type can get to the end of its body without returning.
+    #[error("`{0}` should return a value, but it can reach the end without returning one")]
+    MissingReturn(String),
+    #[error("`{array}` has {size} elements, so there isn't an element at index {index}")]
+    IndexOutOfBounds {
+        array: String,
+        index: i64,
+        size: i64,
+    },
+    /// A call passes more arguments than the function has parameters. The span is that of the
+    /// first extra argument.
+    #[error("`{function}` has {expected} parameters, but this passes {found} arguments")]
+    TooManyArguments {
+        function: String,
+        expected: usize,
+        found: usize,
+        span: Option<Span>,
+    },
+    /// A call names an argument for a parameter which the function doesn't have.
+    #[error("`{function}` doesn't have a parameter called `{parameter}`")]
+    UnknownParameter {
+        function: String,
+        parameter: String,
+        span: Option<Span>,
+    },
+    /// A call gives a parameter more than one argument (e.g. one by position and one by name).
+    #[error("this passes more than one argument for the `{parameter}` parameter of `{function}`")]
+    DuplicateArgument {
+        function: String,
+        parameter: String,
+        span: Option<Span>,
+    },
+    /// A call gives an argument by name for a later parameter than `parameter`, which it doesn't
+    /// give an argument for. Arguments are passed by position in the output, so the default value
+    /// is passed in its place, which only means the same thing if it's a literal. The span is that
+    /// of the named argument.
+    #[error(
+        "`{parameter}` can't be left out by naming a later argument to `{function}`, because its \
+         default value isn't a literal"
+    )]
+    SkippedParameter {
+        function: String,
+        parameter: String,
+        span: Option<Span>,
+    },
+    /// A call doesn't pass an argument for a parameter which doesn't have a default value. The
+    /// span is that of the call's closing bracket (where the argument would have gone).
+    #[error("`{function}` needs an argument for its `{parameter}` parameter")]
+    MissingArgument {
+        function: String,
+        parameter: String,
+        span: Option<Span>,
+    },
+}
+
+impl TypeError {
+    /// The part of the source code the error is about (if this is known).
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            TypeError::TooManyArguments { span, .. }
+            | TypeError::UnknownParameter { span, .. }
+            | TypeError::DuplicateArgument { span, .. }
+            | TypeError::SkippedParameter { span, .. }
+            | TypeError::MissingArgument { span, .. } => *span,
+            _ => None,
+        }
+    }
+}
 
 /// Maintains a "jar" containing all the bound variables and their types.
+#[derive(Default)]
 pub struct BindingJar {
     bindings: HashMap<String, Type>,
+    /// The number of elements in each array whose size is known.
+    array_sizes: HashMap<String, i64>,
 }
 
-#[derive(PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Path {
     /// The parts of the path.
     parts: Vec<String>,
@@ -16,7 +189,20 @@ pub struct Path {
 /// A type.
 ///
 /// Types are inferred. Entire programs are statically typed.
-pub struct Type {
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Type {
+    /// A type which is referred to by name (e.g. `Integer`).
+    Named(NamedType),
+    /// An array whose elements all have the given type.
+    Array(Box<Type>),
+    /// A function which takes arguments of the first types and returns the second type.
+    Function(Vec<Type>, Box<Type>),
+}
+
+/// A type which is referred to by name.
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub struct NamedType {
     /// A unique identifier for each type.
     id: i32,
     /// The name of the type
@@ -25,8 +211,755 @@ pub struct Type {
     location: Path,
 }
 
-impl PartialEq for Type {
+impl PartialEq for NamedType {
     fn eq(&self, other: &Self) -> bool {
         self.name == other.name && self.location == other.location
     }
 }
+
+impl Eq for NamedType {}
+
+impl Hash for NamedType {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // this has to agree with `PartialEq`, so the id is left out
+        self.name.hash(state);
+        self.location.hash(state);
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Named(named) => f.write_str(&named.name),
+            Type::Array(element) => write!(f, "Array({})", element),
+            Type::Function(parameters, returns) => {
+                f.write_str("Function(")?;
+                for (i, parameter) in parameters.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", parameter)?;
+                }
+                write!(f, ") -> {}", returns)
+            }
+        }
+    }
+}
+
+impl Type {
+    /// Looks up a builtin type by name, ignoring case (so `REAL` and `Real` are the same). `FLOAT`,
+    /// `INT`, `STR` and `BOOL` are accepted as well.
+    pub fn builtin(name: &str) -> Option<Self> {
+        let name = match name.to_lowercase().as_str() {
+            "integer" | "int" => "Integer",
+            "real" | "float" => "Real",
+            "string" | "str" => "String",
+            "boolean" | "bool" => "Boolean",
+            _ => return None,
+        };
+        let id = BUILTIN_TYPES
+            .iter()
+            .position(|builtin| *builtin == name)
+            .unwrap() as i32;
+        Some(Type::Named(NamedType {
+            id,
+            name: name.to_string(),
+            location: Path { parts: vec![] },
+        }))
+    }
+
+    /// A type which is referred to by a name which isn't a builtin (e.g. an alias). These don't have
+    /// ids yet, so they all get -1.
+    pub fn named(name: &str) -> Self {
+        Type::Named(NamedType {
+            id: -1,
+            name: name.to_string(),
+            location: Path { parts: vec![] },
+        })
+    }
+
+    /// Whether this is the builtin type with the given name.
+    pub fn is_builtin(&self, name: &str) -> bool {
+        Type::builtin(name).as_ref() == Some(self)
+    }
+
+    /// Whether a value of this type can be used where a value of type `expected` is expected.
+    ///
+    /// This is the case if they are the same type, or if an integer is used where a real is
+    /// expected.
+    fn fits(&self, expected: &Type) -> bool {
+        self == expected || (self.is_builtin("Integer") && expected.is_builtin("Real"))
+    }
+
+    /// Whether this is an integer or a real.
+    fn is_number(&self) -> bool {
+        self.is_builtin("Integer") || self.is_builtin("Real")
+    }
+
+    /// The type of a value which could have either of two types, which is whichever of the two the
+    /// other one fits into (so an integer and a real give a real).
+    fn common(self, other: Type) -> Option<Type> {
+        if self.fits(&other) {
+            Some(other)
+        } else if other.fits(&self) {
+            Some(self)
+        } else {
+            None
+        }
+    }
+}
+
+/// Walks the program, checking the types of statements and expressions (see `check` for what's
+/// checked) until it finds an error.
+#[derive(Default)]
+struct Checker {
+    bindings: BindingJar,
+    /// The types which other names stand for (declared with `TYPE Score = INTEGER`). Like
+    /// `functions`, these are collected before anything is checked.
+    aliases: HashMap<String, Type>,
+    /// The values of the integer constants declared so far. Constants declared outside of a
+    /// function can be used inside it, but not the other way round.
+    constants: HashMap<String, i64>,
+    /// The declared return types of the functions in the program. These are collected before
+    /// anything is checked, so a function can be called above its definition.
+    functions: HashMap<String, Type>,
+    /// The parameters of the functions in the program, and their default values. Like `functions`,
+    /// these are collected before anything is checked.
+    signatures: HashMap<String, Vec<(String, Option<Expression>)>>,
+    /// The name and declared return type of the function currently being checked.
+    return_type: Option<(String, Type)>,
+    /// The error found in the statement being checked (which stops the rest of its expressions
+    /// being checked).
+    error: Option<TypeError>,
+    /// The errors found in the statements which have been checked.
+    errors: Vec<TypeError>,
+}
+
+/// What the checker knew outside of the function it's checking.
+struct OuterScope {
+    bindings: BindingJar,
+    constants: HashMap<String, i64>,
+    return_type: Option<(String, Type)>,
+}
+
+impl Checker {
+    /// Creates a checker which knows about all the functions and type aliases in a program. These
+    /// can only be defined at the top level, so this finds all of them (including the ones defined
+    /// below where they're used).
+    fn for_program(program: &[Statement]) -> Self {
+        let mut checker = Checker::default();
+        // the aliases are collected first, because a function's return type can be an alias
+        for statement in program {
+            if let Statement::TypeAlias(alias) = statement {
+                checker
+                    .aliases
+                    .insert(alias.name.clone(), Type::named(&alias.ty));
+            }
+        }
+        for statement in program {
+            if let Statement::FunctionDefinition(function) = statement {
+                checker.signatures.insert(
+                    function.name.clone(),
+                    function
+                        .parameters
+                        .iter()
+                        .map(|parameter| (parameter.name.clone(), parameter.default.clone()))
+                        .collect(),
+                );
+                if let Some(Ok(return_type)) = function
+                    .returns
+                    .as_deref()
+                    .map(|name| checker.resolve(name))
+                {
+                    checker.functions.insert(function.name.clone(), return_type);
+                }
+            }
+        }
+        checker
+    }
+
+    /// Records the names which a statement binds (and their types, if they're known). Any
+    /// statements inside it aren't looked at.
+    fn bind(&mut self, statement: &Statement) -> Result<(), TypeError> {
+        match statement {
+            Statement::AssignmentStatement(assignment)
+                if assignment.operator == Operator::Equals
+                    || assignment.operator == Operator::Assign =>
+            {
+                let found = match self.input_conversion(&assignment.ident, &assignment.expression) {
+                    // what's read is converted to the variable's type (see `lower`)
+                    Some(_) => self.bindings.bindings.get(&assignment.ident).cloned(),
+                    None => self.infer(&assignment.expression),
+                };
+                self.bind_variable(&assignment.ident, found);
+            }
+            Statement::Declaration(declaration) => {
+                let ty = self.resolve(&declaration.ty)?;
+                self.bindings.bindings.insert(declaration.name.clone(), ty);
+            }
+            Statement::ConstDeclaration(declaration) => {
+                match self.infer(&declaration.value) {
+                    Some(found) => {
+                        self.bindings
+                            .bindings
+                            .insert(declaration.name.clone(), found);
+                    }
+                    None => {
+                        self.bindings.bindings.remove(&declaration.name);
+                    }
+                }
+                match self.fold(&declaration.value) {
+                    Some(value) => {
+                        self.constants.insert(declaration.name.clone(), value);
+                    }
+                    None => {
+                        self.constants.remove(&declaration.name);
+                    }
+                }
+            }
+            Statement::ForEachStatement(for_each) => match self.infer(&for_each.iterable) {
+                Some(Type::Array(element)) => self.bind_variable(&for_each.item, Some(*element)),
+                Some(found) => return Err(TypeError::NonArrayIteration(Box::new(found))),
+                None => self.bind_variable(&for_each.item, None),
+            },
+            Statement::ArrayDeclaration(declaration) => {
+                self.bindings.bindings.remove(&declaration.name);
+                match self.fold(&declaration.size) {
+                    Some(size) => {
+                        self.bindings
+                            .array_sizes
+                            .insert(declaration.name.clone(), size);
+                    }
+                    None => {
+                        self.bindings.array_sizes.remove(&declaration.name);
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// The builtin which converts what `input` reads (a string) to the type of the variable it's
+    /// assigned to, if that's known to be a number or a boolean (e.g. `int` for `age = input()`
+    /// where `age` is an integer).
+    fn input_conversion(&self, target: &str, expression: &Expression) -> Option<&'static str> {
+        match expression {
+            Expression::FunctionCall { name, .. } if name == "input" => {}
+            _ => return None,
+        }
+        let ty = self.bindings.bindings.get(target)?;
+        [("Integer", "int"), ("Real", "float"), ("Boolean", "bool")]
+            .iter()
+            .find(|(name, _)| ty.is_builtin(name))
+            .map(|(_, conversion)| *conversion)
+    }
+
+    /// Binds a variable to a value of the given type (or of an unknown type), which replaces
+    /// anything known about the variable before.
+    fn bind_variable(&mut self, name: &str, ty: Option<Type>) {
+        self.constants.remove(name);
+        self.bindings.array_sizes.remove(name);
+        match ty {
+            Some(ty) => {
+                self.bindings.bindings.insert(name.to_string(), ty);
+            }
+            None => {
+                self.bindings.bindings.remove(name);
+            }
+        }
+    }
+
+    /// Starts checking the body of a function, which has the given return type and starts without
+    /// any of the variables from outside (but with the constants, unless a parameter has the same
+    /// name). The returned scope has to be passed to `leave_function` afterwards.
+    fn enter_function(
+        &mut self,
+        function: &FunctionDefinition,
+        return_type: Option<(String, Type)>,
+    ) -> OuterScope {
+        let outer = OuterScope {
+            bindings: std::mem::take(&mut self.bindings),
+            constants: self.constants.clone(),
+            return_type: std::mem::replace(&mut self.return_type, return_type),
+        };
+        for parameter in &function.parameters {
+            self.constants.remove(&parameter.name);
+        }
+        outer
+    }
+
+    /// Goes back to the scope outside of a function.
+    fn leave_function(&mut self, outer: OuterScope) {
+        self.bindings = outer.bindings;
+        self.constants = outer.constants;
+        self.return_type = outer.return_type;
+    }
+
+    /// Looks up the type with the given name, expanding any aliases.
+    fn resolve(&self, name: &str) -> Result<Type, TypeError> {
+        self.resolve_named(name, &mut vec![])
+    }
+
+    /// Looks up a type by name, where `resolving` holds the aliases which are being expanded. If
+    /// `name` is one of them then the alias refers to itself (this is the "occurs check"), which
+    /// would otherwise expand forever.
+    fn resolve_named(&self, name: &str, resolving: &mut Vec<String>) -> Result<Type, TypeError> {
+        if let Some(builtin) = Type::builtin(name) {
+            return Ok(builtin);
+        }
+        let definition = match self.aliases.get(name) {
+            Some(definition) => definition,
+            None => return Err(TypeError::UnknownType(name.to_string())),
+        };
+        if resolving.iter().any(|alias| alias == name) {
+            return Err(TypeError::RecursiveType(name.to_string()));
+        }
+        resolving.push(name.to_string());
+        let resolved = self.expand(definition, resolving)?;
+        resolving.pop();
+        Ok(resolved)
+    }
+
+    /// Replaces any aliases inside a type with the types they stand for.
+    fn expand(&self, ty: &Type, resolving: &mut Vec<String>) -> Result<Type, TypeError> {
+        Ok(match ty {
+            Type::Named(named) => self.resolve_named(&named.name, resolving)?,
+            Type::Array(element) => Type::Array(Box::new(self.expand(element, resolving)?)),
+            Type::Function(parameters, returns) => Type::Function(
+                parameters
+                    .iter()
+                    .map(|parameter| self.expand(parameter, resolving))
+                    .collect::<Result<_, _>>()?,
+                Box::new(self.expand(returns, resolving)?),
+            ),
+        })
+    }
+
+    /// Infers the type of an expression, returning `None` if it can't be worked out (e.g. because
+    /// it uses a parameter, whose type isn't known).
+    fn infer(&self, expression: &Expression) -> Option<Type> {
+        match expression {
+            Expression::Integer(_) => Type::builtin("Integer"),
+            Expression::Float(_) => Type::builtin("Real"),
+            Expression::Boolean(_) => Type::builtin("Boolean"),
+            Expression::String(_) | Expression::InterpolatedString(_) => Type::builtin("String"),
+            Expression::Ident(ident) => self.bindings.bindings.get(ident).cloned(),
+            // the elements all have to fit into one type
+            Expression::Array(elements) => {
+                let mut element = self.infer(elements.first()?)?;
+                for other in &elements[1..] {
+                    element = element.common(self.infer(other)?)?;
+                }
+                Some(Type::Array(Box::new(element)))
+            }
+            Expression::UnaryOp {
+                operator: Operator::Not,
+                ..
+            } => Type::builtin("Boolean"),
+            Expression::UnaryOp { operand, .. } => self.infer(operand),
+            Expression::BinaryOp { operator, lhs, rhs } => match operator {
+                operator if operator.is_comparison() => Type::builtin("Boolean"),
+                Operator::And | Operator::Or => Type::builtin("Boolean"),
+                Operator::Divide => Type::builtin("Real"),
+                Operator::IntegerDivide => Type::builtin("Integer"),
+                Operator::Times if self.is_string(lhs) || self.is_string(rhs) => {
+                    Type::builtin("String")
+                }
+                _ => self.infer_common(lhs, rhs),
+            },
+            Expression::FunctionCall { name, args, .. } => match (name.as_str(), &args[..]) {
+                ("MIN", [lhs, rhs]) | ("MAX", [lhs, rhs]) => self.infer_common(lhs, rhs),
+                // these give back a value of the array's element type
+                ("MIN", [array]) | ("MAX", [array]) | ("SUM", [array]) => {
+                    match self.infer(array)? {
+                        Type::Array(element) if element.is_number() => Some(*element),
+                        _ => None,
+                    }
+                }
+                ("input", _) | ("str", [_]) => Type::builtin("String"),
+                ("int", [_]) => Type::builtin("Integer"),
+                ("float", [_]) | ("real", [_]) => Type::builtin("Real"),
+                ("bool", [_]) => Type::builtin("Boolean"),
+                ("RANDOM", []) => Type::builtin("Real"),
+                ("RANDOM_INT", [_, _]) => Type::builtin("Integer"),
+                _ => self.functions.get(name).cloned(),
+            },
+            Expression::Index { array, .. } => match self.infer(array)? {
+                Type::Array(element) => Some(*element),
+                _ => None,
+            },
+            Expression::Slice { array, .. } => match self.infer(array)? {
+                array @ Type::Array(_) => Some(array),
+                _ => None,
+            },
+            Expression::Member { .. } => None,
+            // string repetition is turned into this by `lower`
+            Expression::MethodCall { object, name, .. } if name == "repeat" => self.infer(object),
+            Expression::MethodCall { .. } => None,
+        }
+    }
+
+    /// Whether an expression is known to be a string.
+    fn is_string(&self, expression: &Expression) -> bool {
+        self.infer(expression)
+            .is_some_and(|found| found.is_builtin("String"))
+    }
+
+    /// Works out the value of an integer expression which only uses literals and constants (e.g.
+    /// `SIZE - 1`), returning `None` if it can't be known before the program is run.
+    fn fold(&self, expression: &Expression) -> Option<i64> {
+        match expression {
+            Expression::Integer(integer) => Some(*integer),
+            Expression::Ident(ident) => self.constants.get(ident).copied(),
+            Expression::UnaryOp {
+                operator: Operator::Minus,
+                operand,
+            } => self.fold(operand)?.checked_neg(),
+            Expression::BinaryOp { operator, lhs, rhs } => {
+                let (lhs, rhs) = (self.fold(lhs)?, self.fold(rhs)?);
+                match operator {
+                    Operator::Plus => lhs.checked_add(rhs),
+                    Operator::Minus => lhs.checked_sub(rhs),
+                    Operator::Times => lhs.checked_mul(rhs),
+                    Operator::IntegerDivide => lhs.checked_div(rhs),
+                    Operator::Modulo => lhs.checked_rem(rhs),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Checks that an operand of `AND`, `OR` or `NOT` is a boolean (if its type is known),
+    /// returning whether it is.
+    fn check_boolean(&mut self, operator: &Operator, operand: &Expression) -> bool {
+        match self.infer(operand) {
+            Some(found) if !found.is_builtin("Boolean") => {
+                self.error = Some(TypeError::NonBooleanOperand {
+                    operator: operator.clone(),
+                    operand: formatter::expression(operand),
+                    found: Box::new(found),
+                });
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// Checks that a call to one of the program's functions passes the right arguments (see
+    /// `check`), returning whether it does.
+    fn check_arguments(
+        &mut self,
+        function: &str,
+        args: &[Expression],
+        named_args: &NamedArgs,
+        spans: &ArgSpans,
+    ) -> bool {
+        let parameters = match self.signatures.get(function) {
+            Some(parameters) => parameters,
+            None => return true,
+        };
+        if args.len() > parameters.len() {
+            self.error = Some(TypeError::TooManyArguments {
+                function: function.to_string(),
+                expected: parameters.len(),
+                found: args.len() + named_args.len(),
+                span: spans.args.get(parameters.len()).copied(),
+            });
+            return false;
+        }
+        let is_named = |parameter: &str| named_args.iter().any(|(named, _)| named == parameter);
+        // the index of the parameter each named argument is for
+        let mut indexes = vec![];
+        for (i, (name, _)) in named_args.iter().enumerate() {
+            let span = spans.named.get(i).copied();
+            let error = match parameters
+                .iter()
+                .position(|(parameter, _)| parameter == name)
+            {
+                Some(index)
+                    if index >= args.len()
+                        && !named_args[..i].iter().any(|(other, _)| other == name) =>
+                {
+                    indexes.push(index);
+                    continue;
+                }
+                Some(_) => TypeError::DuplicateArgument {
+                    function: function.to_string(),
+                    parameter: name.clone(),
+                    span,
+                },
+                None => TypeError::UnknownParameter {
+                    function: function.to_string(),
+                    parameter: name.clone(),
+                    span,
+                },
+            };
+            self.error = Some(error);
+            return false;
+        }
+        let missing = parameters
+            .iter()
+            .skip(args.len())
+            .find(|(name, default)| default.is_none() && !is_named(name));
+        if let Some((parameter, _)) = missing {
+            self.error = Some(TypeError::MissingArgument {
+                function: function.to_string(),
+                parameter: parameter.clone(),
+                span: spans.close,
+            });
+            return false;
+        }
+        // the parameters which are left out before the last named argument get their default
+        // values passed by position (see `lower`)
+        let (last, span) = match indexes.iter().enumerate().max_by_key(|(_, index)| **index) {
+            Some((i, index)) => (*index, spans.named.get(i).copied()),
+            None => return true,
+        };
+        let skipped = parameters[args.len()..last].iter().find(|(name, default)| {
+            !is_named(name)
+                && !matches!(
+                    default,
+                    Some(
+                        Expression::Integer(_)
+                            | Expression::Float(_)
+                            | Expression::Boolean(_)
+                            | Expression::String(_)
+                    )
+                )
+        });
+        if let Some((parameter, _)) = skipped {
+            self.error = Some(TypeError::SkippedParameter {
+                function: function.to_string(),
+                parameter: parameter.clone(),
+                span,
+            });
+            return false;
+        }
+        true
+    }
+
+    /// Infers the type of a value which could be either `lhs` or `rhs` (e.g. the result of
+    /// `lhs + rhs`), which is whichever of the two types the other one fits into.
+    fn infer_common(&self, lhs: &Expression, rhs: &Expression) -> Option<Type> {
+        self.infer(lhs)?.common(self.infer(rhs)?)
+    }
+}
+
+impl Visitor for Checker {
+    fn visit_statement(&mut self, statement: &Statement) {
+        // an error in the statement around this one (e.g. in the condition of an `if`) doesn't stop
+        // the statements inside it being checked, and nor does one in an earlier statement
+        self.errors.extend(self.error.take());
+        self.check_statement(statement);
+        self.errors.extend(self.error.take());
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        if self.error.is_some() {
+            return;
+        }
+        self.check_expression(expression);
+    }
+}
+
+impl Checker {
+    /// Checks a statement (and the statements and expressions inside it), stopping at the first
+    /// error.
+    fn check_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::FunctionDefinition(function) => {
+                let return_type = match &function.returns {
+                    Some(name) => match self.resolve(name) {
+                        Ok(return_type) => Some((function.name.clone(), return_type)),
+                        Err(error) => {
+                            self.error = Some(error);
+                            return;
+                        }
+                    },
+                    None => None,
+                };
+                if return_type.is_some() && !always_returns(&function.block) {
+                    self.error = Some(TypeError::MissingReturn(function.name.clone()));
+                    return;
+                }
+                let outer = self.enter_function(function, return_type);
+                walk_statement(self, statement);
+                self.leave_function(outer);
+            }
+            // this is where an alias which can't be expanded is reported (rather than where it's
+            // used)
+            Statement::TypeAlias(alias) => {
+                if let Err(error) = self.resolve(&alias.name) {
+                    self.error = Some(error);
+                }
+            }
+            Statement::AssignmentStatement(_)
+            | Statement::ForEachStatement(_)
+            | Statement::Declaration(_)
+            | Statement::ConstDeclaration(_)
+            | Statement::ArrayDeclaration(_) => match self.bind(statement) {
+                Ok(()) => walk_statement(self, statement),
+                Err(error) => self.error = Some(error),
+            },
+            Statement::SwitchStatement(switch) => {
+                // every case has to have the same type as the subject (or, if the subject's type
+                // isn't known, as the other cases)
+                let mut expected = self.infer(&switch.subject);
+                for case in &switch.cases {
+                    let value = match &case.pattern {
+                        SwitchCasePattern::Value(value)
+                        | SwitchCasePattern::Comparison(_, value) => value,
+                    };
+                    match (self.infer(value), &expected) {
+                        (Some(found), Some(subject))
+                            if !found.fits(subject) && !subject.fits(&found) =>
+                        {
+                            self.error = Some(TypeError::MismatchedSwitchCase {
+                                expected: Box::new(subject.clone()),
+                                found: Box::new(found),
+                            });
+                            return;
+                        }
+                        (Some(found), None) => expected = Some(found),
+                        _ => {}
+                    }
+                }
+                walk_statement(self, statement)
+            }
+            // the error message is only bound to the catch variable once the body has been run
+            Statement::TryStatement(try_statement) => {
+                walk_block(self, &try_statement.body);
+                self.bind_variable(&try_statement.catch_var, Type::builtin("String"));
+                walk_block(self, &try_statement.catch_block);
+            }
+            Statement::ThrowStatement(expression) => match self.infer(expression) {
+                Some(found) if !found.is_builtin("String") => {
+                    self.error = Some(TypeError::NonStringThrow(Box::new(found)))
+                }
+                _ => walk_statement(self, statement),
+            },
+            Statement::ReturnStatement(expression) => {
+                if let (Some((function, expected)), Some(found)) =
+                    (&self.return_type, self.infer(expression))
+                {
+                    if !found.fits(expected) {
+                        self.error = Some(TypeError::MismatchedReturnType {
+                            function: function.clone(),
+                            expected: Box::new(expected.clone()),
+                            found: Box::new(found),
+                        });
+                        return;
+                    }
+                }
+                walk_statement(self, statement)
+            }
+            _ => walk_statement(self, statement),
+        }
+    }
+
+    /// Checks an expression (and the expressions inside it).
+    fn check_expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::BinaryOp {
+                operator: Operator::IntegerDivide,
+                lhs,
+                rhs,
+            } => {
+                for operand in [lhs, rhs] {
+                    match self.infer(operand) {
+                        Some(found) if !found.is_builtin("Integer") => {
+                            self.error = Some(TypeError::NonIntegerDivision(Box::new(found)));
+                            return;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Expression::FunctionCall {
+                name,
+                args,
+                named_args,
+                spans,
+            } if !self.check_arguments(name, args, named_args, spans) => return,
+            Expression::FunctionCall { name, args, .. }
+                if matches!(name.as_str(), "MIN" | "MAX" | "SUM") && args.len() == 1 =>
+            {
+                match self.infer(&args[0]) {
+                    Some(Type::Array(element)) if element.is_number() => {}
+                    Some(found) => {
+                        self.error = Some(TypeError::NonNumericArray {
+                            function: name.clone(),
+                            found: Box::new(found),
+                        });
+                        return;
+                    }
+                    None => {}
+                }
+            }
+            // a string can be repeated (`"-" * 10` or `10 * "-"`), but only a whole number of times
+            Expression::BinaryOp {
+                operator: Operator::Times,
+                lhs,
+                rhs,
+            } if self.is_string(lhs) || self.is_string(rhs) => {
+                let count = if self.is_string(lhs) { rhs } else { lhs };
+                match self.infer(count) {
+                    Some(found) if !found.is_builtin("Integer") => {
+                        self.error = Some(TypeError::NonIntegerRepetition(Box::new(found)));
+                        return;
+                    }
+                    _ => {}
+                }
+            }
+            // integers and reals can be compared with each other (so `5 == 5.0` is true), but
+            // nothing else can be compared with a different type of value
+            Expression::BinaryOp { operator, lhs, rhs } if operator.is_comparison() => {
+                if let (Some(lhs), Some(rhs)) = (self.infer(lhs), self.infer(rhs)) {
+                    if !lhs.fits(&rhs) && !rhs.fits(&lhs) {
+                        self.error = Some(TypeError::MismatchedComparison {
+                            lhs: Box::new(lhs),
+                            rhs: Box::new(rhs),
+                        });
+                        return;
+                    }
+                }
+            }
+            Expression::BinaryOp {
+                operator: operator @ (Operator::And | Operator::Or),
+                lhs,
+                rhs,
+            } => {
+                for operand in [lhs, rhs] {
+                    if !self.check_boolean(operator, operand) {
+                        return;
+                    }
+                }
+            }
+            Expression::UnaryOp {
+                operator: operator @ Operator::Not,
+                operand,
+            } if !self.check_boolean(operator, operand) => return,
+            // indexing an array whose size is known with a constant has to stay inside it
+            Expression::Index { array, index } => {
+                if let Expression::Ident(array) = array.as_ref() {
+                    if let (Some(&size), Some(index)) =
+                        (self.bindings.array_sizes.get(array), self.fold(index))
+                    {
+                        if index < 0 || index >= size {
+                            self.error = Some(TypeError::IndexOutOfBounds {
+                                array: array.clone(),
+                                index,
+                                size,
+                            });
+                            return;
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        walk_expression(self, expression)
+    }
+}
@@ -0,0 +1,716 @@
+//! Unit tests for the type checker.
+
+use crate::lexer::{lex, lex_spanned, LexOptions, Loc, Operator, Span};
+use crate::parser::{parse, parse_spanned, Expression, Statement};
+use crate::type_checker::{check, fold_constants, Checker, Type, TypeError};
+use std::collections::HashSet;
+
+fn check_source(source: &str) -> Result<(), TypeError> {
+    let mut source = source.to_string();
+    check(&parse(lex(&mut source).unwrap()).unwrap())
+}
+
+#[test]
+pub fn test_accepts_declared_return_type() {
+    check_source(
+        r#"
+        function area(r) returns REAL
+            pi = 3.14
+            return pi * r * r
+        endfunction
+        function half(x) returns REAL
+            return 1
+        endfunction
+    "#,
+    )
+    .unwrap();
+}
+
+#[test]
+pub fn test_rejects_mismatched_return_type() {
+    match check_source(
+        r#"
+        function count() returns INTEGER
+            message = "one"
+            return message
+        endfunction
+    "#,
+    ) {
+        Err(TypeError::MismatchedReturnType {
+            function,
+            expected,
+            found,
+        }) => {
+            assert_eq!(function, "count");
+            assert_eq!(*expected, Type::builtin("Integer").unwrap());
+            assert_eq!(*found, Type::builtin("String").unwrap());
+        }
+        other => panic!("expected a mismatched return type, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn test_rejects_unknown_return_type() {
+    assert!(matches!(
+        check_source(
+            r#"
+        function f() returns WIDGET
+            return 1
+        endfunction
+    "#
+        ),
+        Err(TypeError::UnknownType(name)) if name == "WIDGET"
+    ));
+}
+
+#[test]
+pub fn test_infers_min_and_max_of_scalars() {
+    check_source(
+        r#"
+        function larger() returns REAL
+            return MAX(1, 2.5)
+        endfunction
+    "#,
+    )
+    .unwrap();
+    assert!(matches!(
+        check_source(
+            r#"
+        function smaller() returns INTEGER
+            return MIN(1, 2.5)
+        endfunction
+    "#
+        ),
+        Err(TypeError::MismatchedReturnType { .. })
+    ));
+}
+
+#[test]
+pub fn test_infers_random_int() {
+    check_source(
+        r#"
+        function roll() returns INTEGER
+            return RANDOM_INT(1, 6)
+        endfunction
+    "#,
+    )
+    .unwrap();
+    assert!(matches!(
+        check_source(
+            r#"
+        function chance() returns INTEGER
+            return RANDOM()
+        endfunction
+    "#
+        ),
+        Err(TypeError::MismatchedReturnType { .. })
+    ));
+}
+
+#[test]
+pub fn test_compares_array_types_structurally() {
+    let integer = Type::builtin("Integer").unwrap();
+    let real = Type::builtin("Real").unwrap();
+    assert_eq!(
+        Type::Array(Box::new(integer.clone())),
+        Type::Array(Box::new(integer.clone()))
+    );
+    assert_ne!(
+        Type::Array(Box::new(integer.clone())),
+        Type::Array(Box::new(real.clone()))
+    );
+    assert_eq!(
+        Type::Function(vec![integer.clone()], Box::new(real.clone())),
+        Type::Function(vec![integer.clone()], Box::new(real.clone()))
+    );
+    assert_ne!(
+        Type::Function(vec![integer.clone()], Box::new(real.clone())),
+        Type::Function(vec![real], Box::new(integer))
+    );
+}
+
+#[test]
+pub fn test_equal_types_hash_the_same() {
+    let mut types = HashSet::new();
+    types.insert(Type::Array(Box::new(Type::builtin("INTEGER").unwrap())));
+    types.insert(Type::Array(Box::new(Type::builtin("int").unwrap())));
+    assert_eq!(types.len(), 1);
+}
+
+#[test]
+pub fn test_binds_declared_variables() {
+    assert!(matches!(
+        check_source(
+            r#"
+        function name() returns INTEGER
+            DECLARE name : STRING
+            return name
+        endfunction
+    "#
+        ),
+        Err(TypeError::MismatchedReturnType { .. })
+    ));
+    assert!(matches!(
+        check_source("DECLARE count : WIDGET\n"),
+        Err(TypeError::UnknownType(name)) if name == "WIDGET"
+    ));
+}
+
+#[test]
+pub fn test_checks_switch_cases_against_subject() {
+    let switch = |case: &str| {
+        format!(
+            "score = 3\nswitch score:\n    case {}:\n        print(score)\nendswitch\n",
+            case
+        )
+    };
+    check_source(&switch("is != 10")).unwrap();
+    assert!(matches!(
+        check_source(&switch("is != \"ten\"")),
+        Err(TypeError::MismatchedSwitchCase { .. })
+    ));
+}
+
+#[test]
+pub fn test_checks_division() {
+    // `/` always gives a real number, and `DIV` gives an integer
+    check_source("function half() returns REAL\n    return 5 / 2\nendfunction\n").unwrap();
+    check_source("function half() returns INTEGER\n    return 5 DIV 2\nendfunction\n").unwrap();
+    assert!(matches!(
+        check_source("function half() returns INTEGER\n    return 5 / 2\nendfunction\n"),
+        Err(TypeError::MismatchedReturnType { .. })
+    ));
+    assert!(matches!(
+        check_source("x = 5.0 DIV 2\n"),
+        Err(TypeError::NonIntegerDivision(_))
+    ));
+}
+
+#[test]
+pub fn test_checks_string_repetition() {
+    check_source("line = \"-\" * 10\nsame = line == \"----------\"\n").unwrap();
+    assert!(matches!(
+        check_source("line = \"-\" * 10\nsame = line == 10\n"),
+        Err(TypeError::MismatchedComparison { .. })
+    ));
+    assert!(matches!(
+        check_source("line = \"-\" * \"-\"\n"),
+        Err(TypeError::NonIntegerRepetition(found)) if found.is_builtin("String")
+    ));
+}
+
+#[test]
+pub fn test_checks_string_repetition_in_either_order() {
+    check_source("dash = \"-\"\nn = 3\nline = n * dash\nsame = line == \"---\"\n").unwrap();
+    assert!(matches!(
+        check_source("line = 3 * \"-\"\nsame = line == 3\n"),
+        Err(TypeError::MismatchedComparison { .. })
+    ));
+    assert!(matches!(
+        check_source("dash = \"-\"\nline = 2.5 * dash\n"),
+        Err(TypeError::NonIntegerRepetition(found)) if found.is_builtin("Real")
+    ));
+}
+
+#[test]
+pub fn test_checks_for_each_loops() {
+    // the loop variable has the type of the array's elements
+    check_source("for each x in [1, 2.5]\n    half = x / 2\n    same = x == 1\nnext x\n").unwrap();
+    assert!(matches!(
+        check_source("for each x in [1, 2]\n    same = x == \"1\"\nnext x\n"),
+        Err(TypeError::MismatchedComparison { .. })
+    ));
+    assert!(matches!(
+        check_source(
+            "names = [\"Ada\"]\nfor each name in names[0:1]\n    same = name == 1\nnext name\n"
+        ),
+        Err(TypeError::MismatchedComparison { .. })
+    ));
+    // the element type of an array which isn't written out isn't known
+    check_source("array arr[5]\nfor each x in arr\n    print(x)\nnext x\n").unwrap();
+    assert!(matches!(
+        check_source("for each c in \"abc\"\n    print(c)\nnext c\n"),
+        Err(TypeError::NonArrayIteration(found)) if found.is_builtin("String")
+    ));
+}
+
+#[test]
+pub fn test_binds_catch_variable_as_string() {
+    let source = |check: &str| {
+        format!(
+            "try\n    e = 1\n    x = 1 DIV 0\ncatch e\n    {}\nendtry\n",
+            check
+        )
+    };
+    check_source(&source("same = e == \"division by zero\"")).unwrap();
+    assert!(matches!(
+        check_source(&source("same = e == 1")),
+        Err(TypeError::MismatchedComparison { lhs, .. }) if lhs.is_builtin("String")
+    ));
+}
+
+#[test]
+pub fn test_checks_thrown_values_are_strings() {
+    check_source("try\n    throw \"oops\"\ncatch e\n    print(e)\nendtry\n").unwrap();
+    check_source("message = \"oops\"\ntry\n    throw message * 2\ncatch e\n    print(e)\nendtry\n")
+        .unwrap();
+    assert!(matches!(
+        check_source("try\n    throw 404\ncatch e\n    print(e)\nendtry\n"),
+        Err(TypeError::NonStringThrow(found)) if found.is_builtin("Integer")
+    ));
+}
+
+#[test]
+pub fn test_checks_min_max_and_sum_of_arrays() {
+    check_source(
+        r#"
+        scores = [3, 1, 2]
+        lowest = MIN(scores)
+        highest = MAX(scores) + 1
+        total = SUM(scores) DIV 3
+        average = SUM([1.5, 2]) / 2
+        "#,
+    )
+    .unwrap();
+    // the element type comes back out
+    assert!(matches!(
+        check_source("total = SUM([1, 2, 3])\nsame = total == \"6\"\n"),
+        Err(TypeError::MismatchedComparison { lhs, .. }) if lhs.is_builtin("Integer")
+    ));
+    assert!(matches!(
+        check_source("total = SUM([1.5, 2]) DIV 2\n"),
+        Err(TypeError::NonIntegerDivision(found)) if found.is_builtin("Real")
+    ));
+    assert!(matches!(
+        check_source("lowest = MIN([\"a\", \"b\"])\n"),
+        Err(TypeError::NonNumericArray { function, .. }) if function == "MIN"
+    ));
+    assert!(matches!(
+        check_source("total = SUM(5)\n"),
+        Err(TypeError::NonNumericArray { found, .. }) if found.is_builtin("Integer")
+    ));
+}
+
+#[test]
+pub fn test_keeps_the_type_of_variables_read_by_input() {
+    check_source("DECLARE age : INTEGER\nage = input()\nadult = age >= 18\n").unwrap();
+    check_source("age = int(input())\nadult = age >= 18\n").unwrap();
+    // without a type to convert to, what's read is a string
+    assert!(matches!(
+        check_source("age = input()\nadult = age >= 18\n"),
+        Err(TypeError::MismatchedComparison { lhs, .. }) if lhs.is_builtin("String")
+    ));
+}
+
+#[test]
+pub fn test_checks_comparisons() {
+    check_source("same = 5 == 5.0\n").unwrap();
+    check_source("different = 5 != 5.5\n").unwrap();
+    assert!(matches!(
+        check_source("same = 5 == \"5\"\n"),
+        Err(TypeError::MismatchedComparison { .. })
+    ));
+}
+
+#[test]
+pub fn test_checks_string_switch() {
+    check_source(
+        "command = input()\nswitch command:\n    case \"start\":\n        print(1)\n    case \"stop\":\n        print(2)\nendswitch\n",
+    )
+    .unwrap();
+    // the subject's type isn't known here, but the cases still have to agree with each other
+    assert!(matches!(
+        check_source(
+            "function run(command)\n    switch command:\n        case \"start\":\n            print(1)\n        case 2:\n            print(2)\n    endswitch\n    return 0\nendfunction\n"
+        ),
+        Err(TypeError::MismatchedSwitchCase { .. })
+    ));
+}
+
+#[test]
+pub fn test_infers_comparison_as_boolean() {
+    check_source(
+        r#"
+        function big(x) returns BOOLEAN
+            flag = x > 3
+            return flag
+        endfunction
+    "#,
+    )
+    .unwrap();
+    assert!(matches!(
+        check_source(
+            r#"
+        function big(x) returns INTEGER
+            flag = x > 3
+            return flag
+        endfunction
+    "#
+        ),
+        Err(TypeError::MismatchedReturnType { .. })
+    ));
+}
+
+#[test]
+pub fn test_checks_constant_indices_of_const_sized_arrays() {
+    check_source("const N = 5\narray names[N]\nprint(names[N - 1])\n").unwrap();
+    assert!(matches!(
+        check_source("const N = 5\narray names[N]\nprint(names[N])\n"),
+        Err(TypeError::IndexOutOfBounds {
+            index: 5,
+            size: 5,
+            ..
+        })
+    ));
+    // the index isn't known until the program is run, so it can't be checked
+    check_source("const N = 5\narray names[N]\ni = input()\nprint(names[i])\n").unwrap();
+    // a constant declared in a function isn't visible outside of it
+    check_source(
+        "function f()\n    const N = 1\n    return N\nendfunction\nconst M = N\narray names[M]\nprint(names[3])\n",
+    )
+    .unwrap();
+}
+
+#[test]
+pub fn test_requires_boolean_logic_operands() {
+    check_source("a = 1\nb = 2\nboth = a > 0 AND b > 0\n").unwrap();
+    check_source("a = 1\nneither = NOT (a > 0 OR a < 0)\n").unwrap();
+    match check_source("both = 1 AND 2\n") {
+        Err(error @ TypeError::NonBooleanOperand { .. }) => assert_eq!(
+            error.to_string(),
+            "`AND` only works on true or false values, but `1` is Integer"
+        ),
+        result => panic!("expected a NonBooleanOperand error, found {:?}", result),
+    }
+    assert!(matches!(
+        check_source("a = 1\nflag = NOT a\n"),
+        Err(TypeError::NonBooleanOperand { .. })
+    ));
+}
+
+#[test]
+pub fn test_infers_exponents_as_real() {
+    check_source("function big() returns REAL\n    return 1e10\nendfunction\n").unwrap();
+    assert!(matches!(
+        check_source("function big() returns INTEGER\n    return 1e10\nendfunction\n"),
+        Err(TypeError::MismatchedReturnType { .. })
+    ));
+}
+
+#[test]
+pub fn test_infers_booleans() {
+    check_source("function done() returns BOOLEAN\n    return false\nendfunction\n").unwrap();
+    check_source("flag = true AND NOT false\n").unwrap();
+}
+
+#[test]
+pub fn test_infers_calls_to_functions_defined_later() {
+    check_source(
+        r#"
+        total = half(4) + 1
+        function half(x) returns REAL
+            return x / 2
+        endfunction
+    "#,
+    )
+    .unwrap();
+    // the call's type comes from the definition below it, so it can't be compared to a string
+    assert!(matches!(
+        check_source(
+            r#"
+        if half(4) == "two" then
+            print("two")
+        endif
+        function half(x) returns REAL
+            return x / 2
+        endfunction
+    "#
+        ),
+        Err(TypeError::MismatchedComparison { .. })
+    ));
+    // and a function can return the result of one defined after it
+    assert!(matches!(
+        check_source(
+            r#"
+        function count() returns INTEGER
+            return name()
+        endfunction
+        function name() returns STRING
+            return "count"
+        endfunction
+    "#
+        ),
+        Err(TypeError::MismatchedReturnType { .. })
+    ));
+}
+
+#[test]
+pub fn test_requires_a_return_on_every_path() {
+    check_source(
+        r#"
+        function sign(x) returns INTEGER
+            if x > 0 then
+                return 1
+            elseif x < 0 then
+                return -1
+            else
+                return 0
+            endif
+        endfunction
+    "#,
+    )
+    .unwrap();
+    match check_source(
+        r#"
+        function sign(x) returns INTEGER
+            if x > 0 then
+                return 1
+            else
+                print("not positive")
+            endif
+        endfunction
+    "#,
+    ) {
+        Err(error @ TypeError::MissingReturn(_)) => assert_eq!(
+            error.to_string(),
+            "`sign` should return a value, but it can reach the end without returning one"
+        ),
+        result => panic!("expected a MissingReturn error, found {:?}", result),
+    }
+    // a function without a declared return type doesn't have to return anything
+    check_source("function greet()\n    print(\"hi\")\nendfunction\n").unwrap();
+}
+
+#[test]
+pub fn test_expands_aliases() {
+    let mut checker = Checker::default();
+    checker.aliases.insert(
+        "Row".to_string(),
+        Type::Array(Box::new(Type::builtin("Integer").unwrap())),
+    );
+    checker.aliases.insert(
+        "Grid".to_string(),
+        Type::Array(Box::new(Type::named("Row"))),
+    );
+    assert_eq!(
+        checker.resolve("Grid").unwrap(),
+        Type::Array(Box::new(Type::Array(Box::new(
+            Type::builtin("Integer").unwrap()
+        ))))
+    );
+    assert!(matches!(
+        checker.resolve("Table"),
+        Err(TypeError::UnknownType(name)) if name == "Table"
+    ));
+}
+
+/// Aliases can only be declared with a name (not an array or function type) in programs, so this
+/// cycle is put straight into the checker.
+#[test]
+pub fn test_reports_recursive_type() {
+    let mut checker = Checker::default();
+    checker.aliases.insert(
+        "Row".to_string(),
+        Type::Array(Box::new(Type::named("Grid"))),
+    );
+    checker.aliases.insert(
+        "Grid".to_string(),
+        Type::Array(Box::new(Type::named("Row"))),
+    );
+    checker
+        .aliases
+        .insert("Node".to_string(), Type::named("Node"));
+    assert!(matches!(
+        checker.resolve("Grid"),
+        Err(TypeError::RecursiveType(name)) if name == "Grid"
+    ));
+    assert!(matches!(
+        checker.resolve("Node"),
+        Err(TypeError::RecursiveType(name)) if name == "Node"
+    ));
+}
+
+#[test]
+pub fn test_resolves_declared_aliases() {
+    // an alias can be used above where it's declared
+    match check_source(
+        r#"
+        DECLARE total : Score
+        TYPE Score = Points
+        TYPE Points = INTEGER
+        same = total == "ten"
+    "#,
+    ) {
+        Err(TypeError::MismatchedComparison { lhs, rhs }) => {
+            assert!(lhs.is_builtin("Integer"));
+            assert!(rhs.is_builtin("String"));
+        }
+        other => panic!("expected a mismatched comparison, got {:?}", other),
+    }
+    check_source(
+        "function half(x) returns Amount
+    return x / 2
+endfunction
+TYPE Amount = REAL
+",
+    )
+    .unwrap();
+}
+
+#[test]
+pub fn test_reports_recursive_alias_declarations() {
+    assert!(matches!(
+        check_source("TYPE Node = Node
+    "),
+        Err(TypeError::RecursiveType(name)) if name == "Node"
+    ));
+    assert!(matches!(
+        check_source("TYPE Row = Grid
+TYPE Grid = Row
+"),
+        Err(TypeError::RecursiveType(name)) if name == "Row"
+    ));
+    assert!(matches!(
+        check_source("TYPE Table = Widget
+    "),
+        Err(TypeError::UnknownType(name)) if name == "Widget"
+    ));
+}
+
+fn check_spanned(source: &str) -> Result<(), TypeError> {
+    let mut source = source.to_string();
+    check(&parse_spanned(&lex_spanned(&mut source, LexOptions::default()).unwrap()).unwrap())
+}
+
+const ADD: &str = "function add(a, b, c = 0)
+    return a + b + c
+endfunction
+";
+
+#[test]
+pub fn test_reports_extra_arguments_at_the_first_one() {
+    check_spanned(&format!("{}x = add(1, 2)\ny = add(1, 2, 3)\n", ADD)).unwrap();
+    match check_spanned(&format!("{}x = add(1, 2, 3, 4 * 5, 6)\n", ADD)) {
+        Err(error @ TypeError::TooManyArguments { .. }) => {
+            assert_eq!(
+                error.span(),
+                Some(Span::new(Loc::new(4, 18), Loc::new(4, 23)))
+            );
+            assert_eq!(
+                error.to_string(),
+                "`add` has 3 parameters, but this passes 5 arguments"
+            );
+        }
+        other => panic!("expected too many arguments, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn test_reports_missing_arguments_at_the_closing_bracket() {
+    match check_spanned(&format!("{}x = add(1)\n", ADD)) {
+        Err(error @ TypeError::MissingArgument { .. }) => {
+            assert_eq!(
+                error.span(),
+                Some(Span::new(Loc::new(4, 10), Loc::new(4, 11)))
+            );
+            assert!(matches!(
+                error,
+                TypeError::MissingArgument { parameter, .. } if parameter == "b"
+            ));
+        }
+        other => panic!("expected a missing argument, got {:?}", other),
+    }
+    // an argument can be passed by name instead
+    check_spanned(&format!("{}x = add(1, b = 2)\n", ADD)).unwrap();
+}
+
+#[test]
+pub fn test_checks_named_arguments() {
+    check_spanned(&format!("{}x = add(b = 2, a = 1, c = 3)\n", ADD)).unwrap();
+    match check_spanned(&format!("{}x = add(1, 2, a = 3)\n", ADD)) {
+        Err(error @ TypeError::DuplicateArgument { .. }) => {
+            assert_eq!(
+                error.span(),
+                Some(Span::new(Loc::new(4, 15), Loc::new(4, 20)))
+            );
+            assert!(matches!(
+                error,
+                TypeError::DuplicateArgument { parameter, .. } if parameter == "a"
+            ));
+        }
+        other => panic!("expected a duplicate argument, got {:?}", other),
+    }
+    // `b` is left out, so its default value is passed by position in its place
+    let defaults = "function f(a, b = 1, c = 2)\n    return a + b + c\nendfunction\n";
+    check_spanned(&format!("{}x = f(0, c = 5)\n", defaults)).unwrap();
+    let defaults = "function f(a, b = a, c = 2)\n    return a + b + c\nendfunction\n";
+    check_spanned(&format!("{}x = f(0, b = 1)\n", defaults)).unwrap();
+    assert!(matches!(
+        check_spanned(&format!("{}x = f(0, c = 5)\n", defaults)),
+        Err(TypeError::SkippedParameter { parameter, .. }) if parameter == "b"
+    ));
+}
+
+/// Parses, checks and folds the constants in a program.
+fn fold_source(source: &str) -> Vec<Statement> {
+    let mut source = source.to_string();
+    let mut program = parse(lex(&mut source).unwrap()).unwrap();
+    check(&program).unwrap();
+    fold_constants(&mut program);
+    program
+}
+
+/// The expression assigned by an assignment statement.
+fn assigned(statement: &Statement) -> &Expression {
+    match statement {
+        Statement::AssignmentStatement(assignment) => &assignment.expression,
+        other => panic!("expected an assignment, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn test_folds_constants() {
+    let program = fold_source("const N = 3\nx = N * 2\ny = x * N\n");
+    assert_eq!(assigned(&program[1]), &Expression::Integer(6));
+    // `x` isn't a constant, so only `N` is substituted
+    assert_eq!(
+        assigned(&program[2]),
+        &Expression::BinaryOp {
+            operator: Operator::Times,
+            lhs: Box::new(Expression::Ident("x".to_string())),
+            rhs: Box::new(Expression::Integer(3)),
+        }
+    );
+}
+
+#[test]
+pub fn test_does_not_fold_constants_outside_their_function() {
+    let program = fold_source(
+        r#"
+        const M = 2
+        function f(M)
+            const N = 3
+            return N * M
+        endfunction
+        x = N * M
+        "#,
+    );
+    match &program[1] {
+        Statement::FunctionDefinition(function) => match &function.block[1] {
+            // the parameter hides the outer `M`
+            Statement::ReturnStatement(Expression::BinaryOp { lhs, rhs, .. }) => {
+                assert_eq!(**lhs, Expression::Integer(3));
+                assert_eq!(**rhs, Expression::Ident("M".to_string()));
+            }
+            other => panic!("expected a return, got {:?}", other),
+        },
+        other => panic!("expected a function, got {:?}", other),
+    }
+    match assigned(&program[2]) {
+        Expression::BinaryOp { lhs, rhs, .. } => {
+            assert_eq!(**lhs, Expression::Ident("N".to_string()));
+            assert_eq!(**rhs, Expression::Integer(2));
+        }
+        other => panic!("expected a multiplication, got {:?}", other),
+    }
+}
@@ -0,0 +1,138 @@
+//! Generates WebAssembly text (WAT) from the AST.
+//!
+//! This is the "hard" path mentioned in the Javascript code generator; it's useful for people who
+//! want to run compiled programs outside of a Javascript engine. For the moment only integer
+//! arithmetic is supported, so every value is an `i32`.
+
+#[cfg(test)]
+mod unit_tests;
+
+use crate::lexer::Operator;
+use crate::parser::{Expression, Statement};
+use std::convert::TryFrom;
+use thiserror::Error as ThisError;
+
+#[derive(ThisError, Debug)]
+/// An error encountered while generating WebAssembly text.
+pub enum WasmError {
+    #[error("this can't be compiled to WebAssembly yet: {0}")]
+    Unsupported(&'static str),
+    /// Every value is an `i32`, so a bigger integer literal would silently wrap around.
+    #[error("{0} is too big to be stored in a 32-bit integer")]
+    IntegerOutOfRange(i64),
+}
+
+/// Outputs WebAssembly text from the AST.
+trait WasmCodegen {
+    /// Output the WebAssembly text for this AST node.
+    fn output(&self) -> Result<String, WasmError>;
+}
+
+/// Outputs a WebAssembly module for a whole program.
+///
+/// Each function in the program has to consist of a single `return` statement, and there can't be
+/// anything outside of functions.
+pub fn module(program: &[Statement]) -> Result<String, WasmError> {
+    let mut output = "(module".to_string();
+    for statement in program {
+        let function_definition = match statement {
+            Statement::FunctionDefinition(function_definition) => function_definition,
+            _ => return Err(WasmError::Unsupported("statements outside of functions")),
+        };
+        let body = match &function_definition.block[..] {
+            [Statement::ReturnStatement(body)] => body,
+            _ => {
+                return Err(WasmError::Unsupported(
+                    "functions with more than a return statement",
+                ))
+            }
+        };
+        let mut parameters = vec![];
+        for parameter in &function_definition.parameters {
+            if parameter.default.is_some() {
+                return Err(WasmError::Unsupported("default arguments"));
+            }
+            parameters.push(parameter.name.clone());
+        }
+        output.push_str("\n  ");
+        output.push_str(
+            &function(&function_definition.name, &parameters, body)?.replace('\n', "\n  "),
+        );
+    }
+    output.push_str(")\n");
+    Ok(output)
+}
+
+/// Outputs an (exported) WebAssembly function which takes `i32` parameters and returns the `i32`
+/// that `body` evaluates to.
+pub fn function(name: &str, parameters: &[String], body: &Expression) -> Result<String, WasmError> {
+    let mut output = format!("(func ${} (export \"{}\")", name, name);
+    for parameter in parameters {
+        output.push_str(&format!(" (param ${} i32)", parameter));
+    }
+    output.push_str(" (result i32)\n  ");
+    output.push_str(&body.output()?);
+    output.push(')');
+    Ok(output)
+}
+
+impl WasmCodegen for Expression {
+    fn output(&self) -> Result<String, WasmError> {
+        Ok(match self {
+            Expression::Integer(integer) => match i32::try_from(*integer) {
+                Ok(integer) => format!("(i32.const {})", integer),
+                Err(_) => return Err(WasmError::IntegerOutOfRange(*integer)),
+            },
+            Expression::Boolean(boolean) => format!("(i32.const {})", i32::from(*boolean)),
+            Expression::Ident(ident) => format!("(local.get ${})", ident),
+            Expression::FunctionCall { named_args, .. } if !named_args.is_empty() => {
+                return Err(WasmError::Unsupported("named arguments"))
+            }
+            Expression::FunctionCall { name, args, .. } => {
+                let mut output = format!("(call ${}", name);
+                for arg in args {
+                    output.push(' ');
+                    output.push_str(&arg.output()?);
+                }
+                output.push(')');
+                output
+            }
+            Expression::UnaryOp { operator, operand } => match operator {
+                Operator::Minus => format!("(i32.sub (i32.const 0) {})", operand.output()?),
+                Operator::Not => format!("(i32.eqz {})", operand.output()?),
+                _ => return Err(WasmError::Unsupported("this unary operator")),
+            },
+            Expression::BinaryOp { operator, lhs, rhs } => {
+                let instruction = match operator {
+                    Operator::Plus => "i32.add",
+                    Operator::Minus => "i32.sub",
+                    Operator::Times => "i32.mul",
+                    Operator::IntegerDivide => "i32.div_s",
+                    // `/` always gives a real number, which can't be stored in an `i32`
+                    Operator::Divide => return Err(WasmError::Unsupported("real division")),
+                    Operator::Modulo => "i32.rem_s",
+                    Operator::Equals | Operator::Comparison => "i32.eq",
+                    Operator::NotEquals => "i32.ne",
+                    Operator::LessThan => "i32.lt_s",
+                    Operator::GreaterThan => "i32.gt_s",
+                    Operator::LessThanOrEqual => "i32.le_s",
+                    Operator::GreaterThanOrEqual => "i32.ge_s",
+                    Operator::And => "i32.and",
+                    Operator::Or => "i32.or",
+                    _ => return Err(WasmError::Unsupported("this binary operator")),
+                };
+                format!("({} {} {})", instruction, lhs.output()?, rhs.output()?)
+            }
+            Expression::Float(_) => return Err(WasmError::Unsupported("floats")),
+            Expression::String(_) | Expression::InterpolatedString(_) => {
+                return Err(WasmError::Unsupported("strings"))
+            }
+            Expression::Array(_) | Expression::Index { .. } | Expression::Slice { .. } => {
+                return Err(WasmError::Unsupported("arrays"))
+            }
+            Expression::Member { .. } | Expression::MethodCall { .. } => {
+                return Err(WasmError::Unsupported("fields and methods"))
+            }
+        })
+    }
+}
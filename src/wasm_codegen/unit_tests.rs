@@ -0,0 +1,99 @@
+//! Unit tests for the WebAssembly text generator.
+
+use crate::lexer::{lex, Operator, Punctuation, Token};
+use crate::parser::{parse, parse_expression};
+use crate::wasm_codegen::{function, module, WasmError};
+
+/// Lexes, parses and outputs a whole module.
+fn output_module(source: &str) -> Result<String, WasmError> {
+    let mut source = source.to_string();
+    module(&parse(lex(source.as_mut_str()).unwrap()).unwrap())
+}
+
+#[test]
+pub fn test_outputs_multiply_function() {
+    let body = parse_expression(&[
+        Token::Ident("x".to_string()),
+        Token::Operator(Operator::Times),
+        Token::Ident("y".to_string()),
+    ])
+    .unwrap();
+    let output = function("multiply", &["x".to_string(), "y".to_string()], &body).unwrap();
+    assert!(output.contains("(func $multiply"));
+    assert!(output.contains("(param $x i32) (param $y i32) (result i32)"));
+    assert!(output.contains("(i32.mul (local.get $x) (local.get $y))"));
+}
+
+#[test]
+pub fn test_rejects_strings() {
+    let body = parse_expression(&[
+        Token::Punctuation(Punctuation::Quote),
+        Token::String("x".to_string()),
+        Token::Punctuation(Punctuation::Quote),
+    ])
+    .unwrap();
+    assert!(function("f", &[], &body).is_err());
+}
+
+#[test]
+pub fn test_only_integer_division_is_supported() {
+    let divide = |operator| {
+        parse_expression(&[
+            Token::Ident("x".to_string()),
+            Token::Operator(operator),
+            Token::Ident("y".to_string()),
+        ])
+        .unwrap()
+    };
+    let output = function(
+        "f",
+        &["x".to_string(), "y".to_string()],
+        &divide(Operator::IntegerDivide),
+    )
+    .unwrap();
+    assert!(output.contains("(i32.div_s (local.get $x) (local.get $y))"));
+    assert!(matches!(
+        function(
+            "f",
+            &["x".to_string(), "y".to_string()],
+            &divide(Operator::Divide)
+        ),
+        Err(WasmError::Unsupported("real division"))
+    ));
+}
+
+#[test]
+pub fn test_rejects_integers_which_do_not_fit_in_an_i32() {
+    let output = output_module("function f()\n    return 2147483647 + -2147483648\nendfunction\n");
+    assert!(output
+        .unwrap()
+        .contains("(i32.add (i32.const 2147483647) (i32.const -2147483648))"));
+    assert!(matches!(
+        output_module("function f()\n    return 2147483648\nendfunction\n"),
+        Err(WasmError::IntegerOutOfRange(2147483648))
+    ));
+}
+
+#[test]
+pub fn test_rejects_power() {
+    assert!(matches!(
+        output_module("function square(x)\n    return x ^ 2\nendfunction\n"),
+        Err(WasmError::Unsupported("this binary operator"))
+    ));
+}
+
+#[test]
+pub fn test_rejects_functions_which_are_not_a_single_return() {
+    assert!(matches!(
+        output_module("function f(x)\n    y = x + 1\n    return y\nendfunction\n"),
+        Err(WasmError::Unsupported(
+            "functions with more than a return statement"
+        ))
+    ));
+    assert!(matches!(
+        output_module("function f(x)\n    print(x)\nendfunction\n"),
+        Err(WasmError::Unsupported(
+            "functions with more than a return statement"
+        ))
+    ));
+}